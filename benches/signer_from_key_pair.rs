@@ -0,0 +1,83 @@
+//! Compare creating a signer from a PEM per signature with creating it from a parsed key pair.
+//!
+//! Run with `cargo bench --bench signer_from_key_pair`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use josekit::jws::{JwsSigner, ES256, RS256};
+
+const SIGNATURES: usize = 2000;
+
+fn main() {
+    let rsa_pem = load_file("pem/RSA_2048bit_private.pem");
+    let rsa_key_pair = RS256.key_pair_from_pem(&rsa_pem).unwrap();
+
+    let from_pem = measure(|| {
+        for i in 0..SIGNATURES {
+            let signer = RS256.signer_from_pem(&rsa_pem).unwrap();
+            signer.sign(message(i).as_bytes()).unwrap();
+        }
+    });
+    let from_key_pair = measure(|| {
+        for i in 0..SIGNATURES {
+            let signer = RS256.signer_from_key_pair(&rsa_key_pair).unwrap();
+            signer.sign(message(i).as_bytes()).unwrap();
+        }
+    });
+    report("RS256 from pem", from_pem);
+    report("RS256 from key pair", from_key_pair);
+
+    let ec_pem = load_file("pem/EC_P-256_private.pem");
+    let ec_key_pair = ES256.key_pair_from_pem(&ec_pem).unwrap();
+
+    let from_pem = measure(|| {
+        for i in 0..SIGNATURES {
+            let signer = ES256.signer_from_pem(&ec_pem).unwrap();
+            signer.sign(message(i).as_bytes()).unwrap();
+        }
+    });
+    let from_key_pair = measure(|| {
+        for i in 0..SIGNATURES {
+            let signer = ES256.signer_from_key_pair(&ec_key_pair).unwrap();
+            signer.sign(message(i).as_bytes()).unwrap();
+        }
+    });
+    report("ES256 from pem", from_pem);
+    report("ES256 from key pair", from_key_pair);
+}
+
+fn message(i: usize) -> String {
+    format!("eyJhbGciOiJSUzI1NiJ9.eyJzdWIiOiJ1c2VyJ{}In0", i)
+}
+
+fn measure(f: impl Fn()) -> Duration {
+    // Warm up once, then take the best of a few runs.
+    f();
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:<20} {:>10.1} signatures/s ({:?} for {} signatures)",
+        name,
+        SIGNATURES as f64 / elapsed.as_secs_f64(),
+        elapsed,
+        SIGNATURES
+    );
+}
+
+fn load_file(path: &str) -> Vec<u8> {
+    let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    pb.push("data");
+    pb.push(path);
+    fs::read(&pb).unwrap()
+}
@@ -0,0 +1,210 @@
+#!/usr/bin/env python3
+# Generate JWE JSON serialization fixtures without josekit.
+#
+# The JWEs are built from the primitives of pyca/cryptography by following
+# RFC 7516 and RFC 7518 directly, so they are independent of this crate.
+#
+#   pip install cryptography
+#   python3 data/jwe_json/generate.py
+
+import base64
+import hashlib
+import hmac
+import json
+import os
+import struct
+
+from cryptography.hazmat.primitives import hashes, padding
+from cryptography.hazmat.primitives.asymmetric import ec, rsa
+from cryptography.hazmat.primitives.asymmetric import padding as asym_padding
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.ciphers.aead import AESGCM
+from cryptography.hazmat.primitives.keywrap import aes_key_wrap
+
+DATA_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..")
+PAYLOAD = b'{"iss":"joe","exp":1300819380,"http://example.com/is_root":true}'
+
+
+def b64e(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode("ascii")
+
+
+def b64d(data):
+    return base64.urlsafe_b64decode(data + "=" * (-len(data) % 4))
+
+
+def to_int(data):
+    return int.from_bytes(b64d(data), "big")
+
+
+def load_jwk(name):
+    with open(os.path.join(DATA_DIR, "jwk", name)) as f:
+        return json.load(f)
+
+
+def rsa_public_key(jwk):
+    return rsa.RSAPublicNumbers(to_int(jwk["e"]), to_int(jwk["n"])).public_key()
+
+
+def ec_public_key(jwk):
+    return ec.EllipticCurvePublicNumbers(
+        to_int(jwk["x"]), to_int(jwk["y"]), ec.SECP256R1()
+    ).public_key()
+
+
+def encode_protected(header):
+    return b64e(json.dumps(header, separators=(",", ":")).encode("utf-8"))
+
+
+def full_aad(protected_b64, aad_b64):
+    # RFC 7516 5.1 step 14
+    if aad_b64 is None:
+        return protected_b64.encode("ascii")
+    return (protected_b64 + "." + aad_b64).encode("ascii")
+
+
+def encrypt_a256gcm(key, aad):
+    iv = os.urandom(12)
+    sealed = AESGCM(key).encrypt(iv, PAYLOAD, aad)
+    return iv, sealed[:-16], sealed[-16:]
+
+
+def encrypt_a128cbc_hs256(key, aad):
+    mac_key, enc_key = key[:16], key[16:]
+    iv = os.urandom(16)
+    padder = padding.PKCS7(128).padder()
+    padded = padder.update(PAYLOAD) + padder.finalize()
+    encryptor = Cipher(algorithms.AES(enc_key), modes.CBC(iv)).encryptor()
+    ciphertext = encryptor.update(padded) + encryptor.finalize()
+    al = struct.pack(">Q", len(aad) * 8)
+    tag = hmac.new(mac_key, aad + iv + ciphertext + al, hashlib.sha256).digest()[:16]
+    return iv, ciphertext, tag
+
+
+def rsa_oaep_wrap(jwk, cek):
+    oaep = asym_padding.OAEP(
+        mgf=asym_padding.MGF1(algorithm=hashes.SHA1()),
+        algorithm=hashes.SHA1(),
+        label=None,
+    )
+    return rsa_public_key(jwk).encrypt(cek, oaep)
+
+
+def ecdh_es_agree(jwk, enc, key_len):
+    ephemeral = ec.generate_private_key(ec.SECP256R1())
+    z = ephemeral.exchange(ec.ECDH(), ec_public_key(jwk))
+
+    # Concat KDF (RFC 7518 4.6.2) without apu and apv
+    alg_id = enc.encode("ascii")
+    other_info = (
+        struct.pack(">I", len(alg_id))
+        + alg_id
+        + struct.pack(">I", 0)
+        + struct.pack(">I", 0)
+        + struct.pack(">I", key_len * 8)
+    )
+    cek = hashlib.sha256(struct.pack(">I", 1) + z + other_info).digest()[:key_len]
+
+    numbers = ephemeral.public_key().public_numbers()
+    epk = {
+        "kty": "EC",
+        "crv": "P-256",
+        "x": b64e(numbers.x.to_bytes(32, "big")),
+        "y": b64e(numbers.y.to_bytes(32, "big")),
+    }
+    return cek, epk
+
+
+def write(name, value):
+    with open(os.path.join(DATA_DIR, "jwe_json", name), "w") as f:
+        json.dump(value, f, indent=2)
+        f.write("\n")
+
+
+def rsa_oaep_a256gcm():
+    rsa_jwk = load_jwk("RSA_public.jwk")
+    oct_jwk = load_jwk("oct_128bit_private.jwk")
+
+    # general: two recipients, unprotected header and aad
+    cek = os.urandom(32)
+    protected = encode_protected({"enc": "A256GCM"})
+    aad = b64e(b"external aad")
+    iv, ciphertext, tag = encrypt_a256gcm(cek, full_aad(protected, aad))
+    write(
+        "RSA-OAEP_A256GCM_general.json",
+        {
+            "protected": protected,
+            "unprotected": {"typ": "JWT"},
+            "recipients": [
+                {
+                    "header": {"alg": "A128KW", "kid": "oct-key"},
+                    "encrypted_key": b64e(aes_key_wrap(b64d(oct_jwk["k"]), cek)),
+                },
+                {
+                    "header": {"alg": "RSA-OAEP", "kid": "rsa-key"},
+                    "encrypted_key": b64e(rsa_oaep_wrap(rsa_jwk, cek)),
+                },
+            ],
+            "aad": aad,
+            "iv": b64e(iv),
+            "ciphertext": b64e(ciphertext),
+            "tag": b64e(tag),
+        },
+    )
+
+    # flattened: alg in protected and kid in header
+    cek = os.urandom(32)
+    protected = encode_protected({"alg": "RSA-OAEP", "enc": "A256GCM"})
+    iv, ciphertext, tag = encrypt_a256gcm(cek, full_aad(protected, None))
+    write(
+        "RSA-OAEP_A256GCM_flattened.json",
+        {
+            "protected": protected,
+            "header": {"kid": "rsa-key"},
+            "encrypted_key": b64e(rsa_oaep_wrap(rsa_jwk, cek)),
+            "iv": b64e(iv),
+            "ciphertext": b64e(ciphertext),
+            "tag": b64e(tag),
+        },
+    )
+
+
+def ecdh_es_a128cbc_hs256():
+    ec_jwk = load_jwk("EC_P-256_public.jwk")
+
+    # general: alg and epk in the recipient header
+    cek, epk = ecdh_es_agree(ec_jwk, "A128CBC-HS256", 32)
+    protected = encode_protected({"enc": "A128CBC-HS256"})
+    iv, ciphertext, tag = encrypt_a128cbc_hs256(cek, full_aad(protected, None))
+    write(
+        "ECDH-ES_A128CBC-HS256_general.json",
+        {
+            "protected": protected,
+            "recipients": [{"header": {"alg": "ECDH-ES", "kid": "ec-key", "epk": epk}}],
+            "iv": b64e(iv),
+            "ciphertext": b64e(ciphertext),
+            "tag": b64e(tag),
+        },
+    )
+
+    # flattened: alg and epk in protected, unprotected header and aad
+    cek, epk = ecdh_es_agree(ec_jwk, "A128CBC-HS256", 32)
+    protected = encode_protected({"alg": "ECDH-ES", "enc": "A128CBC-HS256", "epk": epk})
+    aad = b64e(b"external aad")
+    iv, ciphertext, tag = encrypt_a128cbc_hs256(cek, full_aad(protected, aad))
+    write(
+        "ECDH-ES_A128CBC-HS256_flattened.json",
+        {
+            "protected": protected,
+            "unprotected": {"typ": "JWT"},
+            "aad": aad,
+            "iv": b64e(iv),
+            "ciphertext": b64e(ciphertext),
+            "tag": b64e(tag),
+        },
+    )
+
+
+if __name__ == "__main__":
+    rsa_oaep_a256gcm()
+    ecdh_es_a128cbc_hs256()
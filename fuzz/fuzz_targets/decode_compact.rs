@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::time::SystemTime;
+
+use josekit::jwe::Dir;
+use josekit::jws::HS256;
+use josekit::jwt;
+
+// Any input must be rejected or accepted without a panic.
+fuzz_target!(|data: &[u8]| {
+    let verifier = HS256
+        .verifier_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")
+        .unwrap();
+    let decrypter = Dir.decrypter_from_bytes(b"0123456789ABCDEF").unwrap();
+
+    let _ = jwt::decode_header(data);
+    let _ = jwt::is_expired(data, &SystemTime::now());
+    let _ = jwt::decode_unsecured(data);
+    let _ = jwt::decode_with_verifier(data, &verifier);
+    let _ = jwt::decode_with_decrypter(data, &decrypter);
+});
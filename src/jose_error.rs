@@ -5,6 +5,9 @@ pub enum JoseError {
     #[error("Unsupported signature algorithm: {0}")]
     UnsupportedSignatureAlgorithm(#[source] anyhow::Error),
 
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
     #[error("Invalid JWT format: {0}")]
     InvalidJwtFormat(#[source] anyhow::Error),
 
@@ -29,3 +32,182 @@ pub enum JoseError {
     #[error("Invalid signature: {0}")]
     InvalidSignature(#[source] anyhow::Error),
 }
+
+impl JoseError {
+    /// Return the kind of a JWT claim validation error.
+    ///
+    /// None is returned if this error is not caused by a JWT claim validation.
+    pub fn claim_error_kind(&self) -> Option<ClaimErrorKind> {
+        match self {
+            JoseError::InvalidClaim(err) => err.downcast_ref::<ClaimError>().map(|val| val.kind()),
+            _ => None,
+        }
+    }
+
+    /// Return the segment count error of a compact serialization.
+    ///
+    /// None is returned if this error is not caused by the number of segments.
+    pub fn segment_count_error(&self) -> Option<&SegmentCountError> {
+        match self {
+            JoseError::InvalidJwtFormat(err)
+            | JoseError::InvalidJwsFormat(err)
+            | JoseError::InvalidJweFormat(err) => err.downcast_ref::<SegmentCountError>(),
+            _ => None,
+        }
+    }
+
+    /// Return the segment decoding error of a compact serialization.
+    ///
+    /// None is returned if this error is not caused by a malformed segment.
+    pub fn segment_decode_error(&self) -> Option<&SegmentDecodeError> {
+        match self {
+            JoseError::InvalidJwtFormat(err)
+            | JoseError::InvalidJwsFormat(err)
+            | JoseError::InvalidJweFormat(err) => err.downcast_ref::<SegmentDecodeError>(),
+            _ => None,
+        }
+    }
+
+    /// Return the token length error of a JWT decoding.
+    ///
+    /// None is returned if this error is not caused by the length of the token.
+    pub fn token_length_error(&self) -> Option<&TokenLengthError> {
+        match self {
+            JoseError::InvalidJwtFormat(err) => err.downcast_ref::<TokenLengthError>(),
+            _ => None,
+        }
+    }
+
+    /// Return the key policy error of a JWS or JWT verification.
+    ///
+    /// None is returned if this error is not caused by the key policy.
+    pub fn key_policy_error(&self) -> Option<&KeyPolicyError> {
+        match self {
+            JoseError::InvalidJwtFormat(err)
+            | JoseError::InvalidJwsFormat(err)
+            | JoseError::InvalidKeyFormat(err) => err.downcast_ref::<KeyPolicyError>(),
+            _ => None,
+        }
+    }
+
+    /// Return the algorithm confusion error of a JWS or JWT verification.
+    ///
+    /// None is returned if this error is not caused by a algorithm confusion.
+    pub fn algorithm_confusion_error(&self) -> Option<&AlgorithmConfusionError> {
+        match self {
+            JoseError::InvalidJwtFormat(err) | JoseError::InvalidJwsFormat(err) => {
+                err.downcast_ref::<AlgorithmConfusionError>()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Represents a kind of JWT claim validation error.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ClaimErrorKind {
+    /// The token has expired (exp).
+    Expired,
+
+    /// The token is not yet valid (nbf).
+    NotYetValid,
+
+    /// The issuer (iss) is not expected.
+    InvalidIssuer,
+
+    /// The audience (aud) is not expected.
+    InvalidAudience,
+
+    /// A claim does not match the expected value or range.
+    ClaimMismatch,
+
+    /// A required claim is missing.
+    MissingClaim,
+}
+
+/// Represents a JWT claim validation error.
+///
+/// It is the source of `JoseError::InvalidClaim` returned by `JwtPayloadValidator`.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct ClaimError {
+    kind: ClaimErrorKind,
+    message: String,
+}
+
+impl ClaimError {
+    /// Return a new ClaimError.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - a kind of error
+    /// * `message` - a error message
+    pub fn new(kind: ClaimErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Return the kind of error.
+    pub fn kind(&self) -> ClaimErrorKind {
+        self.kind
+    }
+}
+
+/// Represents a error of the number of dot separated segments in a compact serialization.
+///
+/// It is the source of `JoseError::InvalidJwsFormat`, `InvalidJweFormat` or `InvalidJwtFormat`.
+/// The expected count is 3 for JWS (and JWT) and 5 for JWE.
+#[derive(Error, Debug, Eq, PartialEq, Copy, Clone)]
+#[error("The compact serialization must have {expected} parts separated by dots: {found}")]
+pub struct SegmentCountError {
+    pub expected: usize,
+    pub found: usize,
+}
+
+/// Represents a error of a segment in a compact serialization that cannot be decoded.
+///
+/// It is the source of `JoseError::InvalidJwsFormat`, `InvalidJweFormat` or `InvalidJwtFormat`.
+/// The index starts from 0 (the header segment), and the reason is a message of
+/// the base64url or JSON decoding error.
+#[derive(Error, Debug, Eq, PartialEq, Clone)]
+#[error("The {name} segment ({index}) is invalid: {reason}")]
+pub struct SegmentDecodeError {
+    pub index: usize,
+    pub name: &'static str,
+    pub reason: String,
+}
+
+/// Represents a error of a token whose alg header claim is a symmetric (HMAC) algorithm
+/// when the verifier has a asymmetric public key, or vice versa.
+///
+/// It is the source of `JoseError::InvalidJwsFormat` or `InvalidJwtFormat`, and it is
+/// returned before any signature verification is done.
+#[derive(Error, Debug, Eq, PartialEq, Clone)]
+#[error("The alg header claim {found} cannot be verified by a {expected} key.")]
+pub struct AlgorithmConfusionError {
+    pub expected: String,
+    pub found: String,
+}
+
+/// Represents a error of a token that is longer than the limit of `JwtContext::set_max_token_len`.
+///
+/// It is the source of `JoseError::InvalidJwtFormat`, and it is returned before any parsing.
+#[derive(Error, Debug, Eq, PartialEq, Copy, Clone)]
+#[error("The token length must be {max} or less: {found}")]
+pub struct TokenLengthError {
+    pub max: usize,
+    pub found: usize,
+}
+
+/// Represents a error of a key that violates the key policy of `JwsContext`
+/// (the minimum RSA key length and the allowed curves).
+///
+/// It is the source of `JoseError::InvalidKeyFormat` returned by `JwsContext::check_jwk`,
+/// or of `JoseError::InvalidJwsFormat` or `InvalidJwtFormat` when a verifier violates it.
+#[derive(Error, Debug, Eq, PartialEq, Clone)]
+#[error("The key violates the key policy: {reason}")]
+pub struct KeyPolicyError {
+    pub reason: String,
+}
@@ -1,4 +1,6 @@
-use crate::Value;
+use anyhow::anyhow;
+
+use crate::{JoseError, Map, Value};
 
 pub trait JoseHeader: Send + Sync {
     // Return claim count.
@@ -19,3 +21,41 @@ impl Clone for Box<dyn JoseHeader> {
         self.box_clone()
     }
 }
+
+/// Represents a header of this crate whose claims can be merged with another header.
+///
+/// This trait is sealed, so it is only implemented by the header types of this crate.
+pub trait JoseHeaderMerge: JoseHeader + private::Sealed {
+    /// Return values for all header claims.
+    fn to_map(&self) -> Map<String, Value>;
+
+    /// Return values for header claims merged with another header
+    /// (e.g. a protected header and a unprotected header of JSON serialization).
+    ///
+    /// The claims of this header come first. A duplicate header claim in the both
+    /// headers is rejected because RFC 7515 and RFC 7516 forbid it, and the error is
+    /// InvalidJwsFormat for a JWS header and InvalidJweFormat for a JWE header.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - a header to be merged
+    fn merged_with(&self, other: &dyn JoseHeaderMerge) -> Result<Map<String, Value>, JoseError> {
+        let mut map = self.to_map();
+        for (key, value) in other.to_map() {
+            if map.contains_key(&key) {
+                return Err(self.format_error(anyhow!("A duplicate key exists: {}", key)));
+            }
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+pub(crate) mod private {
+    use crate::JoseError;
+
+    pub trait Sealed {
+        /// Return a error of the format of this header.
+        fn format_error(&self, err: anyhow::Error) -> JoseError;
+    }
+}
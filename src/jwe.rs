@@ -10,8 +10,10 @@ mod jwe_header;
 mod jwe_header_set;
 pub mod zip;
 
+use anyhow::bail;
 use once_cell::sync::Lazy;
 
+use crate::jwk::Jwk;
 use crate::JoseError;
 
 pub use crate::jwe::jwe_algorithm::JweAlgorithm;
@@ -56,6 +58,31 @@ pub use RsaesJweAlgorithm::RsaOaep512 as RSA_OAEP_512;
 
 static DEFAULT_CONTEXT: Lazy<JweContext> = Lazy::new(|| JweContext::new());
 
+/// Return the JWE alg header claim values that are supported by this crate.
+pub fn supported_key_algorithms() -> &'static [&'static str] {
+    JweContext::SUPPORTED_KEY_ALGORITHMS
+}
+
+/// Return the JWE enc header claim values that are supported by this crate.
+///
+/// A content encryption that is added by `JweContext::add_content_encryption` is not contained.
+pub fn supported_content_encryptions() -> &'static [&'static str] {
+    JweContext::SUPPORTED_CONTENT_ENCRYPTIONS
+}
+
+/// Return a content encryption algorithm that is supported by this crate for a enc header
+/// claim value (e.g. "A256GCM").
+///
+/// It is useful to select a content encryption from a configuration. The name of the
+/// returned value can be set to `JweHeader::set_content_encryption`.
+///
+/// # Arguments
+///
+/// * `name` - a content encryption header claim value
+pub fn content_encryption_by_name(name: &str) -> Option<&'static dyn JweContentEncryption> {
+    DEFAULT_CONTEXT.get_content_encryption(name)
+}
+
 /// Return a representation of the data that is formatted by compact serialization.
 ///
 /// # Arguments
@@ -240,20 +267,168 @@ where
     DEFAULT_CONTEXT.deserialize_json_with_selector(input, selector)
 }
 
+/// Deserialize the input that is formatted by general or flattened json serialization,
+/// and return the index of the recipient that is decrypted.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `decrypter` - The JWE decrypter.
+pub fn deserialize_general_json(
+    input: &str,
+    decrypter: &dyn JweDecrypter,
+) -> Result<(Vec<u8>, JweHeader, usize), JoseError> {
+    DEFAULT_CONTEXT.deserialize_general_json(input, decrypter)
+}
+
+/// Deserialize the input that is formatted by general or flattened json serialization,
+/// and return the index of the recipient that is decrypted.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `selector` - a function for selecting the decrypting algorithm.
+pub fn deserialize_general_json_with_selector<'a, F>(
+    input: &str,
+    selector: F,
+) -> Result<(Vec<u8>, JweHeader, usize), JoseError>
+where
+    F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
+{
+    DEFAULT_CONTEXT.deserialize_general_json_with_selector(input, selector)
+}
+
+/// Add a recipient to the input that is formatted by general or flattened json serialization,
+/// and return a representation that is formatted by general json serialization.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `decrypter` - The JWE decrypter of a existing recipient.
+/// * `new_header` - The JWE unprotected header claims for the new recipient.
+/// * `new_encrypter` - The JWE encrypter for the new recipient.
+pub fn add_recipient(
+    input: impl AsRef<[u8]>,
+    decrypter: &dyn JweDecrypter,
+    new_header: Option<&JweHeader>,
+    new_encrypter: &dyn JweEncrypter,
+) -> Result<String, JoseError> {
+    DEFAULT_CONTEXT.add_recipient(input, decrypter, new_header, new_encrypter)
+}
+
+/// Return a decrypter that is selected by the parameters of a JWK.
+///
+/// The algorithm is selected by the alg parameter. If it is absent,
+/// ECDH-ES is inferred for a EC key or a OKP key of X25519 or X448.
+///
+/// # Arguments
+///
+/// * `jwk` - A private key that is formatted by a JWK.
+pub fn decrypter_from_jwk(jwk: &Jwk) -> Result<Box<dyn JweDecrypter>, JoseError> {
+    (|| -> anyhow::Result<Box<dyn JweDecrypter>> {
+        let alg = match jwk.algorithm() {
+            Some(val) => val,
+            None => match (jwk.key_type(), jwk.curve()) {
+                ("EC", _) | ("OKP", Some("X25519")) | ("OKP", Some("X448")) => "ECDH-ES",
+                (kty, crv) => {
+                    bail!(
+                        "A JWE algorithm cannot be inferred from the JWK: kty={}, crv={}",
+                        kty,
+                        crv.unwrap_or("none")
+                    )
+                }
+            },
+        };
+
+        #[allow(deprecated)]
+        let decrypter: Box<dyn JweDecrypter> = match alg {
+            "dir" => Box::new(Dir.decrypter_from_jwk(jwk)?),
+            "ECDH-ES" => Box::new(ECDH_ES.decrypter_from_jwk(jwk)?),
+            "ECDH-ES+A128KW" => Box::new(ECDH_ES_A128KW.decrypter_from_jwk(jwk)?),
+            "ECDH-ES+A192KW" => Box::new(ECDH_ES_A192KW.decrypter_from_jwk(jwk)?),
+            "ECDH-ES+A256KW" => Box::new(ECDH_ES_A256KW.decrypter_from_jwk(jwk)?),
+            "A128KW" => Box::new(A128KW.decrypter_from_jwk(jwk)?),
+            "A192KW" => Box::new(A192KW.decrypter_from_jwk(jwk)?),
+            "A256KW" => Box::new(A256KW.decrypter_from_jwk(jwk)?),
+            "A128GCMKW" => Box::new(A128GCMKW.decrypter_from_jwk(jwk)?),
+            "A192GCMKW" => Box::new(A192GCMKW.decrypter_from_jwk(jwk)?),
+            "A256GCMKW" => Box::new(A256GCMKW.decrypter_from_jwk(jwk)?),
+            "PBES2-HS256+A128KW" => Box::new(PBES2_HS256_A128KW.decrypter_from_jwk(jwk)?),
+            "PBES2-HS384+A192KW" => Box::new(PBES2_HS384_A192KW.decrypter_from_jwk(jwk)?),
+            "PBES2-HS512+A256KW" => Box::new(PBES2_HS512_A256KW.decrypter_from_jwk(jwk)?),
+            "RSA1_5" => Box::new(RSA1_5.decrypter_from_jwk(jwk)?),
+            "RSA-OAEP" => Box::new(RSA_OAEP.decrypter_from_jwk(jwk)?),
+            "RSA-OAEP-256" => Box::new(RSA_OAEP_256.decrypter_from_jwk(jwk)?),
+            "RSA-OAEP-384" => Box::new(RSA_OAEP_384.decrypter_from_jwk(jwk)?),
+            "RSA-OAEP-512" => Box::new(RSA_OAEP_512.decrypter_from_jwk(jwk)?),
+            _ => {
+                bail!(
+                    "The JWK is not supported for a JWE decrypter: kty={}, crv={}, alg={}",
+                    jwk.key_type(),
+                    jwk.curve().unwrap_or("none"),
+                    alg
+                )
+            }
+        };
+        Ok(decrypter)
+    })()
+    .map_err(|err| match err.downcast::<JoseError>() {
+        Ok(err) => err,
+        Err(err) => JoseError::InvalidKeyFormat(err),
+    })
+}
+
+/// Return a decrypter that is selected by the parameters of a JWK that is explicitly
+/// marked for encryption.
+///
+/// Unlike `decrypter_from_jwk`, the JWK must have a use parameter of "enc", and
+/// a key_ops parameter, if present, must contain a decryption operation
+/// ("decrypt", "unwrapKey" or "deriveKey"). A key only for signing is rejected.
+///
+/// # Arguments
+///
+/// * `jwk` - A private key that is formatted by a JWK.
+pub fn decrypter_from_jwk_strict(jwk: &Jwk) -> Result<Box<dyn JweDecrypter>, JoseError> {
+    (|| -> anyhow::Result<()> {
+        match jwk.key_use() {
+            Some("enc") => {}
+            Some(val) => bail!("A parameter use must be enc: {}", val),
+            None => bail!("A parameter use is required."),
+        }
+        if let Some(vals) = jwk.key_operations() {
+            if !vals
+                .iter()
+                .any(|val| matches!(*val, "decrypt" | "unwrapKey" | "deriveKey"))
+            {
+                bail!("A parameter key_ops must contain a decryption operation.");
+            }
+        }
+        Ok(())
+    })()
+    .map_err(JoseError::InvalidKeyFormat)?;
+
+    decrypter_from_jwk(jwk)
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
     use std::path::PathBuf;
 
     use anyhow::Result;
+    use serde::Serialize;
+    use serde_json::json;
 
+    #[allow(deprecated)]
     use crate::jwe::{
-        self, Dir, JweAlgorithm, JweHeader, JweHeaderSet, ECDH_ES_A128KW, PBES2_HS256_A128KW,
-        RSA_OAEP,
+        self, Dir, JweAlgorithm, JweContext, JweDecrypter, JweHeader, JweHeaderSet, A128GCMKW,
+        A128KW, A192GCMKW, A192KW, A256GCMKW, A256KW, ECDH_ES, ECDH_ES_A128KW, ECDH_ES_A192KW,
+        ECDH_ES_A256KW, PBES2_HS256_A128KW, PBES2_HS384_A192KW, PBES2_HS512_A256KW, RSA1_5,
+        RSA_OAEP, RSA_OAEP_256, RSA_OAEP_384, RSA_OAEP_512,
     };
-    use crate::jwk::Jwk;
+    use crate::jwk::{Jwk, JwkSet, P_256};
     use crate::util;
-    use crate::Value;
+    use crate::{JoseError, Map, Value};
 
     #[test]
     fn test_jwe_compact_serialization() -> Result<()> {
@@ -296,6 +471,562 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwe_compact_serialization_with_private_header_claim() -> Result<()> {
+        let alg = Dir;
+        let key = util::random_bytes(16);
+
+        let mut src_header = JweHeader::new();
+        src_header.set_content_encryption("A128GCM");
+        src_header.set_claim(
+            "x-private",
+            Some(json!({ "nested": { "list": [1, "two", null] }, "flag": true })),
+        )?;
+        let src_payload = b"test payload!";
+
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let jwe = jwe::serialize_compact(src_payload, &src_header, &encrypter)?;
+
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+        let (_, dst_header) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_header.claim("x-private"), dst_header.claim("x-private"));
+
+        let jwe2 = jwe::serialize_compact(src_payload, &dst_header, &encrypter)?;
+        assert_eq!(jwe.split('.').next(), jwe2.split('.').next());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_max_compression_ratio() -> Result<()> {
+        let alg = Dir;
+        let key = util::random_bytes(16);
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_compression("DEF");
+
+        let mut context = JweContext::new();
+        assert_eq!(context.max_compression_ratio(), None);
+        context.set_max_compression_ratio(Some(100));
+
+        let src_payload = b"test payload! test payload! test payload!";
+        let jwe = context.serialize_compact(src_payload, &header, &encrypter)?;
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        // A highly compressible payload is accepted by default.
+        let src_payload = vec![0u8; 1024 * 1024];
+        let jwe = context.serialize_compact(&src_payload, &header, &encrypter)?;
+        let (dst_payload, _) = JweContext::new().deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload, dst_payload);
+        assert!(matches!(
+            context.deserialize_compact(&jwe, &decrypter),
+            Err(JoseError::InvalidJweFormat(_))
+        ));
+
+        context.set_max_compression_ratio(None);
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload, dst_payload);
+
+        context.set_max_compression_ratio(Some(2000));
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload, dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_jwk_alg_member() -> Result<()> {
+        let oct = Jwk::generate_oct_key(16)?;
+        let rsa = Jwk::generate_rsa_key(2048)?;
+        let ec = Jwk::generate_ec_key(P_256)?;
+
+        util::check_jwk_alg_member(&oct, "dir", |jwk| {
+            Dir.encrypter_from_jwk(jwk)?;
+            Dir.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&oct, "A128KW", |jwk| {
+            A128KW.encrypter_from_jwk(jwk)?;
+            A128KW.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&oct, "A128GCMKW", |jwk| {
+            A128GCMKW.encrypter_from_jwk(jwk)?;
+            A128GCMKW.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&oct, "PBES2-HS256+A128KW", |jwk| {
+            PBES2_HS256_A128KW.encrypter_from_jwk(jwk)?;
+            PBES2_HS256_A128KW.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&rsa, "RSA-OAEP", |jwk| {
+            RSA_OAEP.encrypter_from_jwk(&jwk.to_public_key()?)?;
+            RSA_OAEP.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&ec, "ECDH-ES", |jwk| {
+            ECDH_ES.encrypter_from_jwk(&jwk.to_public_key()?)?;
+            ECDH_ES.decrypter_from_jwk(jwk)?;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_jwe_json_decrypt() -> Result<()> {
+        let rsa_decrypter =
+            RSA_OAEP.decrypter_from_jwk(&Jwk::from_bytes(&load_file("jwk/RSA_private.jwk")?)?)?;
+        let ec_decrypter = ECDH_ES
+            .decrypter_from_jwk(&Jwk::from_bytes(&load_file("jwk/EC_P-256_private.jwk")?)?)?;
+
+        let fixtures: Vec<(&str, &dyn JweDecrypter, Value)> = vec![
+            (
+                "jwe_json/RSA-OAEP_A256GCM_general.json",
+                &rsa_decrypter,
+                json!({"alg": "RSA-OAEP", "enc": "A256GCM", "kid": "rsa-key", "typ": "JWT"}),
+            ),
+            (
+                "jwe_json/RSA-OAEP_A256GCM_flattened.json",
+                &rsa_decrypter,
+                json!({"alg": "RSA-OAEP", "enc": "A256GCM", "kid": "rsa-key"}),
+            ),
+            (
+                "jwe_json/ECDH-ES_A128CBC-HS256_general.json",
+                &ec_decrypter,
+                json!({"alg": "ECDH-ES", "enc": "A128CBC-HS256", "kid": "ec-key"}),
+            ),
+            (
+                "jwe_json/ECDH-ES_A128CBC-HS256_flattened.json",
+                &ec_decrypter,
+                json!({"alg": "ECDH-ES", "enc": "A128CBC-HS256", "typ": "JWT"}),
+            ),
+        ];
+
+        for (path, decrypter, expected) in fixtures {
+            let input = String::from_utf8(load_file(path)?)?;
+            let input_map: Map<String, Value> = serde_json::from_str(&input)?;
+            let (payload, header) = jwe::deserialize_json(&input, decrypter)?;
+
+            let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
+            assert_eq!(payload.get("iss"), Some(&json!("joe")), "{}", path);
+            assert_eq!(payload.get("exp"), Some(&json!(1300819380)), "{}", path);
+            assert_eq!(
+                payload.get("http://example.com/is_root"),
+                Some(&json!(true)),
+                "{}",
+                path
+            );
+
+            // The ephemeral key is generated for each fixture, so only its presence is checked.
+            let mut claims = header.claims_set().clone();
+            assert!(
+                matches!(claims.remove("epk"), Some(Value::Object(_))) == path.contains("ECDH-ES")
+            );
+            assert_eq!(Value::Object(claims), expected, "{}", path);
+
+            // A tampered aad (or a added one) must fail the authentication.
+            let mut tampered = input_map.clone();
+            tampered.insert("aad".to_string(), json!("dGFtcGVyZWQ"));
+            let tampered = serde_json::to_string(&tampered)?;
+            assert!(
+                jwe::deserialize_json(&tampered, decrypter).is_err(),
+                "{}",
+                path
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_json_duplicate_header_claim() -> Result<()> {
+        let decrypter =
+            RSA_OAEP.decrypter_from_jwk(&Jwk::from_bytes(&load_file("jwk/RSA_private.jwk")?)?)?;
+
+        let input = String::from_utf8(load_file("jwe_json/RSA-OAEP_A256GCM_flattened.json")?)?;
+        let mut map: Map<String, Value> = serde_json::from_str(&input)?;
+        map.insert("unprotected".to_string(), json!({"enc": "A256GCM"}));
+        let input = serde_json::to_string(&map)?;
+        match jwe::deserialize_json(&input, &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                assert_eq!(err.to_string(), "A duplicate key exists: enc")
+            }
+            res => panic!("{:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_content_encryption_by_name() -> Result<()> {
+        for name in jwe::supported_content_encryptions() {
+            let cencryption = match jwe::content_encryption_by_name(name) {
+                Some(val) => val,
+                None => unreachable!("{}", name),
+            };
+            assert_eq!(cencryption.name(), *name);
+
+            let mut header = JweHeader::new();
+            header.set_content_encryption(cencryption.name());
+
+            let key = util::random_bytes(cencryption.key_len());
+            let encrypter = Dir.encrypter_from_bytes(&key)?;
+            let jwe = jwe::serialize_compact(b"test payload!", &header, &encrypter)?;
+
+            let decrypter = Dir.decrypter_from_bytes(&key)?;
+            let (payload, header) = jwe::deserialize_compact(&jwe, &decrypter)?;
+            assert_eq!(payload, b"test payload!");
+            assert_eq!(header.content_encryption(), Some(*name));
+        }
+
+        assert!(jwe::content_encryption_by_name("A512GCM").is_none());
+        assert!(jwe::content_encryption_by_name("a256gcm").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_jwe_supported_algorithms() -> Result<()> {
+        let algs: Vec<&dyn JweAlgorithm> = vec![
+            &Dir,
+            &A128KW,
+            &A192KW,
+            &A256KW,
+            &A128GCMKW,
+            &A192GCMKW,
+            &A256GCMKW,
+            &ECDH_ES,
+            &ECDH_ES_A128KW,
+            &ECDH_ES_A192KW,
+            &ECDH_ES_A256KW,
+            &PBES2_HS256_A128KW,
+            &PBES2_HS384_A192KW,
+            &PBES2_HS512_A256KW,
+            &RSA1_5,
+            &RSA_OAEP,
+            &RSA_OAEP_256,
+            &RSA_OAEP_384,
+            &RSA_OAEP_512,
+        ];
+        let supported = jwe::supported_key_algorithms();
+        assert_eq!(supported.len(), algs.len());
+        for alg in algs {
+            assert!(supported.contains(&alg.name()), "{}", alg.name());
+        }
+
+        let context = JweContext::new();
+        let supported = jwe::supported_content_encryptions();
+        assert_eq!(supported.len(), 6);
+        for enc in supported {
+            assert!(context.get_content_encryption(enc).is_some(), "{}", enc);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_max_decompressed_size() -> Result<()> {
+        let alg = Dir;
+        let key = util::random_bytes(16);
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_compression("DEF");
+
+        let mut context = JweContext::new();
+        assert_eq!(context.max_decompressed_size(), Some(10 * 1024 * 1024));
+        context.set_max_decompressed_size(Some(1024));
+
+        let src_payload = vec![b'a'; 1024];
+        let jwe = context.serialize_compact(&src_payload, &header, &encrypter)?;
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload, dst_payload);
+
+        let src_payload = vec![b'a'; 1025];
+        let jwe = context.serialize_compact(&src_payload, &header, &encrypter)?;
+        assert!(jwe.len() < 1024);
+        assert!(matches!(
+            context.deserialize_compact(&jwe, &decrypter),
+            Err(JoseError::InvalidJweFormat(err)) if err.to_string().contains("1024 bytes")
+        ));
+
+        context.set_max_decompressed_size(None);
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload, dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_compression_default_decompress_with_limit() -> Result<()> {
+        use std::io;
+
+        use crate::jwe::{zip, JweCompression};
+
+        #[derive(Debug, Clone)]
+        struct CustomCompression;
+
+        impl JweCompression for CustomCompression {
+            fn name(&self) -> &str {
+                "CUSTOM"
+            }
+
+            fn compress(&self, message: &[u8]) -> std::result::Result<Vec<u8>, io::Error> {
+                zip::Def.compress(message)
+            }
+
+            fn decompress(&self, message: &[u8]) -> std::result::Result<Vec<u8>, io::Error> {
+                zip::Def.decompress(message)
+            }
+
+            fn box_clone(&self) -> Box<dyn JweCompression> {
+                Box::new(self.clone())
+            }
+        }
+
+        let compression = CustomCompression;
+        let compressed = compression.compress(&vec![b'a'; 1025])?;
+
+        let decompressed = compression.decompress_with_limit(&compressed, 2048)?;
+        assert_eq!(decompressed.len(), 1025);
+
+        let decompressed = compression.decompress_with_limit(&compressed, 1024)?;
+        assert_eq!(decompressed.len(), 1025);
+
+        let decompressed = compression.decompress_with_limit(&compressed, 100)?;
+        assert_eq!(decompressed.len(), 101);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_zip_must_be_protected() -> Result<()> {
+        let alg = Dir;
+        let key = util::random_bytes(16);
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+
+        let src_payload = b"test payload! test payload! test payload!";
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        header.set_compression("DEF");
+        let jwe =
+            jwe::serialize_flattened_json(src_payload, Some(&header), None, None, &encrypter)?;
+        let (dst_payload, _) = jwe::deserialize_json(&jwe, &decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        header.set_claim("zip", Some(Value::from("DEF")), false)?;
+        assert!(
+            jwe::serialize_flattened_json(src_payload, Some(&header), None, None, &encrypter)
+                .is_err()
+        );
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        let mut recipient_header = JweHeader::new();
+        recipient_header.set_compression("DEF");
+        assert!(jwe::serialize_flattened_json(
+            src_payload,
+            Some(&header),
+            Some(&recipient_header),
+            None,
+            &encrypter
+        )
+        .is_err());
+
+        let jwe =
+            jwe::serialize_flattened_json(src_payload, Some(&header), None, None, &encrypter)?;
+        let mut map: Map<String, Value> = serde_json::from_str(&jwe)?;
+        map.insert("unprotected".to_string(), json!({"zip": "DEF"}));
+        assert!(matches!(
+            jwe::deserialize_json(&serde_json::to_string(&map)?, &decrypter),
+            Err(JoseError::InvalidJweFormat(err)) if err.to_string().contains("zip")
+        ));
+
+        map.remove("unprotected");
+        map.insert("header".to_string(), json!({"zip": "DEF"}));
+        assert!(matches!(
+            jwe::deserialize_json(&serde_json::to_string(&map)?, &decrypter),
+            Err(JoseError::InvalidJweFormat(err)) if err.to_string().contains("zip")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_header_from_serialize() -> Result<()> {
+        #[derive(Serialize)]
+        struct Header {
+            alg: String,
+            enc: String,
+            zip: String,
+            p2c: usize,
+            apu: String,
+        }
+
+        let header = JweHeader::from_serialize(&Header {
+            alg: "PBES2-HS256+A128KW".to_string(),
+            enc: "A128GCM".to_string(),
+            zip: "DEF".to_string(),
+            p2c: 2000,
+            apu: base64::encode_config(b"party", base64::URL_SAFE_NO_PAD),
+        })?;
+        assert_eq!(header.algorithm(), Some("PBES2-HS256+A128KW"));
+        assert_eq!(header.content_encryption(), Some("A128GCM"));
+        assert_eq!(header.compression(), Some("DEF"));
+        assert_eq!(header.pbes2_count(), Some(2000));
+        assert_eq!(header.agreement_partyuinfo(), Some(b"party".to_vec()));
+
+        #[derive(Serialize)]
+        struct InvalidHeader {
+            apu: String,
+        }
+        assert!(matches!(
+            JweHeader::from_serialize(&InvalidHeader {
+                apu: "not base64!".to_string()
+            }),
+            Err(JoseError::InvalidJweFormat(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_max_pbes2_count() -> Result<()> {
+        let alg = PBES2_HS256_A128KW;
+        let key = util::random_bytes(16);
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_pbes2_count(2000);
+
+        let mut context = JweContext::new();
+        assert_eq!(context.max_pbes2_count(), Some(10_000_000));
+
+        let src_payload = b"test payload!";
+        let jwe = context.serialize_compact(src_payload, &header, &encrypter)?;
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        context.set_max_pbes2_count(Some(1500));
+        assert!(matches!(
+            context.deserialize_compact(&jwe, &decrypter),
+            Err(JoseError::InvalidJweFormat(_))
+        ));
+
+        context.set_max_pbes2_count(None);
+        let (dst_payload, _) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_decrypter_from_jwk() -> Result<()> {
+        let src_payload = b"test payload!";
+        let mut src_header = JweHeader::new();
+        src_header.set_content_encryption("A128GCM");
+
+        let private_jwk = Jwk::from_bytes(load_file("jwk/OKP_X25519_private.jwk")?)?;
+        let public_jwk = private_jwk.to_public_key()?;
+        let encrypter = ECDH_ES.encrypter_from_jwk(&public_jwk)?;
+        let jwe = jwe::serialize_compact(src_payload, &src_header, &encrypter)?;
+        let decrypter = jwe::decrypter_from_jwk(&private_jwk)?;
+        assert_eq!(decrypter.algorithm().name(), "ECDH-ES");
+        let (dst_payload, _) = jwe::deserialize_compact(&jwe, &*decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let mut oct_jwk = Jwk::from_bytes(load_file("jwk/oct_128bit_private.jwk")?)?;
+        let err = jwe::decrypter_from_jwk(&oct_jwk).unwrap_err();
+        assert!(format!("{}", err).contains("kty=oct"));
+        oct_jwk.set_algorithm("A128KW");
+        let encrypter = A128KW.encrypter_from_jwk(&oct_jwk)?;
+        let jwe = jwe::serialize_compact(src_payload, &src_header, &encrypter)?;
+        let decrypter = jwe::decrypter_from_jwk(&oct_jwk)?;
+        let (dst_payload, _) = jwe::deserialize_compact(&jwe, &*decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        oct_jwk.set_algorithm("A128XX");
+        let err = jwe::decrypter_from_jwk(&oct_jwk).unwrap_err();
+        assert!(format!("{}", err).contains("alg=A128XX"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_decrypter_from_jwk_strict() -> Result<()> {
+        let src_payload = b"test payload!";
+        let mut src_header = JweHeader::new();
+        src_header.set_content_encryption("A128GCM");
+
+        let mut private_jwk = Jwk::from_bytes(load_file("jwk/RSA_private.jwk")?)?;
+        private_jwk.set_algorithm("RSA-OAEP");
+        jwe::decrypter_from_jwk(&private_jwk)?;
+        assert!(jwe::decrypter_from_jwk_strict(&private_jwk).is_err());
+
+        private_jwk.set_key_use("enc");
+        let encrypter = RSA_OAEP.encrypter_from_jwk(&private_jwk.to_public_key()?)?;
+        let jwe = jwe::serialize_compact(src_payload, &src_header, &encrypter)?;
+        let decrypter = jwe::decrypter_from_jwk_strict(&private_jwk)?;
+        let (dst_payload, _) = jwe::deserialize_compact(&jwe, &*decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        private_jwk.set_key_operations(vec!["sign"]);
+        assert!(jwe::decrypter_from_jwk_strict(&private_jwk).is_err());
+        private_jwk.set_key_operations(vec!["decrypt"]);
+        jwe::decrypter_from_jwk_strict(&private_jwk)?;
+
+        private_jwk.set_key_use("sig");
+        assert!(jwe::decrypter_from_jwk_strict(&private_jwk).is_err());
+
+        let mut oct_jwk = Jwk::from_bytes(load_file("jwk/oct_128bit_private.jwk")?)?;
+        oct_jwk.set_algorithm("A128KW");
+        assert!(jwe::decrypter_from_jwk_strict(&oct_jwk).is_err());
+        oct_jwk.set_key_use("enc");
+        jwe::decrypter_from_jwk_strict(&oct_jwk)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_unsupported_algorithm() -> Result<()> {
+        let key = util::random_bytes(16);
+        let decrypter = Dir.decrypter_from_bytes(&key)?;
+
+        for (header, expected) in &[
+            (
+                json!({ "alg": "RSA-OAEP-1024", "enc": "A128GCM" }),
+                "RSA-OAEP-1024",
+            ),
+            (json!({ "alg": "dir", "enc": "A512GCM" }), "A512GCM"),
+        ] {
+            let header = serde_json::to_vec(header)?;
+            let header_b64 = base64::encode_config(header, base64::URL_SAFE_NO_PAD);
+            let jwe = format!("{}..AAAA.AAAA.AAAA", header_b64);
+
+            match jwe::deserialize_compact(&jwe, &decrypter) {
+                Err(JoseError::UnsupportedAlgorithm(val)) => assert_eq!(&val, expected),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_jwe_json_serialization() -> Result<()> {
         let alg = RSA_OAEP;
@@ -383,6 +1114,250 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwe_general_json_recipient_index() -> Result<()> {
+        let public_key_1 = load_file("pem/RSA_2048bit_public.pem")?;
+        let public_key_2 = load_file("der/EC_P-256_spki_public.der")?;
+
+        let private_key_1 = load_file("pem/RSA_2048bit_private.pem")?;
+        let private_key_2 = load_file("der/EC_P-256_pkcs8_private.der")?;
+
+        let src_payload = b"test payload!";
+
+        let mut src_header = JweHeaderSet::new();
+        src_header.set_content_encryption("A128CBC-HS256", true);
+
+        let mut src_rheader_1 = JweHeader::new();
+        src_rheader_1.set_key_id("xxx-1");
+        let encrypter_1 = RSA_OAEP.encrypter_from_pem(&public_key_1)?;
+
+        let mut src_rheader_2 = JweHeader::new();
+        src_rheader_2.set_key_id("xxx-2");
+        let encrypter_2 = ECDH_ES_A128KW.encrypter_from_der(&public_key_2)?;
+
+        let json = jwe::serialize_general_json(
+            src_payload,
+            Some(&src_header),
+            &[
+                (Some(&src_rheader_1), &*encrypter_1),
+                (Some(&src_rheader_2), &*encrypter_2),
+            ],
+            None,
+        )?;
+
+        let decrypter_1 = RSA_OAEP.decrypter_from_pem(&private_key_1)?;
+        let (dst_payload, dst_header, index) = jwe::deserialize_general_json(&json, &decrypter_1)?;
+        assert_eq!(index, 0);
+        assert_eq!(src_rheader_1.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let decrypter_2 = ECDH_ES_A128KW.decrypter_from_der(&private_key_2)?;
+        let (dst_payload, dst_header, index) = jwe::deserialize_general_json(&json, &decrypter_2)?;
+        assert_eq!(index, 1);
+        assert_eq!(src_rheader_2.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_add_recipient() -> Result<()> {
+        let public_key_1 = load_file("pem/RSA_2048bit_public.pem")?;
+        let public_key_2 = load_file("der/EC_P-256_spki_public.der")?;
+
+        let private_key_1 = load_file("pem/RSA_2048bit_private.pem")?;
+        let private_key_2 = load_file("der/EC_P-256_pkcs8_private.der")?;
+        let key_3 = util::random_bytes(16);
+
+        let src_payload = b"test payload!";
+
+        let mut src_header = JweHeaderSet::new();
+        src_header.set_content_encryption("A128CBC-HS256", true);
+
+        let mut src_rheader_1 = JweHeader::new();
+        src_rheader_1.set_key_id("xxx-1");
+        let encrypter_1 = RSA_OAEP.encrypter_from_pem(&public_key_1)?;
+        let decrypter_1 = RSA_OAEP.decrypter_from_pem(&private_key_1)?;
+
+        let json = jwe::serialize_general_json(
+            src_payload,
+            Some(&src_header),
+            &[(Some(&src_rheader_1), &*encrypter_1)],
+            None,
+        )?;
+
+        let mut src_rheader_2 = JweHeader::new();
+        src_rheader_2.set_key_id("xxx-2");
+        let encrypter_2 = ECDH_ES_A128KW.encrypter_from_der(&public_key_2)?;
+        let decrypter_2 = ECDH_ES_A128KW.decrypter_from_der(&private_key_2)?;
+
+        let json = jwe::add_recipient(&json, &decrypter_1, Some(&src_rheader_2), &encrypter_2)?;
+
+        let encrypter_3 = A128KW.encrypter_from_bytes(&key_3)?;
+        let decrypter_3 = A128KW.decrypter_from_bytes(&key_3)?;
+        let json = jwe::add_recipient(&json, &decrypter_2, None, &encrypter_3)?;
+
+        let (dst_payload, dst_header, index) = jwe::deserialize_general_json(&json, &decrypter_1)?;
+        assert_eq!(index, 0);
+        assert_eq!(src_rheader_1.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let (dst_payload, dst_header, index) = jwe::deserialize_general_json(&json, &decrypter_2)?;
+        assert_eq!(index, 1);
+        assert_eq!(src_rheader_2.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let (dst_payload, _, index) = jwe::deserialize_general_json(&json, &decrypter_3)?;
+        assert_eq!(index, 2);
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128CBC-HS256");
+        let compact = jwe::serialize_compact(src_payload, &header, &encrypter_1)?;
+        assert!(jwe::add_recipient(&compact, &decrypter_1, None, &encrypter_3).is_err());
+
+        let flattened = jwe::serialize_flattened_json(
+            src_payload,
+            Some(&src_header),
+            None,
+            None,
+            &encrypter_1,
+        )?;
+        assert!(jwe::add_recipient(&flattened, &decrypter_1, None, &encrypter_3).is_err());
+        let json2 = jwe::add_recipient(&flattened, &decrypter_1, None, &encrypter_1)?;
+        let (dst_payload, _, index) = jwe::deserialize_general_json(&json2, &decrypter_1)?;
+        assert_eq!(index, 0);
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let other_decrypter = A128KW.decrypter_from_bytes(&util::random_bytes(16))?;
+        assert!(jwe::add_recipient(&json, &other_decrypter, None, &encrypter_3).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_general_json_dir_with_jwk_set() -> Result<()> {
+        let mut keys = Vec::new();
+        for i in 0..3 {
+            let mut jwk = Jwk::generate_oct_key(32)?;
+            jwk.set_key_id(format!("oct-{}", i));
+            keys.push(Value::Object(jwk.into()));
+        }
+        let mut map = Map::new();
+        map.insert("keys".to_string(), Value::Array(keys));
+        let jwk_set = JwkSet::from_map(map)?;
+        let decrypters = jwk_set
+            .keys()
+            .iter()
+            .map(|jwk| Dir.decrypter_from_jwk(jwk))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let encrypter_1 = RSA_OAEP.encrypter_from_pem(&public_key)?;
+        let encrypter_2 = Dir.encrypter_from_jwk(jwk_set.get("oct-1")[0])?;
+
+        let src_payload = b"test payload!";
+
+        let mut src_header = JweHeaderSet::new();
+        src_header.set_content_encryption("A128CBC-HS256", true);
+
+        let mut src_rheader_1 = JweHeader::new();
+        src_rheader_1.set_key_id("rsa");
+
+        let json = jwe::serialize_general_json(
+            src_payload,
+            Some(&src_header),
+            &[(Some(&src_rheader_1), &*encrypter_1), (None, &*encrypter_2)],
+            None,
+        )?;
+
+        let (dst_payload, dst_header, index) =
+            jwe::deserialize_general_json_with_selector(&json, |header| {
+                if header.algorithm() != Some("dir") {
+                    return Ok(None);
+                }
+                let key_id = match header.key_id() {
+                    Some(val) => val,
+                    None => return Ok(None),
+                };
+                Ok(decrypters
+                    .iter()
+                    .find(|decrypter| decrypter.key_id() == Some(key_id))
+                    .map(|decrypter| decrypter as &dyn JweDecrypter))
+            })?;
+        assert_eq!(index, 1);
+        assert_eq!(dst_header.algorithm(), Some("dir"));
+        assert_eq!(dst_header.key_id(), Some("oct-1"));
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let (_, _, index) = jwe::deserialize_general_json(&json, &decrypters[1])?;
+        assert_eq!(index, 1);
+        assert!(jwe::deserialize_general_json(&json, &decrypters[0]).is_err());
+
+        let flattened = jwe::serialize_flattened_json(
+            src_payload,
+            Some(&src_header),
+            None,
+            None,
+            &encrypter_2,
+        )?;
+        assert!(!flattened.contains("encrypted_key"));
+        let (dst_payload, _) = jwe::deserialize_json(&flattened, &decrypters[1])?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_general_json_recipient_critical() -> Result<()> {
+        let public_key_1 = load_file("pem/RSA_2048bit_public.pem")?;
+        let public_key_2 = load_file("der/EC_P-256_spki_public.der")?;
+
+        let private_key_1 = load_file("pem/RSA_2048bit_private.pem")?;
+        let private_key_2 = load_file("der/EC_P-256_pkcs8_private.der")?;
+
+        let src_payload = b"test payload!";
+
+        let mut src_header = JweHeaderSet::new();
+        src_header.set_content_encryption("A128CBC-HS256", true);
+
+        let mut src_rheader_1 = JweHeader::new();
+        src_rheader_1.set_key_id("xxx-1");
+        src_rheader_1.set_critical(&vec!["unknown"]);
+        src_rheader_1.set_claim("unknown", Some(Value::Bool(true)))?;
+        let encrypter_1 = RSA_OAEP.encrypter_from_pem(&public_key_1)?;
+
+        let mut src_rheader_2 = JweHeader::new();
+        src_rheader_2.set_key_id("xxx-2");
+        let encrypter_2 = ECDH_ES_A128KW.encrypter_from_der(&public_key_2)?;
+
+        let json = jwe::serialize_general_json(
+            src_payload,
+            Some(&src_header),
+            &[
+                (Some(&src_rheader_1), &*encrypter_1),
+                (Some(&src_rheader_2), &*encrypter_2),
+            ],
+            None,
+        )?;
+
+        let decrypter_1 = RSA_OAEP.decrypter_from_pem(&private_key_1)?;
+        assert!(jwe::deserialize_json(&json, &decrypter_1).is_err());
+
+        let decrypter_2 = ECDH_ES_A128KW.decrypter_from_der(&private_key_2)?;
+        let (dst_payload, dst_header) = jwe::deserialize_json(&json, &decrypter_2)?;
+        assert_eq!(src_rheader_2.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let mut context = JweContext::new();
+        context.add_acceptable_critical("unknown");
+        let (dst_payload, dst_header) = context.deserialize_json(&json, &decrypter_1)?;
+        assert_eq!(src_rheader_1.key_id(), dst_header.key_id());
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
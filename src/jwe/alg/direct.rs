@@ -180,7 +180,8 @@ impl JweEncrypter for DirectJweEncrypter {
             let actual_len = self.cencryption_key.len();
             if cencryption.key_len() != actual_len {
                 bail!(
-                    "The key size is expected to be {}: {}",
+                    "The key size for {} is expected to be {} bytes: {}",
+                    cencryption.name(),
                     cencryption.key_len(),
                     actual_len
                 );
@@ -278,8 +279,10 @@ mod tests {
 
     use super::DirectJweAlgorithm;
     use crate::jwe::enc::aescbc_hmac::AescbcHmacJweEncryption;
-    use crate::jwe::JweHeader;
+    use crate::jwe::{self, JweHeader};
     use crate::jwk::Jwk;
+    use crate::util;
+    use crate::JoseError;
 
     #[test]
     fn encrypt_and_decrypt_direct() -> Result<()> {
@@ -314,4 +317,42 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn encrypt_direct_key_length() -> Result<()> {
+        let alg = DirectJweAlgorithm::Dir;
+
+        for enc in vec!["A256GCM", "A128CBC-HS256"] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc);
+
+            let encrypter = alg.encrypter_from_bytes(util::random_bytes(32))?;
+            let jwe = jwe::serialize_compact(b"test payload!", &header, &encrypter)?;
+            assert_eq!(jwe.split('.').count(), 5);
+
+            for len in &[31, 33] {
+                let mut jwk = Jwk::new("oct");
+                jwk.set_parameter(
+                    "k",
+                    Some(json!(base64::encode_config(
+                        util::random_bytes(*len),
+                        base64::URL_SAFE_NO_PAD
+                    ))),
+                )?;
+                let encrypter = alg.encrypter_from_jwk(&jwk)?;
+                match jwe::serialize_compact(b"test payload!", &header, &encrypter) {
+                    Err(JoseError::InvalidKeyFormat(err)) => assert_eq!(
+                        format!("{}", err),
+                        format!(
+                            "The key size for {} is expected to be 32 bytes: {}",
+                            enc, len
+                        )
+                    ),
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -224,16 +224,13 @@ impl EcdhEsJweAlgorithm {
                         };
                         let y = match jwk.parameter("y") {
                             Some(Value::String(val)) => {
-                                base64::decode_config(val, base64::URL_SAFE_NO_PAD)?
+                                Some(base64::decode_config(val, base64::URL_SAFE_NO_PAD)?)
                             }
                             Some(_) => bail!("A parameter y must be a string."),
-                            None => bail!("A parameter y is required."),
+                            None => None,
                         };
 
-                        let mut vec = Vec::with_capacity(1 + x.len() + y.len());
-                        vec.push(0x04);
-                        vec.extend_from_slice(&x);
-                        vec.extend_from_slice(&y);
+                        let vec = EcKeyPair::to_uncompressed_point(&x, y.as_deref(), curve)?;
 
                         let pkcs8 = EcKeyPair::to_pkcs8(&vec, true, curve);
                         let public_key = PKey::public_key_from_der(&pkcs8)?;
@@ -500,16 +497,37 @@ impl EcdhEsJweAlgorithm {
         Some(key_type)
     }
 
-    fn concat_kdf(
-        &self,
-        alg: &str,
-        shared_key_len: usize,
-        derived_key: &[u8],
-        apu: Option<&[u8]>,
-        apv: Option<&[u8]>,
-    ) -> anyhow::Result<Vec<u8>> {
-        let shared_key_len_bytes = ((shared_key_len * 8) as u32).to_be_bytes();
-        let alg_len_bytes = (alg.len() as u32).to_be_bytes();
+    /// Return the AlgorithmID and the key length of Concat KDF (RFC 7518 Section 4.6.2).
+    ///
+    /// The AlgorithmID is the enc header claim value in Direct Key Agreement mode,
+    /// and the alg header claim value in Key Agreement with Key Wrapping mode.
+    fn algorithm_id<'a>(&'a self, cencryption: &'a dyn JweContentEncryption) -> (&'a str, usize) {
+        match self {
+            Self::EcdhEs => (cencryption.name(), cencryption.key_len()),
+            _ => (self.name(), self.key_len()),
+        }
+    }
+}
+
+/// Return a key derived by Concat KDF with SHA-256 (NIST SP 800-56A Section 5.8.1)
+/// as used by ECDH-ES (RFC 7518 Section 4.6.2).
+///
+/// # Arguments
+/// * `algorithm_id` - The AlgorithmID (the enc value for ECDH-ES, otherwise the alg value).
+/// * `key_len` - The length of the derived key in bytes.
+/// * `z` - The shared secret of the key agreement.
+/// * `apu` - The decoded Agreement PartyUInfo.
+/// * `apv` - The decoded Agreement PartyVInfo.
+pub fn concat_kdf(
+    algorithm_id: &str,
+    key_len: usize,
+    z: &[u8],
+    apu: Option<&[u8]>,
+    apv: Option<&[u8]>,
+) -> Result<Vec<u8>, JoseError> {
+    (|| -> anyhow::Result<Vec<u8>> {
+        let key_len_bytes = ((key_len * 8) as u32).to_be_bytes();
+        let algorithm_id_len_bytes = (algorithm_id.len() as u32).to_be_bytes();
         let apu_len_bytes = (match apu {
             Some(val) => val.len(),
             None => 0,
@@ -521,15 +539,15 @@ impl EcdhEsJweAlgorithm {
         } as u32)
             .to_be_bytes();
 
-        let mut shared_key = Vec::new();
+        let mut derived_key = Vec::new();
         let md = MessageDigest::sha256();
-        let count = util::ceiling(shared_key_len, md.size());
+        let count = util::ceiling(key_len, md.size());
         for i in 0..count {
             let mut hasher = Hasher::new(md)?;
             hasher.update(&((i + 1) as u32).to_be_bytes())?;
-            hasher.update(&derived_key)?;
-            hasher.update(&alg_len_bytes)?;
-            hasher.update(alg.as_bytes())?;
+            hasher.update(z)?;
+            hasher.update(&algorithm_id_len_bytes)?;
+            hasher.update(algorithm_id.as_bytes())?;
             hasher.update(&apu_len_bytes)?;
             if let Some(val) = apu {
                 hasher.update(val)?;
@@ -538,20 +556,16 @@ impl EcdhEsJweAlgorithm {
             if let Some(val) = apv {
                 hasher.update(val)?;
             }
-            hasher.update(&shared_key_len_bytes)?;
+            hasher.update(&key_len_bytes)?;
 
             let digest = hasher.finish()?;
-            shared_key.extend(digest.to_vec());
-        }
-
-        if shared_key.len() > shared_key_len {
-            shared_key.truncate(shared_key_len);
-        } else if shared_key.len() < shared_key_len {
-            unreachable!();
+            derived_key.extend(digest.to_vec());
         }
 
-        Ok(shared_key)
-    }
+        derived_key.truncate(key_len);
+        Ok(derived_key)
+    })()
+    .map_err(JoseError::InvalidKeyFormat)
 }
 
 impl JweAlgorithm for EcdhEsJweAlgorithm {
@@ -621,7 +635,7 @@ impl EcdhEsJweEncrypter {
     fn compute_shared_key(
         &self,
         header: &mut JweHeader,
-        alg: &str,
+        algorithm_id: &str,
         key_len: usize,
     ) -> Result<Vec<u8>, JoseError> {
         (|| -> anyhow::Result<Vec<u8>> {
@@ -708,8 +722,8 @@ impl EcdhEsJweEncrypter {
             deriver.set_peer(&self.public_key)?;
             let derived_key = deriver.derive_to_vec()?;
 
-            let shared_key = self.algorithm.concat_kdf(
-                alg,
+            let shared_key = concat_kdf(
+                algorithm_id,
                 key_len,
                 &derived_key,
                 apu.as_deref(),
@@ -744,8 +758,8 @@ impl JweEncrypter for EcdhEsJweEncrypter {
         header: &mut JweHeader,
     ) -> Result<Option<Cow<[u8]>>, JoseError> {
         if let EcdhEsJweAlgorithm::EcdhEs = self.algorithm {
-            let shared_key =
-                self.compute_shared_key(header, cencryption.name(), cencryption.key_len())?;
+            let (algorithm_id, key_len) = self.algorithm.algorithm_id(cencryption);
+            let shared_key = self.compute_shared_key(header, algorithm_id, key_len)?;
             Ok(Some(Cow::Owned(shared_key)))
         } else {
             Ok(None)
@@ -946,25 +960,18 @@ impl JweDecrypter for EcdhEsJweDecrypter {
             deriver.set_peer(&public_key)?;
             let derived_key = deriver.derive_to_vec()?;
 
-            // concat KDF
+            let (algorithm_id, key_len) = self.algorithm.algorithm_id(cencryption);
+            let shared_key = concat_kdf(
+                algorithm_id,
+                key_len,
+                &derived_key,
+                apu.as_deref(),
+                apv.as_deref(),
+            )?;
+
             if let EcdhEsJweAlgorithm::EcdhEs = self.algorithm {
-                let shared_key = self.algorithm.concat_kdf(
-                    cencryption.name(),
-                    cencryption.key_len(),
-                    &derived_key,
-                    apu.as_deref(),
-                    apv.as_deref(),
-                )?;
                 Ok(Cow::Owned(shared_key))
             } else {
-                let shared_key = self.algorithm.concat_kdf(
-                    self.algorithm.name(),
-                    self.algorithm.key_len(),
-                    &derived_key,
-                    apu.as_deref(),
-                    apv.as_deref(),
-                )?;
-
                 let aes = match AesKey::new_decrypt(&shared_key) {
                     Ok(val) => val,
                     Err(_) => bail!("Failed to set encrypt key."),
@@ -1011,7 +1018,7 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
-    use super::{EcdhEsJweAlgorithm, EcdhEsKeyType};
+    use super::{concat_kdf, EcdhEsJweAlgorithm, EcdhEsKeyType};
     use crate::jwe::enc::aescbc_hmac::AescbcHmacJweEncryption;
     use crate::jwe::enc::aesgcm::AesgcmJweEncryption;
     use crate::jwe::JweHeader;
@@ -1019,6 +1026,36 @@ mod tests {
     use crate::jwk::Jwk;
     use crate::util;
 
+    #[test]
+    fn concat_kdf_rfc7518_appendix_c() -> Result<()> {
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+
+        let derived_key = concat_kdf("A128GCM", 16, &z, Some(b"Alice"), Some(b"Bob"))?;
+        assert_eq!(
+            derived_key,
+            vec![86, 170, 141, 234, 248, 35, 109, 32, 92, 34, 40, 205, 113, 167, 16, 26]
+        );
+        assert_eq!(
+            base64::encode_config(&derived_key, base64::URL_SAFE_NO_PAD),
+            "VqqN6vgjbSBcIijNcacQGg"
+        );
+
+        let enc = AesgcmJweEncryption::A128gcm;
+        assert_eq!(
+            EcdhEsJweAlgorithm::EcdhEs.algorithm_id(&enc),
+            ("A128GCM", 16)
+        );
+        assert_eq!(
+            EcdhEsJweAlgorithm::EcdhEsA256kw.algorithm_id(&enc),
+            ("ECDH-ES+A256KW", 32)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn encrypt_and_decrypt_ecdh_es_with_pkcs8_der() -> Result<()> {
         let enc = AescbcHmacJweEncryption::A128cbcHs256;
@@ -12,6 +12,9 @@ use crate::jwk::Jwk;
 use crate::util::{self, HashAlgorithm};
 use crate::{JoseError, JoseHeader, Number, Value};
 
+/// The minimum iteration count (p2c) that is recommended by RFC 7518 4.8.1.2.
+const MIN_ITER_COUNT: usize = 1000;
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Pbes2HmacAeskwJweAlgorithm {
     /// PBES2 with HMAC SHA-256 and "A128KW" wrapping
@@ -38,7 +41,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
                 algorithm: self.clone(),
                 private_key,
                 salt_len: 8,
-                iter_count: 1000,
+                iter_count: MIN_ITER_COUNT,
                 key_id: None,
             })
         })()
@@ -80,7 +83,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
                 algorithm: self.clone(),
                 private_key: k,
                 salt_len: 8,
-                iter_count: 1000,
+                iter_count: MIN_ITER_COUNT,
                 key_id,
             })
         })()
@@ -101,6 +104,8 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweDecrypter {
                 algorithm: self.clone(),
                 private_key,
+                min_iter_count: MIN_ITER_COUNT,
+                max_iter_count: 10_000_000,
                 key_id: None,
             })
         })()
@@ -142,6 +147,8 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweDecrypter {
                 algorithm: self.clone(),
                 private_key: k,
+                min_iter_count: MIN_ITER_COUNT,
+                max_iter_count: 10_000_000,
                 key_id,
             })
         })()
@@ -211,8 +218,11 @@ impl Pbes2HmacAeskwJweEncrypter {
     }
 
     pub fn set_iter_count(&mut self, iter_count: usize) {
-        if iter_count < 1000 {
-            panic!("iter_count must be 1000 or more: {}", iter_count);
+        if iter_count < MIN_ITER_COUNT {
+            panic!(
+                "iter_count must be {} or more: {}",
+                MIN_ITER_COUNT, iter_count
+            );
         }
         self.iter_count = iter_count;
     }
@@ -272,10 +282,20 @@ impl JweEncrypter for Pbes2HmacAeskwJweEncrypter {
             };
             let p2c = match in_header.claim("p2c") {
                 Some(Value::Number(val)) => match val.as_u64() {
-                    Some(val) => usize::try_from(val)?,
+                    Some(val) => {
+                        let p2c = usize::try_from(val)?;
+                        if p2c < MIN_ITER_COUNT {
+                            bail!(
+                                "The p2c header claim must be {} or more: {}",
+                                MIN_ITER_COUNT,
+                                p2c
+                            );
+                        }
+                        p2c
+                    }
                     None => bail!("Overflow u64 value: {}", val),
                 },
-                Some(_) => bail!("The apv header claim must be string."),
+                Some(_) => bail!("The p2c header claim must be a number."),
                 None => {
                     let p2c = self.iter_count;
                     out_header.set_claim("p2c", Some(Value::Number(Number::from(p2c))))?;
@@ -329,10 +349,43 @@ impl Deref for Pbes2HmacAeskwJweEncrypter {
 pub struct Pbes2HmacAeskwJweDecrypter {
     algorithm: Pbes2HmacAeskwJweAlgorithm,
     private_key: Vec<u8>,
+    min_iter_count: usize,
+    max_iter_count: usize,
     key_id: Option<String>,
 }
 
 impl Pbes2HmacAeskwJweDecrypter {
+    /// Return the minimum iteration count (p2c) that is accepted. The default is 1000.
+    pub fn min_iter_count(&self) -> usize {
+        self.min_iter_count
+    }
+
+    /// Set the minimum iteration count (p2c) that is accepted.
+    /// A JWE that has a smaller p2c header claim is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_iter_count` - a minimum iteration count
+    pub fn set_min_iter_count(&mut self, min_iter_count: usize) {
+        self.min_iter_count = min_iter_count;
+    }
+
+    /// Return the maximum iteration count (p2c) that is accepted. The default is 10,000,000.
+    pub fn max_iter_count(&self) -> usize {
+        self.max_iter_count
+    }
+
+    /// Set the maximum iteration count (p2c) that is accepted.
+    /// A JWE that has a larger p2c header claim is rejected before the key is derived,
+    /// so a huge p2c cannot be used to exhaust CPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_iter_count` - a maximum iteration count
+    pub fn set_max_iter_count(&mut self, max_iter_count: usize) {
+        self.max_iter_count = max_iter_count;
+    }
+
     pub fn set_key_id(&mut self, value: impl Into<String>) {
         self.key_id = Some(value.into());
     }
@@ -382,9 +435,23 @@ impl JweDecrypter for Pbes2HmacAeskwJweDecrypter {
                     Some(val) => usize::try_from(val)?,
                     None => bail!("Overflow u64 value: {}", val),
                 },
-                Some(_) => bail!("The p2s header claim must be string."),
+                Some(_) => bail!("The p2c header claim must be a number."),
                 None => bail!("The p2c header claim is required."),
             };
+            if p2c < self.min_iter_count {
+                bail!(
+                    "The p2c header claim must be {} or more: {}",
+                    self.min_iter_count,
+                    p2c
+                );
+            }
+            if p2c > self.max_iter_count {
+                bail!(
+                    "The p2c header claim must be {} or less: {}",
+                    self.max_iter_count,
+                    p2c
+                );
+            }
 
             let mut salt = Vec::with_capacity(self.algorithm().name().len() + 1 + p2s.len());
             salt.extend_from_slice(self.algorithm().name().as_bytes());
@@ -476,4 +543,57 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn encrypt_and_decrypt_pbes2_hmac_with_count() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let alg = Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw;
+        let key = util::random_bytes(16);
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        header.set_pbes2_count(2000);
+
+        let encrypter = alg.encrypter_from_bytes(&key)?;
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+        assert_eq!(out_header.pbes2_count(), Some(2000));
+        assert_eq!(out_header.pbes2_salt_input().map(|val| val.len()), Some(8));
+
+        let mut decrypter = alg.decrypter_from_bytes(&key)?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        decrypter.set_min_iter_count(5000);
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        decrypter.set_min_iter_count(1000);
+        decrypter.set_max_iter_count(1500);
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        let decrypter = alg.decrypter_from_bytes(&key)?;
+        assert_eq!(decrypter.max_iter_count(), 10_000_000);
+        out_header.set_pbes2_count(usize::MAX);
+        let err = decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .unwrap_err();
+        assert!(format!("{}", err).contains("or less"));
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        header.set_pbes2_count(100);
+        header.set_pbes2_salt_input(b"saltsalt");
+
+        let mut out_header = header.clone();
+        assert!(encrypter
+            .encrypt(&src_key, &header, &mut out_header)
+            .is_err());
+
+        Ok(())
+    }
 }
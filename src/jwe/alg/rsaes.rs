@@ -122,6 +122,7 @@ impl RsaesJweAlgorithm {
                 algorithm: self.clone(),
                 public_key,
                 key_id: None,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -155,6 +156,7 @@ impl RsaesJweAlgorithm {
                 algorithm: self.clone(),
                 public_key,
                 key_id: None,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -213,6 +215,7 @@ impl RsaesJweAlgorithm {
                 algorithm: self.clone(),
                 public_key,
                 key_id,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -227,6 +230,7 @@ impl RsaesJweAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            oaep_label: None,
         })
     }
 
@@ -239,6 +243,7 @@ impl RsaesJweAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            oaep_label: None,
         })
     }
 
@@ -270,6 +275,7 @@ impl RsaesJweAlgorithm {
                 algorithm: self.clone(),
                 private_key,
                 key_id,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -312,6 +318,7 @@ pub struct RsaesJweEncrypter {
     algorithm: RsaesJweAlgorithm,
     public_key: PKey<Public>,
     key_id: Option<String>,
+    oaep_label: Option<Vec<u8>>,
 }
 
 impl RsaesJweEncrypter {
@@ -322,6 +329,27 @@ impl RsaesJweEncrypter {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return the label of RSA-OAEP.
+    pub fn oaep_label(&self) -> Option<&[u8]> {
+        self.oaep_label.as_deref()
+    }
+
+    /// Set a label of RSA-OAEP. The label is not carried in the JWE header,
+    /// so it must be shared between the encrypter and the decrypter out-of-band.
+    /// JOSE uses a empty label, which is the default, so a label is only for
+    /// a non-JOSE use of the primitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a label of RSA-OAEP
+    pub fn set_oaep_label(&mut self, value: impl Into<Vec<u8>>) {
+        self.oaep_label = Some(value.into());
+    }
+
+    pub fn remove_oaep_label(&mut self) {
+        self.oaep_label = None;
+    }
 }
 
 impl JweEncrypter for RsaesJweEncrypter {
@@ -354,13 +382,25 @@ impl JweEncrypter for RsaesJweEncrypter {
     ) -> Result<Option<Vec<u8>>, JoseError> {
         (|| -> anyhow::Result<Option<Vec<u8>>> {
             let rsa = self.public_key.rsa()?;
+            let label = self.oaep_label.as_deref();
             let encrypted_key = match self.algorithm {
+                RsaesJweAlgorithm::Rsa1_5 if label.is_some() => {
+                    bail!("A label cannot be used with {}.", self.algorithm);
+                }
                 RsaesJweAlgorithm::Rsa1_5 => {
                     let mut encrypted_key = vec![0; rsa.size() as usize];
                     let len = rsa.public_encrypt(&key, &mut encrypted_key, Padding::PKCS1)?;
                     encrypted_key.truncate(len);
                     encrypted_key
                 }
+                RsaesJweAlgorithm::RsaOaep if label.is_some() => {
+                    openssl_rsa_oaep::pkey_public_encrypt(
+                        &self.public_key,
+                        key,
+                        MessageDigest::sha1(),
+                        label,
+                    )?
+                }
                 RsaesJweAlgorithm::RsaOaep => {
                     let mut encrypted_key = vec![0; rsa.size() as usize];
                     let len = rsa.public_encrypt(&key, &mut encrypted_key, Padding::PKCS1_OAEP)?;
@@ -371,16 +411,19 @@ impl JweEncrypter for RsaesJweEncrypter {
                     &self.public_key,
                     &key,
                     MessageDigest::sha256(),
+                    label,
                 )?,
                 RsaesJweAlgorithm::RsaOaep384 => openssl_rsa_oaep::pkey_public_encrypt(
                     &self.public_key,
                     &key,
                     MessageDigest::sha384(),
+                    label,
                 )?,
                 RsaesJweAlgorithm::RsaOaep512 => openssl_rsa_oaep::pkey_public_encrypt(
                     &self.public_key,
                     &key,
                     MessageDigest::sha512(),
+                    label,
                 )?,
             };
 
@@ -407,6 +450,7 @@ pub struct RsaesJweDecrypter {
     algorithm: RsaesJweAlgorithm,
     private_key: PKey<Private>,
     key_id: Option<String>,
+    oaep_label: Option<Vec<u8>>,
 }
 
 impl RsaesJweDecrypter {
@@ -417,6 +461,27 @@ impl RsaesJweDecrypter {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return the label of RSA-OAEP.
+    pub fn oaep_label(&self) -> Option<&[u8]> {
+        self.oaep_label.as_deref()
+    }
+
+    /// Set a label of RSA-OAEP. The label is not carried in the JWE header,
+    /// so it must be shared between the encrypter and the decrypter out-of-band.
+    /// JOSE uses a empty label, which is the default, so a label is only for
+    /// a non-JOSE use of the primitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a label of RSA-OAEP
+    pub fn set_oaep_label(&mut self, value: impl Into<Vec<u8>>) {
+        self.oaep_label = Some(value.into());
+    }
+
+    pub fn remove_oaep_label(&mut self) {
+        self.oaep_label = None;
+    }
 }
 
 impl JweDecrypter for RsaesJweDecrypter {
@@ -445,13 +510,25 @@ impl JweDecrypter for RsaesJweDecrypter {
             };
 
             let rsa = self.private_key.rsa()?;
+            let label = self.oaep_label.as_deref();
             let key = match self.algorithm {
+                RsaesJweAlgorithm::Rsa1_5 if label.is_some() => {
+                    bail!("A label cannot be used with {}.", self.algorithm);
+                }
                 RsaesJweAlgorithm::Rsa1_5 => {
                     let mut key = vec![0; rsa.size() as usize];
                     let len = rsa.private_decrypt(&encrypted_key, &mut key, Padding::PKCS1)?;
                     key.truncate(len);
                     key
                 }
+                RsaesJweAlgorithm::RsaOaep if label.is_some() => {
+                    openssl_rsa_oaep::pkey_private_decrypt(
+                        &self.private_key,
+                        encrypted_key,
+                        MessageDigest::sha1(),
+                        label,
+                    )?
+                }
                 RsaesJweAlgorithm::RsaOaep => {
                     let mut key = vec![0; rsa.size() as usize];
                     let len = rsa.private_decrypt(&encrypted_key, &mut key, Padding::PKCS1_OAEP)?;
@@ -462,16 +539,19 @@ impl JweDecrypter for RsaesJweDecrypter {
                     &self.private_key,
                     &encrypted_key,
                     MessageDigest::sha256(),
+                    label,
                 )?,
                 RsaesJweAlgorithm::RsaOaep384 => openssl_rsa_oaep::pkey_private_decrypt(
                     &self.private_key,
                     &encrypted_key,
                     MessageDigest::sha384(),
+                    label,
                 )?,
                 RsaesJweAlgorithm::RsaOaep512 => openssl_rsa_oaep::pkey_private_decrypt(
                     &self.private_key,
                     &encrypted_key,
                     MessageDigest::sha512(),
+                    label,
                 )?,
             };
 
@@ -499,9 +579,12 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+
     use super::RsaesJweAlgorithm;
     use crate::jwe::enc::aescbc_hmac::AescbcHmacJweEncryption;
-    use crate::jwe::JweHeader;
+    use crate::jwe::{self, JweHeader};
     use crate::jwk::Jwk;
     use crate::util;
 
@@ -540,6 +623,100 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn encrypt_and_decrypt_rsaes_with_oaep_label() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("jwk/RSA_private.jwk")?;
+        let private_key = Jwk::from_bytes(&private_key)?;
+
+        let public_key = load_file("jwk/RSA_public.jwk")?;
+        let public_key = Jwk::from_bytes(&public_key)?;
+
+        for alg in vec![
+            RsaesJweAlgorithm::RsaOaep,
+            RsaesJweAlgorithm::RsaOaep256,
+            RsaesJweAlgorithm::RsaOaep384,
+            RsaesJweAlgorithm::RsaOaep512,
+        ] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc.name());
+
+            let mut encrypter = alg.encrypter_from_jwk(&public_key)?;
+            encrypter.set_oaep_label("label");
+            assert_eq!(encrypter.oaep_label(), Some(b"label" as &[u8]));
+            let mut out_header = header.clone();
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+            let mut decrypter = alg.decrypter_from_jwk(&private_key)?;
+            decrypter.set_oaep_label("label");
+            let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+            assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+            decrypter.set_oaep_label("other");
+            assert!(decrypter
+                .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+                .is_err());
+
+            decrypter.remove_oaep_label();
+            assert!(decrypter
+                .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+                .is_err());
+        }
+
+        let mut encrypter = RsaesJweAlgorithm::Rsa1_5.encrypter_from_jwk(&public_key)?;
+        encrypter.set_oaep_label("label");
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let src_key = util::random_bytes(enc.key_len());
+        assert!(encrypter
+            .encrypt(&src_key, &header, &mut header.clone())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_rsaes_with_empty_oaep_label() -> Result<()> {
+        let private_key = PKey::private_key_from_pem(&load_file("pem/RSA_2048bit_private.pem")?)?;
+        let alg = RsaesJweAlgorithm::RsaOaep256;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let encrypter = alg.encrypter_from_pem(&load_file("pem/RSA_2048bit_public.pem")?)?;
+        assert_eq!(encrypter.oaep_label(), None);
+        let jwe = jwe::serialize_compact(b"test payload!", &header, &encrypter)?;
+
+        // The encrypted key of the JWE path is decrypted by the primitive with a empty label.
+        let encrypted_key = jwe.split('.').nth(1).unwrap();
+        let encrypted_key = base64::decode_config(encrypted_key, base64::URL_SAFE_NO_PAD)?;
+        let key = super::openssl_rsa_oaep::pkey_private_decrypt(
+            &private_key,
+            &encrypted_key,
+            MessageDigest::sha256(),
+            None,
+        )?;
+        assert_eq!(key.len(), 16);
+        assert!(super::openssl_rsa_oaep::pkey_private_decrypt(
+            &private_key,
+            &encrypted_key,
+            MessageDigest::sha256(),
+            Some(b"label"),
+        )
+        .is_err());
+
+        let mut decrypter = alg.decrypter_from_pem(&load_file("pem/RSA_2048bit_private.pem")?)?;
+        let (payload, _) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(payload, b"test payload!");
+
+        decrypter.set_oaep_label("label");
+        assert!(jwe::deserialize_compact(&jwe, &decrypter).is_err());
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -563,11 +740,15 @@ mod openssl_rsa_oaep {
         pkey: &PKey<Public>,
         input: &[u8],
         md: MessageDigest,
+        label: Option<&[u8]>,
     ) -> Result<Vec<u8>, ErrorStack> {
         let mut encrypter = Encrypter::new(pkey)?;
         encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
         encrypter.set_rsa_oaep_md(md)?;
         encrypter.set_rsa_mgf1_md(md)?;
+        if let Some(label) = label {
+            encrypter.set_rsa_oaep_label(label)?;
+        }
 
         let outlen = encrypter.encrypt_len(input)?;
         let mut output = vec![0; outlen];
@@ -580,11 +761,15 @@ mod openssl_rsa_oaep {
         pkey: &PKey<Private>,
         input: &[u8],
         md: MessageDigest,
+        label: Option<&[u8]>,
     ) -> Result<Vec<u8>, ErrorStack> {
         let mut decrypter = Decrypter::new(pkey)?;
         decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
         decrypter.set_rsa_oaep_md(md)?;
         decrypter.set_rsa_mgf1_md(md)?;
+        if let Some(label) = label {
+            decrypter.set_rsa_oaep_label(label)?;
+        }
 
         let outlen = decrypter.decrypt_len(input)?;
         let mut output = vec![0; outlen];
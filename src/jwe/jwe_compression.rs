@@ -11,6 +11,22 @@ pub trait JweCompression: Debug + Send + Sync {
 
     fn decompress(&self, message: &[u8]) -> Result<Vec<u8>, io::Error>;
 
+    /// Decompress a message, but stop reading when the output exceeds a limit.
+    ///
+    /// The output may be longer than the limit (e.g. by one byte to detect the excess);
+    /// the caller must check it.
+    ///
+    /// The default implementation calls `decompress` and then checks the limit, so the
+    /// whole message is inflated in memory first. The built-in compressions override it
+    /// to stream the output and stop reading at the limit.
+    fn decompress_with_limit(&self, message: &[u8], limit: usize) -> Result<Vec<u8>, io::Error> {
+        let mut vec = self.decompress(message)?;
+        if vec.len() > limit {
+            vec.truncate(limit.saturating_add(1));
+        }
+        Ok(vec)
+    }
+
     fn box_clone(&self) -> Box<dyn JweCompression>;
 }
 
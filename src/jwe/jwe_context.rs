@@ -11,16 +11,52 @@ use crate::jwe::{
     JweCompression, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, JweHeaderSet,
 };
 use crate::util;
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::{JoseError, JoseHeader, JoseHeaderMerge, Map, Value};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct JweContext {
     acceptable_criticals: BTreeSet<String>,
     compressions: BTreeMap<String, Box<dyn JweCompression>>,
     content_encryptions: BTreeMap<String, Box<dyn JweContentEncryption>>,
+    max_compression_ratio: Option<usize>,
+    max_decompressed_size: Option<usize>,
+    max_pbes2_count: Option<usize>,
 }
 
 impl JweContext {
+    /// The JWE alg header claim values that are supported by this crate.
+    pub(crate) const SUPPORTED_KEY_ALGORITHMS: &'static [&'static str] = &[
+        "dir",
+        "A128KW",
+        "A192KW",
+        "A256KW",
+        "A128GCMKW",
+        "A192GCMKW",
+        "A256GCMKW",
+        "ECDH-ES",
+        "ECDH-ES+A128KW",
+        "ECDH-ES+A192KW",
+        "ECDH-ES+A256KW",
+        "PBES2-HS256+A128KW",
+        "PBES2-HS384+A192KW",
+        "PBES2-HS512+A256KW",
+        "RSA1_5",
+        "RSA-OAEP",
+        "RSA-OAEP-256",
+        "RSA-OAEP-384",
+        "RSA-OAEP-512",
+    ];
+
+    /// The JWE enc header claim values that are supported by this crate.
+    pub(crate) const SUPPORTED_CONTENT_ENCRYPTIONS: &'static [&'static str] = &[
+        "A128CBC-HS256",
+        "A192CBC-HS384",
+        "A256CBC-HS512",
+        "A128GCM",
+        "A192GCM",
+        "A256GCM",
+    ];
+
     pub fn new() -> Self {
         Self {
             acceptable_criticals: BTreeSet::new(),
@@ -49,6 +85,9 @@ impl JweContext {
                 }
                 map
             },
+            max_compression_ratio: None,
+            max_decompressed_size: Some(10 * 1024 * 1024),
+            max_pbes2_count: Some(10_000_000),
         }
     }
 
@@ -79,6 +118,11 @@ impl JweContext {
         self.acceptable_criticals.remove(name);
     }
 
+    /// Remove all acceptable critical header claim names
+    pub fn clear_acceptable_criticals(&mut self) {
+        self.acceptable_criticals.clear();
+    }
+
     /// Get a compression algorithm for zip header claim value.
     ///
     /// # Arguments
@@ -141,6 +185,70 @@ impl JweContext {
         self.content_encryptions.remove(name);
     }
 
+    /// Test a JWE alg header claim value is supported by this crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a JWE alg header claim value
+    pub(crate) fn is_supported_algorithm(name: &str) -> bool {
+        Self::SUPPORTED_KEY_ALGORITHMS.contains(&name)
+    }
+
+    /// Return the maximum ratio of the decompressed size to the compressed size.
+    pub fn max_compression_ratio(&self) -> Option<usize> {
+        self.max_compression_ratio
+    }
+
+    /// Set the maximum ratio of the decompressed size to the compressed size.
+    ///
+    /// A compressed (zip) payload that is decompressed to more than the ratio times
+    /// of the compressed size is rejected to defend against decompression bombs.
+    /// The ratio is not checked if the value is None. The default is None.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum compression ratio
+    pub fn set_max_compression_ratio(&mut self, value: Option<usize>) {
+        self.max_compression_ratio = value;
+    }
+
+    /// Return the maximum size of a decompressed payload in bytes.
+    pub fn max_decompressed_size(&self) -> Option<usize> {
+        self.max_decompressed_size
+    }
+
+    /// Set the maximum size of a decompressed payload in bytes.
+    ///
+    /// A compressed (zip) payload is decompressed up to the size, and it is rejected
+    /// if the output exceeds the size. It is checked in addition to the maximum
+    /// compression ratio. The size is not checked if the value is None.
+    /// The default is 10 MiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum decompressed size in bytes
+    pub fn set_max_decompressed_size(&mut self, value: Option<usize>) {
+        self.max_decompressed_size = value;
+    }
+
+    /// Return the maximum PBES2 iteration count (p2c).
+    pub fn max_pbes2_count(&self) -> Option<usize> {
+        self.max_pbes2_count
+    }
+
+    /// Set the maximum PBES2 iteration count (p2c).
+    ///
+    /// A JWE that has a larger p2c header claim is rejected before it is passed to
+    /// the decrypter to defend against CPU exhaustion by PBKDF2.
+    /// The count is not checked if the value is None. The default is 10,000,000.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum PBES2 iteration count
+    pub fn set_max_pbes2_count(&mut self, value: Option<usize>) {
+        self.max_pbes2_count = value;
+    }
+
     /// Return a representation of the data that is formatted by compact serialization.
     ///
     /// # Arguments
@@ -344,6 +452,10 @@ impl JweContext {
                     Some(_) => bail!("A zip header claim must be a string."),
                     None => {}
                 }
+                // The zip header claim must be integrity protected by the AEAD.
+                if header.claims_set(false).contains_key("zip") {
+                    bail!("The JWE zip header claim must be in protected.");
+                }
             };
 
             let merged_map = match header {
@@ -361,6 +473,9 @@ impl JweContext {
                 let mut merged_map = merged_map.clone();
 
                 if let Some(val) = recipient_header {
+                    if val.claims_set().contains_key("zip") {
+                        bail!("The JWE zip header claim must be in protected.");
+                    }
                     for (key, value) in val.claims_set() {
                         if merged_map.contains_key(key) {
                             bail!("Duplicate key exists: {}", key);
@@ -485,8 +600,9 @@ impl JweContext {
             if let Some(val) = &protected_b64 {
                 full_aad.push_str(&val);
             }
-            full_aad.push_str(".");
+            // The separator is only added if a aad field is present (RFC 7516 5.1).
             if let Some(val) = &aad_b64 {
+                full_aad.push('.');
                 full_aad.push_str(&val);
             }
 
@@ -542,12 +658,16 @@ impl JweContext {
                     writed = true;
                 }
 
-                json.push_str(if writed { "," } else { "{" });
-                json.push_str("\"encrypted_key\":\"");
-                if let Some(val) = encrypted_key {
-                    base64::encode_config_buf(&val, base64::URL_SAFE_NO_PAD, &mut json);
+                // The encrypted_key member is omitted if the JWE encrypted key is empty (e.g. dir).
+                match encrypted_key {
+                    Some(val) => {
+                        json.push_str(if writed { "," } else { "{" });
+                        json.push_str("\"encrypted_key\":\"");
+                        base64::encode_config_buf(&val, base64::URL_SAFE_NO_PAD, &mut json);
+                        json.push_str("\"}");
+                    }
+                    None => json.push_str(if writed { "}" } else { "{}" }),
                 }
-                json.push_str("\"}");
             }
             json.push_str("]");
 
@@ -642,6 +762,10 @@ impl JweContext {
                     Some(_) => bail!("A zip header claim must be a string."),
                     None => {}
                 }
+                // The zip header claim must be integrity protected by the AEAD.
+                if header.claims_set(false).contains_key("zip") {
+                    bail!("The JWE zip header claim must be in protected.");
+                }
             };
 
             let mut merged_map = match header {
@@ -650,6 +774,9 @@ impl JweContext {
             };
 
             if let Some(val) = recipient_header {
+                if val.claims_set().contains_key("zip") {
+                    bail!("The JWE zip header claim must be in protected.");
+                }
                 for (key, value) in val.claims_set() {
                     if merged_map.contains_key(key) {
                         bail!("Duplicate key exists: {}", key);
@@ -738,8 +865,9 @@ impl JweContext {
             if let Some(val) = &protected_b64 {
                 full_aad.push_str(&val);
             }
-            full_aad.push_str(".");
+            // The separator is only added if a aad field is present (RFC 7516 5.1).
             if let Some(val) = &aad_b64 {
+                full_aad.push('.');
                 full_aad.push_str(&val);
             }
 
@@ -783,11 +911,11 @@ impl JweContext {
                 }
             }
 
-            json.push_str(",\"encrypted_key\":\"");
             if let Some(val) = encrypted_key {
+                json.push_str(",\"encrypted_key\":\"");
                 base64::encode_config_buf(&val, base64::URL_SAFE_NO_PAD, &mut json);
+                json.push('"');
             }
-            json.push_str("\"");
 
             if let Some(val) = aad_b64 {
                 json.push_str(",\"aad\":\"");
@@ -848,54 +976,34 @@ impl JweContext {
         F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
     {
         (|| -> anyhow::Result<(Vec<u8>, JweHeader)> {
-            let input = input.as_ref();
-            let indexies: Vec<usize> = input
-                .iter()
-                .enumerate()
-                .filter(|(_, b)| **b == b'.' as u8)
-                .map(|(pos, _)| pos)
-                .collect();
-            if indexies.len() != 4 {
-                bail!(
-                    "The compact serialization form of JWE must be five parts separated by colon."
-                );
-            }
-
-            let header_b64 = &input[0..indexies[0]];
+            let (merged, parts) = util::split_and_decode_segments(input.as_ref(), &[5])?;
 
-            let encrypted_key_b64 = &input[(indexies[0] + 1)..(indexies[1])];
             let encrypted_key_vec;
-            let encrypted_key = if encrypted_key_b64.len() > 0 {
-                encrypted_key_vec =
-                    base64::decode_config(encrypted_key_b64, base64::URL_SAFE_NO_PAD)?;
+            let encrypted_key = if !parts[1].is_empty() {
+                encrypted_key_vec = util::decode_segment(&parts, 1)?;
                 Some(encrypted_key_vec.as_slice())
             } else {
                 None
             };
 
-            let iv_b64 = &input[(indexies[1] + 1)..(indexies[2])];
             let iv_vec;
-            let iv = if iv_b64.len() > 0 {
-                iv_vec = base64::decode_config(iv_b64, base64::URL_SAFE_NO_PAD)?;
+            let iv = if !parts[2].is_empty() {
+                iv_vec = util::decode_segment(&parts, 2)?;
                 Some(iv_vec.as_slice())
             } else {
                 None
             };
 
-            let ciphertext_b64 = &input[(indexies[2] + 1)..(indexies[3])];
-            let ciphertext = base64::decode_config(ciphertext_b64, base64::URL_SAFE_NO_PAD)?;
+            let ciphertext = util::decode_segment(&parts, 3)?;
 
-            let tag_b64 = &input[(indexies[3] + 1)..];
             let tag_vec;
-            let tag = if tag_b64.len() > 0 {
-                tag_vec = base64::decode_config(tag_b64, base64::URL_SAFE_NO_PAD)?;
+            let tag = if !parts[4].is_empty() {
+                tag_vec = util::decode_segment(&parts, 4)?;
                 Some(tag_vec.as_slice())
             } else {
                 None
             };
 
-            let header = base64::decode_config(header_b64, base64::URL_SAFE_NO_PAD)?;
-            let merged: Map<String, Value> = serde_json::from_slice(&header)?;
             let merged = JweHeader::from_map(merged)?;
 
             let decrypter = match selector(&merged)? {
@@ -906,7 +1014,7 @@ impl JweContext {
             let cencryption = match merged.claim("enc") {
                 Some(Value::String(val)) => match self.get_content_encryption(val) {
                     Some(val2) => val2,
-                    None => bail!("A content encryption is not registered: {}", val),
+                    None => return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into()),
                 },
                 Some(_) => bail!("A enc header claim must be a string."),
                 None => bail!("A enc header claim is required."),
@@ -925,6 +1033,9 @@ impl JweContext {
                 Some(Value::String(val)) => {
                     let expected_alg = decrypter.algorithm().name();
                     if val != expected_alg {
+                        if !Self::is_supported_algorithm(val) {
+                            return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into());
+                        }
                         bail!("The JWE alg header claim is not {}: {}", expected_alg, val);
                     }
                 }
@@ -941,6 +1052,7 @@ impl JweContext {
                 None => {}
             }
 
+            self.check_pbes2_count(&merged)?;
             let key = decrypter.decrypt(encrypted_key, cencryption, &merged)?;
             if key.len() != cencryption.key_len() {
                 bail!(
@@ -950,9 +1062,9 @@ impl JweContext {
                 );
             }
 
-            let content = cencryption.decrypt(&key, iv, &ciphertext, header_b64, tag)?;
+            let content = cencryption.decrypt(&key, iv, &ciphertext, parts[0], tag)?;
             let content = match compression {
-                Some(val) => val.decompress(&content)?,
+                Some(val) => self.decompress(val, &content)?,
                 None => content,
             };
 
@@ -975,26 +1087,26 @@ impl JweContext {
         input: impl AsRef<[u8]>,
         decrypter: &'a dyn JweDecrypter,
     ) -> Result<(Vec<u8>, JweHeader), JoseError> {
-        self.deserialize_json_with_selector(input, |header| {
-            match header.algorithm() {
-                Some(val) => {
-                    let expected_alg = decrypter.algorithm().name();
-                    if val != expected_alg {
-                        return Ok(None);
-                    }
-                }
-                _ => return Ok(None),
-            }
-
-            match decrypter.key_id() {
-                Some(expected) => match header.key_id() {
-                    Some(actual) if expected == actual => {}
-                    _ => return Ok(None),
-                },
-                None => {}
-            }
+        let (payload, header, _) = self.deserialize_general_json(input, decrypter)?;
+        Ok((payload, header))
+    }
 
-            Ok(Some(decrypter))
+    /// Deserialize the input that is formatted by general or flattened json serialization,
+    /// and return the index of the recipient that is decrypted.
+    ///
+    /// The index of flattened json serialization is always 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `decrypter` - The JWE decrypter.
+    pub fn deserialize_general_json(
+        &self,
+        input: impl AsRef<[u8]>,
+        decrypter: &dyn JweDecrypter,
+    ) -> Result<(Vec<u8>, JweHeader, usize), JoseError> {
+        self.deserialize_general_json_with_selector(input, |header| {
+            Ok(Self::match_decrypter(header, decrypter))
         })
     }
 
@@ -1012,8 +1124,188 @@ impl JweContext {
     where
         F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
     {
-        (|| -> anyhow::Result<(Vec<u8>, JweHeader)> {
+        let (payload, header, _) = self.deserialize_general_json_with_selector(input, selector)?;
+        Ok((payload, header))
+    }
+
+    /// Deserialize the input that is formatted by general or flattened json serialization,
+    /// and return the index of the recipient that is decrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `selector` - a function for selecting the decrypting algorithm.
+    pub fn deserialize_general_json_with_selector<'a, F>(
+        &self,
+        input: impl AsRef<[u8]>,
+        selector: F,
+    ) -> Result<(Vec<u8>, JweHeader, usize), JoseError>
+    where
+        F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
+    {
+        let (content, header, index, _) = self.decrypt_json(input.as_ref(), selector)?;
+        Ok((content, header, index))
+    }
+
+    /// Add a recipient to the input that is formatted by general or flattened json serialization,
+    /// and return a representation that is formatted by general json serialization.
+    ///
+    /// The content encryption key is decrypted by the decrypter and wrapped by the new
+    /// encrypter, so the ciphertext is not encrypted again. A compact serialization is
+    /// not accepted because it cannot have two or more recipients. Shared header claims
+    /// (e.g. a alg header claim in the protected header) also apply to the new recipient.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `decrypter` - The JWE decrypter of a existing recipient.
+    /// * `new_header` - The JWE unprotected header claims for the new recipient.
+    /// * `new_encrypter` - The JWE encrypter for the new recipient.
+    pub fn add_recipient(
+        &self,
+        input: impl AsRef<[u8]>,
+        decrypter: &dyn JweDecrypter,
+        new_header: Option<&JweHeader>,
+        new_encrypter: &dyn JweEncrypter,
+    ) -> Result<String, JoseError> {
+        (|| -> anyhow::Result<String> {
             let input = input.as_ref();
+            if input.iter().find(|b| !b.is_ascii_whitespace()) != Some(&b'{') {
+                bail!("The input must be formatted by general or flattened json serialization.");
+            }
+
+            let (_, _, _, key) =
+                self.decrypt_json(input, |header| Ok(Self::match_decrypter(header, decrypter)))?;
+
+            let mut map: Map<String, Value> = serde_json::from_slice(input)?;
+
+            let mut merged_map = Map::new();
+            if let Some(Value::String(val)) = map.get("protected") {
+                let vec = base64::decode_config(val, base64::URL_SAFE_NO_PAD)?;
+                let protected: Map<String, Value> = serde_json::from_slice(&vec)?;
+                merged_map.extend(protected);
+            }
+            if let Some(Value::Object(val)) = map.get("unprotected") {
+                merged_map.extend(val.clone());
+            }
+            if let Some(val) = new_header {
+                for (key, value) in val.claims_set() {
+                    if merged_map.contains_key(key) {
+                        bail!("Duplicate key exists: {}", key);
+                    }
+                    merged_map.insert(key.clone(), value.clone());
+                }
+            }
+            let merged = JweHeader::from_map(merged_map)?;
+
+            let cencryption = match merged.claim("enc") {
+                Some(Value::String(val)) => match self.get_content_encryption(val) {
+                    Some(val2) => val2,
+                    None => bail!("A content encryption is not registered: {}", val),
+                },
+                Some(_) => bail!("A enc header claim must be a string."),
+                None => bail!("A enc header claim is required."),
+            };
+
+            let mut recipient_header = match new_header {
+                Some(val) => val.clone(),
+                None => JweHeader::new(),
+            };
+
+            if let Some(val) = new_encrypter.compute_content_encryption_key(
+                cencryption,
+                &merged,
+                &mut recipient_header,
+            )? {
+                if val.as_ref() != key.as_slice() {
+                    bail!("The encrypter cannot wrap the existing content encryption key.");
+                }
+            }
+
+            match merged.algorithm() {
+                Some(val) if val == new_encrypter.algorithm().name() => {}
+                Some(val) => bail!("The alg header claim is shared by all recipients: {}", val),
+                None => {
+                    recipient_header.set_algorithm(new_encrypter.algorithm().name().to_string());
+                }
+            }
+
+            if merged.key_id().is_none() {
+                if let Some(key_id) = new_encrypter.key_id() {
+                    recipient_header.set_key_id(key_id.to_string());
+                }
+            }
+
+            let encrypted_key = new_encrypter.encrypt(&key, &merged, &mut recipient_header)?;
+
+            let mut recipient = Map::new();
+            if recipient_header.len() > 0 {
+                recipient.insert(
+                    "header".to_string(),
+                    Value::Object(recipient_header.claims_set().clone()),
+                );
+            }
+            if let Some(val) = encrypted_key {
+                recipient.insert(
+                    "encrypted_key".to_string(),
+                    Value::String(base64::encode_config(val, base64::URL_SAFE_NO_PAD)),
+                );
+            }
+
+            match map.get_mut("recipients") {
+                Some(Value::Array(vals)) => vals.push(Value::Object(recipient)),
+                Some(_) => bail!("The recipients field must be a array."),
+                None => {
+                    let mut first = Map::new();
+                    if let Some(val) = map.remove("header") {
+                        first.insert("header".to_string(), val);
+                    }
+                    if let Some(val) = map.remove("encrypted_key") {
+                        first.insert("encrypted_key".to_string(), val);
+                    }
+                    map.insert(
+                        "recipients".to_string(),
+                        Value::Array(vec![Value::Object(first), Value::Object(recipient)]),
+                    );
+                }
+            }
+
+            Ok(serde_json::to_string(&map)?)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJweFormat(err),
+        })
+    }
+
+    fn match_decrypter<'a>(
+        header: &JweHeader,
+        decrypter: &'a dyn JweDecrypter,
+    ) -> Option<&'a dyn JweDecrypter> {
+        match header.algorithm() {
+            Some(val) if val == decrypter.algorithm().name() => {}
+            _ => return None,
+        }
+
+        if let Some(expected) = decrypter.key_id() {
+            match header.key_id() {
+                Some(actual) if expected == actual => {}
+                _ => return None,
+            }
+        }
+
+        Some(decrypter)
+    }
+
+    fn decrypt_json<'a, F>(
+        &self,
+        input: &[u8],
+        selector: F,
+    ) -> Result<(Vec<u8>, JweHeader, usize, Vec<u8>), JoseError>
+    where
+        F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
+    {
+        (|| -> anyhow::Result<(Vec<u8>, JweHeader, usize, Vec<u8>)> {
             let mut map: Map<String, Value> = serde_json::from_slice(input)?;
 
             let (protected, protected_b64) = match map.remove("protected") {
@@ -1028,11 +1320,23 @@ impl JweContext {
                 Some(_) => bail!("The protected field must be a string."),
                 None => (None, None),
             };
+            if let Some(Value::Array(vals)) = protected.as_ref().and_then(|val| val.get("crit")) {
+                for val in vals {
+                    if let Value::String(val2) = val {
+                        if !self.is_acceptable_critical(val2) {
+                            bail!("The critical name '{}' is not supported.", val2);
+                        }
+                    }
+                }
+            }
             let unprotected = match map.remove("unprotected") {
                 Some(Value::Object(val)) => {
                     if val.len() == 0 {
                         bail!("The unprotected field must be empty.");
                     }
+                    if val.contains_key("zip") {
+                        bail!("The JWE zip header claim must be in protected.");
+                    }
                     Some(val)
                 }
                 Some(_) => bail!("The JWE unprotected field must be string."),
@@ -1108,7 +1412,15 @@ impl JweContext {
                 }
             };
 
-            for mut recipient in recipients {
+            // The shared unprotected and protected header claims are merged with
+            // the header claims of each recipient.
+            let mut shared = JweHeader::from_map(unprotected.unwrap_or_default())?;
+            if let Some(val) = &protected {
+                shared =
+                    JweHeader::from_map(shared.merged_with(&JweHeader::from_map(val.clone())?)?)?;
+            }
+
+            for (i, mut recipient) in recipients.into_iter().enumerate() {
                 let header = recipient.remove("header");
 
                 let encrypted_key_vec;
@@ -1124,34 +1436,25 @@ impl JweContext {
                     None => None,
                 };
 
-                let mut merged = match header {
-                    Some(Value::Object(val)) => val,
-                    Some(_) => bail!("The protected field must be a object."),
-                    None => Map::new(),
-                };
-
-                if let Some(val) = &unprotected {
-                    for (key, value) in val {
-                        if merged.contains_key(key) {
-                            bail!("A duplicate key exists: {}", key);
-                        } else {
-                            merged.insert(key.clone(), value.clone());
+                let header = match header {
+                    Some(Value::Object(val)) => {
+                        if val.contains_key("zip") {
+                            bail!("The JWE zip header claim must be in protected.");
                         }
+                        JweHeader::from_map(val)?
                     }
-                }
+                    Some(_) => bail!("The protected field must be a object."),
+                    None => JweHeader::new(),
+                };
+                let merged = JweHeader::from_map(header.merged_with(&shared)?)?;
 
-                if let Some(val) = &protected {
-                    for (key, value) in val {
-                        if merged.contains_key(key) {
-                            bail!("A duplicate key exists: {}", key);
-                        } else {
-                            merged.insert(key.clone(), value.clone());
-                        }
+                // A recipient which has a unsupported critical header claim is skipped.
+                if let Some(vals) = merged.critical() {
+                    if vals.iter().any(|val| !self.is_acceptable_critical(val)) {
+                        continue;
                     }
                 }
 
-                let merged = JweHeader::from_map(merged)?;
-
                 let decrypter = match selector(&merged)? {
                     Some(val) => val,
                     None => continue,
@@ -1160,7 +1463,7 @@ impl JweContext {
                 let cencryption = match merged.claim("enc") {
                     Some(Value::String(val)) => match self.get_content_encryption(val) {
                         Some(val2) => val2,
-                        None => bail!("A content encryption is not registered: {}", val),
+                        None => return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into()),
                     },
                     Some(_) => bail!("A enc header claim must be string."),
                     None => bail!("A enc header claim is required."),
@@ -1179,6 +1482,9 @@ impl JweContext {
                     Some(val) => {
                         let expected_alg = decrypter.algorithm().name();
                         if val != expected_alg {
+                            if !Self::is_supported_algorithm(val) {
+                                return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into());
+                            }
                             bail!("The JWE alg header claim is not {}: {}", expected_alg, val);
                         }
                     }
@@ -1198,11 +1504,12 @@ impl JweContext {
                     Some(val) => val,
                     None => String::new(),
                 };
-                full_aad.push_str(".");
                 if let Some(val) = aad_b64 {
+                    full_aad.push('.');
                     full_aad.push_str(&val);
                 }
 
+                self.check_pbes2_count(&merged)?;
                 let key = decrypter.decrypt(encrypted_key, cencryption, &merged)?;
                 if key.len() != cencryption.key_len() {
                     bail!(
@@ -1215,11 +1522,11 @@ impl JweContext {
                 let content =
                     cencryption.decrypt(&key, iv, &ciphertext, full_aad.as_bytes(), tag)?;
                 let content = match compression {
-                    Some(val) => val.decompress(&content)?,
+                    Some(val) => self.decompress(val, &content)?,
                     None => content,
                 };
 
-                return Ok((content, merged));
+                return Ok((content, merged, i, key.into_owned()));
             }
 
             bail!("A recipient that matched the header claims is not found.");
@@ -1229,4 +1536,46 @@ impl JweContext {
             Err(err) => JoseError::InvalidJweFormat(err),
         })
     }
+
+    fn check_pbes2_count(&self, header: &JweHeader) -> anyhow::Result<()> {
+        if let (Some(max), Some(Value::Number(val))) = (self.max_pbes2_count, header.claim("p2c")) {
+            match val.as_u64() {
+                Some(val) if val <= max as u64 => {}
+                _ => bail!("The p2c header claim must be {} or less: {}", max, val),
+            }
+        }
+        Ok(())
+    }
+
+    fn decompress(
+        &self,
+        compression: &dyn JweCompression,
+        content: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let ratio_limit = self
+            .max_compression_ratio
+            .map(|ratio| content.len().saturating_mul(ratio));
+        let limit = match (ratio_limit, self.max_decompressed_size) {
+            (Some(val1), Some(val2)) => val1.min(val2),
+            (Some(val), None) | (None, Some(val)) => val,
+            (None, None) => return Ok(compression.decompress(content)?),
+        };
+
+        let decompressed = compression.decompress_with_limit(content, limit)?;
+        if let Some(max) = self.max_decompressed_size {
+            if decompressed.len() > max {
+                bail!("The decompressed payload exceeds {} bytes.", max);
+            }
+        }
+        if let Some(ratio) = self.max_compression_ratio {
+            if decompressed.len() > content.len().saturating_mul(ratio) {
+                bail!(
+                    "The decompressed payload exceeds {} times the compressed size: {} bytes",
+                    ratio,
+                    content.len()
+                );
+            }
+        }
+        Ok(decompressed)
+    }
 }
@@ -1,13 +1,15 @@
 use std::cmp::Eq;
-use std::convert::Into;
+use std::convert::{Into, TryFrom};
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
 use anyhow::bail;
+use serde::Serialize;
 
+use crate::jose_header::private::Sealed;
 use crate::jwk::Jwk;
 use crate::util;
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::{JoseError, JoseHeader, JoseHeaderMerge, Map, Number, Value};
 
 /// Represent JWE header claims
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -37,6 +39,25 @@ impl JweHeader {
         Ok(header)
     }
 
+    /// Return a new header instance from a serializable value.
+    ///
+    /// The known header claims are checked in the same way as `from_map`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a value that is serialized to a JSON object.
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Self, JoseError> {
+        let claims = (|| -> anyhow::Result<Map<String, Value>> {
+            match serde_json::to_value(value)? {
+                Value::Object(val) => Ok(val),
+                _ => bail!("The JWE header must be a JSON object."),
+            }
+        })()
+        .map_err(JoseError::InvalidJson)?;
+
+        Self::from_map(claims)
+    }
+
     /// Return a new header instance from map.
     ///
     /// # Arguments
@@ -415,6 +436,47 @@ impl JweHeader {
         }
     }
 
+    /// Set a value for a PBES2 salt input header claim (p2s).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A PBES2 salt input
+    pub fn set_pbes2_salt_input(&mut self, value: impl AsRef<[u8]>) {
+        let key = "p2s";
+        let val = base64::encode_config(&value, base64::URL_SAFE_NO_PAD);
+        self.claims.insert(key.to_string(), Value::String(val));
+    }
+
+    /// Return the value for PBES2 salt input header claim (p2s).
+    pub fn pbes2_salt_input(&self) -> Option<Vec<u8>> {
+        match self.claims.get("p2s") {
+            Some(Value::String(val)) => base64::decode_config(val, base64::URL_SAFE_NO_PAD).ok(),
+            _ => None,
+        }
+    }
+
+    /// Set a value for a PBES2 count header claim (p2c).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A PBES2 iteration count
+    pub fn set_pbes2_count(&mut self, value: usize) {
+        let key = "p2c";
+        self.claims
+            .insert(key.to_string(), Value::Number(Number::from(value)));
+    }
+
+    /// Return the value for PBES2 count header claim (p2c).
+    pub fn pbes2_count(&self) -> Option<usize> {
+        match self.claims.get("p2c") {
+            Some(Value::Number(val)) => match val.as_u64() {
+                Some(val) => usize::try_from(val).ok(),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
     /// Set a value for issuer header claim (iss).
     ///
     /// # Arguments
@@ -563,7 +625,7 @@ impl JweHeader {
                     }
                     _ => bail!("The JWE {} header claim must be a array.", key),
                 },
-                "x5t" | "x5t#S256" | "nonce" | "apu" | "apv" => match &value {
+                "x5t" | "x5t#S256" | "nonce" | "apu" | "apv" | "p2s" => match &value {
                     Value::String(val) => {
                         if !util::is_base64_url_safe_nopad(val) {
                             bail!("The JWE {} header claim must be a base64 string.", key);
@@ -592,6 +654,10 @@ impl JweHeader {
                     }
                     _ => bail!("The JWE {} header claim must be a array.", key),
                 },
+                "p2c" => match &value {
+                    Value::Number(val) if matches!(val.as_u64(), Some(val) if val > 0) => {}
+                    _ => bail!("The JWE {} header claim must be a positive number.", key),
+                },
                 "jwk" => match &value {
                     Value::Object(vals) => Jwk::check_map(vals)?,
                     _ => bail!("The JWE {} header claim must be a string.", key),
@@ -619,6 +685,24 @@ impl JoseHeader for JweHeader {
     }
 }
 
+impl JoseHeaderMerge for JweHeader {
+    fn to_map(&self) -> Map<String, Value> {
+        self.claims.clone()
+    }
+}
+
+impl Sealed for JweHeader {
+    fn format_error(&self, err: anyhow::Error) -> JoseError {
+        JoseError::InvalidJweFormat(err)
+    }
+}
+
+impl Default for JweHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AsRef<Map<String, Value>> for JweHeader {
     fn as_ref(&self) -> &Map<String, Value> {
         &self.claims
@@ -711,4 +795,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jwe_header_pbes2_count() -> Result<()> {
+        let mut header = JweHeader::new();
+        header.set_claim("p2c", Some(json!(1000)))?;
+        assert_eq!(header.pbes2_count(), Some(1000));
+        assert!(header.set_claim("p2c", Some(json!(0))).is_err());
+        assert!(header.set_claim("p2c", Some(json!(-1))).is_err());
+        assert!(header.set_claim("p2c", Some(json!("1000"))).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_jwe_header() {
+        #[derive(Default)]
+        struct Holder {
+            header: JweHeader,
+        }
+
+        let holder = Holder::default();
+        assert_eq!(holder.header, JweHeader::new());
+    }
 }
@@ -1,9 +1,10 @@
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
+use crate::jose_header::private::Sealed;
 use crate::jwe::JweHeader;
 use crate::jwk::Jwk;
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::{JoseError, JoseHeader, JoseHeaderMerge, Map, Value};
 
 /// Represent JWE protected and unprotected header claims
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -676,6 +677,18 @@ impl JoseHeader for JweHeaderSet {
     }
 }
 
+impl JoseHeaderMerge for JweHeaderSet {
+    fn to_map(&self) -> Map<String, Value> {
+        Self::to_map(self)
+    }
+}
+
+impl Sealed for JweHeaderSet {
+    fn format_error(&self, err: anyhow::Error) -> JoseError {
+        JoseError::InvalidJweFormat(err)
+    }
+}
+
 impl Display for JweHeaderSet {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let protected = serde_json::to_string(&self.protected).map_err(|_e| std::fmt::Error {})?;
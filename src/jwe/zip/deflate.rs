@@ -35,6 +35,15 @@ impl JweCompression for DeflateJweCompression {
         Ok(vec)
     }
 
+    fn decompress_with_limit(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, io::Error> {
+        let decoder = DeflateDecoder::new(data);
+        let mut vec = Vec::new();
+        decoder
+            .take((limit as u64).saturating_add(1))
+            .read_to_end(&mut vec)?;
+        Ok(vec)
+    }
+
     fn box_clone(&self) -> Box<dyn JweCompression> {
         Box::new(self.clone())
     }
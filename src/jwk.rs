@@ -4,15 +4,21 @@ pub mod alg;
 
 mod jwk;
 mod jwk_set;
+#[cfg(feature = "jwks-fetcher")]
+mod jwk_set_fetcher;
 mod key_info;
 mod key_pair;
+mod public_key_pair;
 
 pub use crate::jwk::jwk::Jwk;
 pub use crate::jwk::jwk_set::JwkSet;
+#[cfg(feature = "jwks-fetcher")]
+pub use crate::jwk::jwk_set_fetcher::JwkSetFetcher;
 pub use crate::jwk::key_info::KeyAlg;
 pub use crate::jwk::key_info::KeyFormat;
 pub use crate::jwk::key_info::KeyInfo;
 pub use crate::jwk::key_pair::KeyPair;
+pub use crate::jwk::public_key_pair::PublicKeyPair;
 
 pub use crate::jwk::alg::ec::EcCurve::Secp256k1;
 pub use crate::jwk::alg::ec::EcCurve::P256 as P_256;
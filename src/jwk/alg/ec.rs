@@ -3,7 +3,7 @@ use std::ops::Deref;
 
 use anyhow::bail;
 use openssl::bn::{BigNum, BigNumContext};
-use openssl::ec::{EcGroup, EcKey};
+use openssl::ec::{EcGroup, EcKey, EcPoint, PointConversionForm};
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private};
 
@@ -200,14 +200,9 @@ impl EcKeyPair {
                 None => None,
             };
 
-            let public_key = if let (Some(x), Some(y)) = (x, y) {
-                let mut public_key = Vec::with_capacity(1 + x.len() + y.len());
-                public_key.push(0x04);
-                public_key.extend_from_slice(&x);
-                public_key.extend_from_slice(&y);
-                Some(public_key)
-            } else {
-                None
+            let public_key = match x {
+                Some(x) => Some(Self::to_uncompressed_point(&x, y.as_deref(), curve)?),
+                None => None,
             };
 
             let mut builder = DerBuilder::new();
@@ -462,6 +457,40 @@ impl EcKeyPair {
         Some(curve)
     }
 
+    /// Return a uncompressed EC point (0x04 || x || y) from JWK coordinates.
+    ///
+    /// When y is absent, x must be a compressed point (0x02 or 0x03 || x)
+    /// and it is decompressed on the curve.
+    pub(crate) fn to_uncompressed_point(
+        x: &[u8],
+        y: Option<&[u8]>,
+        curve: EcCurve,
+    ) -> anyhow::Result<Vec<u8>> {
+        let y = match y {
+            Some(val) => val,
+            None => {
+                if x.len() != curve.coordinate_size() + 1 || (x[0] != 0x02 && x[0] != 0x03) {
+                    bail!("A parameter y is required.");
+                }
+
+                let ec_group = EcGroup::from_curve_name(curve.nid())?;
+                let mut ctx = BigNumContext::new()?;
+                let point = match EcPoint::from_bytes(&ec_group, x, &mut ctx) {
+                    Ok(val) => val,
+                    Err(_) => bail!("A compressed point is not on the curve {}.", curve),
+                };
+                let vec = point.to_bytes(&ec_group, PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+                return Ok(vec);
+            }
+        };
+
+        let mut vec = Vec::with_capacity(1 + x.len() + y.len());
+        vec.push(0x04);
+        vec.extend_from_slice(x);
+        vec.extend_from_slice(y);
+        Ok(vec)
+    }
+
     pub(crate) fn to_pkcs8(input: &[u8], is_public: bool, curve: EcCurve) -> Vec<u8> {
         let mut builder = DerBuilder::new();
         builder.begin(DerType::Sequence);
@@ -550,6 +579,11 @@ mod tests {
     use anyhow::Result;
 
     use super::{EcCurve, EcKeyPair};
+    use crate::jwk::{Jwk, KeyPair};
+    use crate::jws::{JwsAlgorithm, JwsHeader, ES256, ES256K, ES384, ES512};
+    use crate::jwt::{self, JwtPayload};
+    use crate::util;
+    use crate::Value;
 
     #[test]
     fn test_ec_jwt() -> Result<()> {
@@ -575,4 +609,139 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_ec_generated_key_pair_signs_jwt() -> Result<()> {
+        for alg in &[ES256, ES384, ES512, ES256K] {
+            let key_pair = alg.generate_key_pair()?;
+            assert_eq!(key_pair.algorithm(), Some(alg.name()));
+
+            let mut payload = JwtPayload::new();
+            payload.set_subject("generated");
+
+            let signer = alg.signer_from_jwk(&key_pair.to_jwk_key_pair())?;
+            let jwt = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+
+            let verifier = alg.verifier_from_pem(&key_pair.to_pem_public_key())?;
+            let (decoded, _) = jwt::decode_with_verifier(&jwt, &verifier)?;
+            assert_eq!(decoded, payload);
+
+            let verifier = alg.verifier_from_der(&key_pair.to_der_public_key())?;
+            jwt::decode_with_verifier(&jwt, &verifier)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ec_compressed_point() -> Result<()> {
+        // The base point of P-256.
+        let x = hex("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296");
+        let y = hex("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5");
+        let mut compressed = vec![0x03];
+        compressed.extend_from_slice(&x);
+
+        let point = EcKeyPair::to_uncompressed_point(&compressed, None, EcCurve::P256)?;
+        assert_eq!(point, [vec![0x04], x.clone(), y].concat());
+
+        let err = EcKeyPair::to_uncompressed_point(&x, None, EcCurve::P256).unwrap_err();
+        assert_eq!(err.to_string(), "A parameter y is required.");
+
+        let key_pair = ES256.generate_key_pair()?;
+        let signer = ES256.signer_from_der(&key_pair.to_der_private_key())?;
+        let jwt = jwt::encode_with_signer(&JwtPayload::new(), &JwsHeader::new(), &signer)?;
+
+        let public_jwk = key_pair.to_jwk_public_key();
+        let decode = |key: &str| match public_jwk.parameter(key) {
+            Some(Value::String(val)) => base64::decode_config(val, base64::URL_SAFE_NO_PAD),
+            _ => unreachable!(),
+        };
+        let y = decode("y")?;
+        let mut compressed = vec![0x02 | (y[y.len() - 1] & 1)];
+        compressed.extend_from_slice(&decode("x")?);
+
+        let mut jwk = Jwk::new("EC");
+        jwk.set_curve("P-256");
+        jwk.set_parameter(
+            "x",
+            Some(Value::String(base64::encode_config(
+                &compressed,
+                base64::URL_SAFE_NO_PAD,
+            ))),
+        )?;
+        let verifier = ES256.verifier_from_jwk(&jwk)?;
+        jwt::decode_with_verifier(&jwt, &verifier)?;
+
+        let der = EcKeyPair::to_pkcs8(&compressed, true, EcCurve::P256);
+        let verifier = ES256.verifier_from_der(&der)?;
+        jwt::decode_with_verifier(&jwt, &verifier)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ec_x509_certificates() -> Result<()> {
+        for curve in &[
+            EcCurve::P256,
+            EcCurve::P384,
+            EcCurve::P521,
+            EcCurve::Secp256k1,
+        ] {
+            let key_pair_1 = EcKeyPair::generate(*curve)?;
+            let key_pair_2 = EcKeyPair::generate(*curve)?;
+
+            let mut jwk = key_pair_1.to_jwk_public_key();
+            jwk.set_x509_certificate_chain(&vec![util::self_signed_certificate(
+                &key_pair_1.clone().into_private_key(),
+            )?]);
+            assert_eq!(jwk.x509_certificates()?.len(), 1);
+            assert!(jwk.is_x509_leaf_key_matched()?);
+
+            jwk.set_x509_certificate_chain(&vec![util::self_signed_certificate(
+                &key_pair_2.clone().into_private_key(),
+            )?]);
+            assert!(!jwk.is_x509_leaf_key_matched()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ec_jwk_recompute_public_coordinates() -> Result<()> {
+        for (curve, alg) in vec![
+            (EcCurve::P256, ES256),
+            (EcCurve::P384, ES384),
+            (EcCurve::P521, ES512),
+            (EcCurve::Secp256k1, ES256K),
+        ] {
+            let key_pair = EcKeyPair::generate(curve)?;
+            let expected = key_pair.to_jwk_public_key();
+
+            let mut jwk = key_pair.to_jwk_private_key();
+            jwk.set_parameter("x", None)?;
+            jwk.set_parameter("y", None)?;
+            assert!(jwk.to_public_key().is_err());
+
+            jwk.recompute_public_coordinates()?;
+            assert_eq!(jwk.parameter("x"), expected.parameter("x"));
+            assert_eq!(jwk.parameter("y"), expected.parameter("y"));
+
+            let signer = alg.signer_from_jwk(&jwk)?;
+            let signature = signer.sign(b"abcde12345")?;
+            let verifier = alg.verifier_from_jwk(&jwk.to_public_key()?)?;
+            verifier.verify(b"abcde12345", &signature)?;
+        }
+
+        let mut jwk = Jwk::generate_oct_key(32)?;
+        assert!(jwk.recompute_public_coordinates().is_err());
+
+        Ok(())
+    }
+
+    fn hex(input: &str) -> Vec<u8> {
+        (0..input.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&input[i..i + 2], 16).unwrap())
+            .collect()
+    }
 }
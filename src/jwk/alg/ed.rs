@@ -470,6 +470,7 @@ mod tests {
     use anyhow::Result;
 
     use super::{EdCurve, EdKeyPair};
+    use crate::jwk::KeyPair;
 
     #[test]
     fn test_ed_jwt() -> Result<()> {
@@ -490,4 +491,46 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_ed_jwk_recompute_public_coordinates() -> Result<()> {
+        for curve in vec![EdCurve::Ed25519, EdCurve::Ed448] {
+            let key_pair = EdKeyPair::generate(curve)?;
+            let expected = key_pair.to_jwk_public_key();
+
+            let mut jwk = key_pair.to_jwk_private_key();
+            jwk.set_parameter("x", None)?;
+            jwk.recompute_public_coordinates()?;
+            assert_eq!(jwk.parameter("x"), expected.parameter("x"));
+            assert_eq!(jwk.parameter("y"), None);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed_to_public_key_pair() -> Result<()> {
+        for curve in vec![EdCurve::Ed25519, EdCurve::Ed448] {
+            let mut key_pair = EdKeyPair::generate(curve)?;
+            key_pair.set_key_id(Some("ed-key"));
+
+            let public_key_pair = key_pair.to_public_key_pair();
+            assert_eq!(public_key_pair.key_id(), Some("ed-key"));
+            assert_eq!(public_key_pair.algorithm(), key_pair.algorithm());
+            assert_eq!(
+                public_key_pair.to_der_public_key(),
+                key_pair.to_der_public_key()
+            );
+            assert_eq!(
+                public_key_pair.to_pem_public_key(),
+                key_pair.to_pem_public_key()
+            );
+
+            let jwk = public_key_pair.to_jwk_public_key();
+            assert_eq!(jwk, key_pair.to_jwk_public_key());
+            assert_eq!(jwk.parameter("d"), None);
+        }
+
+        Ok(())
+    }
 }
@@ -62,6 +62,10 @@ impl RsaKeyPair {
 
     /// Generate RSA key pair.
     ///
+    /// This function doesn't check the key length.
+    /// Use RsassaJwsAlgorithm::generate_key_pair (e.g. RS256.generate_key_pair) or
+    /// RsaesJweAlgorithm::generate_key_pair to reject a key length less than 2048 bits.
+    ///
     /// # Arguments
     /// * `bits` - RSA key length
     pub fn generate(bits: u32) -> Result<RsaKeyPair, JoseError> {
@@ -438,8 +442,15 @@ impl Deref for RsaKeyPair {
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
 
     use super::RsaKeyPair;
+    use crate::jwk::{Jwk, KeyPair};
+    use crate::jws::{JwsAlgorithm, JwsHeader, RS256, RS384, RS512};
+    use crate::jwt::{self, JwtPayload};
+    use crate::util;
+    use crate::{Map, Value};
 
     #[test]
     fn test_rsa_jwt() -> Result<()> {
@@ -460,4 +471,159 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_rsa_generated_key_pair_signs_jwt() -> Result<()> {
+        for alg in &[RS256, RS384, RS512] {
+            let key_pair = alg.generate_key_pair(2048)?;
+            assert_eq!(key_pair.algorithm(), Some(alg.name()));
+
+            let mut payload = JwtPayload::new();
+            payload.set_subject("generated");
+
+            let signer = alg.signer_from_jwk(&key_pair.to_jwk_key_pair())?;
+            let jwt = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+
+            let verifier = alg.verifier_from_pem(&key_pair.to_pem_public_key())?;
+            let (decoded, _) = jwt::decode_with_verifier(&jwt, &verifier)?;
+            assert_eq!(decoded, payload);
+
+            let verifier = alg.verifier_from_der(&key_pair.to_der_public_key())?;
+            jwt::decode_with_verifier(&jwt, &verifier)?;
+
+            assert!(alg.generate_key_pair(1024).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_jwk_semantically_eq() -> Result<()> {
+        let key_pair = RsaKeyPair::generate(2048)?;
+        let jwk1 = key_pair.to_jwk_public_key();
+
+        let mut map = Map::new();
+        for key in vec!["e", "n", "kty"] {
+            let value = match jwk1.parameter(key) {
+                Some(Value::String(val)) if key != "kty" => {
+                    let padding = "=".repeat((4 - val.len() % 4) % 4);
+                    Value::String(format!("{}{}", val, padding))
+                }
+                Some(val) => val.clone(),
+                None => unreachable!(),
+            };
+            map.insert(key.to_string(), value);
+        }
+        let jwk2 = Jwk::from_map(map)?;
+
+        assert_ne!(jwk1, jwk2);
+        assert!(jwk1.semantically_eq(&jwk2));
+        assert!(jwk2.semantically_eq(&jwk1));
+
+        let mut jwk3 = jwk2.clone();
+        jwk3.set_key_id("kid");
+        assert!(!jwk1.semantically_eq(&jwk3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_jwk_matches_thumbprint() -> Result<()> {
+        let key_pair = RsaKeyPair::generate(2048)?;
+        let mut jwk = key_pair.to_jwk_public_key();
+        let sha256 = openssl::hash::hash(MessageDigest::sha256(), b"certificate")?;
+        let sha1 = openssl::hash::hash(MessageDigest::sha1(), b"certificate")?;
+        assert!(!jwk.matches_thumbprint(&sha256));
+        assert!(!jwk.matches_sha1_thumbprint(&sha1));
+
+        jwk.set_x509_certificate_sha256_thumbprint(&sha256);
+        jwk.set_x509_certificate_sha1_thumbprint(&sha1);
+        assert!(jwk.matches_thumbprint(&sha256));
+        assert!(jwk.matches_sha1_thumbprint(&sha1));
+
+        let other = openssl::hash::hash(MessageDigest::sha256(), b"other certificate")?;
+        assert!(!jwk.matches_thumbprint(&other));
+        assert!(!jwk.matches_thumbprint(&sha256[..31]));
+        assert!(!jwk.matches_thumbprint(&[]));
+        assert!(!jwk.matches_thumbprint(&sha1));
+        assert!(!jwk.matches_sha1_thumbprint(&sha256));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_to_public_key_pair() -> Result<()> {
+        let key_pair = RsaKeyPair::generate(2048)?;
+        let public_key_pair = key_pair.to_public_key_pair();
+
+        assert_eq!(
+            public_key_pair.to_der_public_key(),
+            key_pair.to_der_public_key()
+        );
+        assert_eq!(
+            public_key_pair.to_pem_public_key(),
+            key_pair.to_pem_public_key()
+        );
+        assert_eq!(public_key_pair.to_jwk_public_key().parameter("d"), None);
+        assert_eq!(
+            public_key_pair.to_jwk_public_key().parameter("n"),
+            key_pair.to_jwk_key_pair().parameter("n")
+        );
+        assert_eq!(public_key_pair, public_key_pair.clone());
+
+        let key_pair: Box<dyn KeyPair> = Box::new(key_pair);
+        assert_eq!(key_pair.to_public_key_pair(), public_key_pair);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_jwk_key_id_from_spki_hash() -> Result<()> {
+        let key_pair = RsaKeyPair::generate(2048)?;
+
+        let public_key = PKey::public_key_from_pem(&key_pair.to_pem_public_key())?;
+        let hash = openssl::sha::sha256(&public_key.public_key_to_der()?);
+        let expected = base64::encode_config(&hash, base64::URL_SAFE_NO_PAD);
+
+        let mut jwk = key_pair.to_jwk_public_key();
+        jwk.set_key_id_from_spki_hash()?;
+        assert_eq!(jwk.key_id(), Some(expected.as_str()));
+
+        let mut jwk = key_pair.to_jwk_private_key();
+        jwk.set_key_id_from_spki_hash()?;
+        assert_eq!(jwk.key_id(), Some(expected.as_str()));
+
+        let mut jwk = Jwk::new("oct");
+        assert!(jwk.set_key_id_from_spki_hash().is_err());
+        assert_eq!(jwk.key_id(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rsa_x509_certificates() -> Result<()> {
+        let key_pair_1 = RsaKeyPair::generate(2048)?;
+        let key_pair_2 = RsaKeyPair::generate(2048)?;
+        let cert_1 = util::self_signed_certificate(&key_pair_1.clone().into_private_key())?;
+        let cert_2 = util::self_signed_certificate(&key_pair_2.clone().into_private_key())?;
+
+        let mut jwk = key_pair_1.to_jwk_public_key();
+        assert_eq!(jwk.x509_certificates()?.len(), 0);
+        assert!(jwk.is_x509_leaf_key_matched().is_err());
+
+        jwk.set_x509_certificate_chain(&vec![&cert_1, &cert_2]);
+        let certs = jwk.x509_certificates()?;
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[0].to_der()?, cert_1);
+        assert!(jwk.is_x509_leaf_key_matched()?);
+
+        jwk.set_x509_certificate_chain(&vec![&cert_2, &cert_1]);
+        assert!(!jwk.is_x509_leaf_key_matched()?);
+
+        jwk.set_x509_certificate_chain(&vec![&cert_1[..], b"invalid"]);
+        let err = jwk.x509_certificates().unwrap_err();
+        assert!(format!("{}", err).contains("x5c[1]"));
+
+        Ok(())
+    }
 }
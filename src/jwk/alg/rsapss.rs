@@ -26,6 +26,18 @@ impl RsaPssKeyPair {
         self.key_len
     }
 
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash
+    }
+
+    pub fn mgf1_hash_algorithm(&self) -> HashAlgorithm {
+        self.mgf1_hash
+    }
+
+    pub fn salt_len(&self) -> u8 {
+        self.salt_len
+    }
+
     pub fn set_algorithm(&mut self, value: Option<&str>) {
         self.algorithm = value.map(|val| val.to_string());
     }
@@ -3,11 +3,17 @@ use std::io::Read;
 use std::string::ToString;
 
 use anyhow::bail;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::rsa::Rsa;
+use openssl::x509::X509;
 
 use crate::jwk::alg::ec::{EcCurve, EcKeyPair};
 use crate::jwk::alg::ecx::{EcxCurve, EcxKeyPair};
 use crate::jwk::alg::ed::{EdCurve, EdKeyPair};
 use crate::jwk::alg::rsa::RsaKeyPair;
+use crate::jwk::KeyPair;
 use crate::util;
 use crate::{JoseError, Map, Value};
 
@@ -57,6 +63,27 @@ impl Jwk {
         })
     }
 
+    /// Return a RSA type JWK from a public key that is formatted by a PKCS#1 RSAPublicKey DER.
+    ///
+    /// # Arguments
+    /// * `input` - A public key that is formatted by a PKCS#1 RSAPublicKey DER.
+    pub fn from_pkcs1_public_der(input: impl AsRef<[u8]>) -> Result<Self, JoseError> {
+        (|| -> anyhow::Result<Self> {
+            let rsa = Rsa::public_key_from_der_pkcs1(input.as_ref())?;
+
+            let mut jwk = Jwk::new("RSA");
+            let n = base64::encode_config(rsa.n().to_vec(), base64::URL_SAFE_NO_PAD);
+            jwk.set_parameter("n", Some(Value::String(n)))?;
+            let e = base64::encode_config(rsa.e().to_vec(), base64::URL_SAFE_NO_PAD);
+            jwk.set_parameter("e", Some(Value::String(e)))?;
+            Ok(jwk)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+
     /// Generate a new oct type JWK.
     ///
     /// # Arguments
@@ -108,6 +135,28 @@ impl Jwk {
         Ok(key_pair.to_jwk_key_pair())
     }
 
+    /// Test this JWK has private key parameters of its key type.
+    ///
+    /// The private key parameters are k for oct, d, p, q, dp, dq, qi and oth for RSA,
+    /// and d for EC and OKP. All of them are checked for a unknown key type.
+    pub fn is_private(&self) -> bool {
+        Self::private_parameters(self.key_type())
+            .iter()
+            .any(|key| self.map.contains_key(*key))
+    }
+
+    /// Return a copy of this JWK without the private key parameters of its key type.
+    ///
+    /// Unlike `to_public_key`, the other parameters (e.g. kid, alg and x5c) are kept,
+    /// so the result can be published in a JWK set.
+    pub fn to_public(&self) -> Self {
+        let mut jwk = self.clone();
+        for key in Self::private_parameters(self.key_type()) {
+            jwk.map.remove(*key);
+        }
+        jwk
+    }
+
     /// Generate private key from private key.
     pub fn to_public_key(&self) -> Result<Self, JoseError> {
         (|| -> anyhow::Result<Jwk> {
@@ -210,6 +259,36 @@ impl Jwk {
         .map_err(|err| JoseError::InvalidJwkFormat(err))
     }
 
+    /// Recompute the public key parameters of a EC or OKP private key from the private key (d).
+    ///
+    /// The x and y parameters of a EC key, or the x parameter of a OKP key are
+    /// overwritten by the values that are derived from the d parameter.
+    pub fn recompute_public_coordinates(&mut self) -> Result<(), JoseError> {
+        let public_key = match (self.key_type(), self.curve()) {
+            ("EC", _) => EcKeyPair::from_jwk(self)?.to_jwk_public_key(),
+            ("OKP", Some("Ed25519")) | ("OKP", Some("Ed448")) => {
+                EdKeyPair::from_jwk(self)?.to_jwk_public_key()
+            }
+            ("OKP", Some("X25519")) | ("OKP", Some("X448")) => {
+                EcxKeyPair::from_jwk(self)?.to_jwk_public_key()
+            }
+            (kty, crv) => {
+                return Err(JoseError::InvalidJwkFormat(anyhow::anyhow!(
+                    "The public key cannot be computed: kty={}, crv={}",
+                    kty,
+                    crv.unwrap_or("none")
+                )))
+            }
+        };
+
+        for key in &["x", "y"] {
+            if let Some(val) = public_key.parameter(key) {
+                self.map.insert(key.to_string(), val.clone());
+            }
+        }
+        Ok(())
+    }
+
     /// Set a value for a key type parameter (kty).
     ///
     /// # Arguments
@@ -322,6 +401,60 @@ impl Jwk {
         }
     }
 
+    /// Set a key ID parameter (kid) to the SHA-256 hash of the public key.
+    ///
+    /// The hash is computed over the DER encoded SubjectPublicKeyInfo and encoded
+    /// by base64url without padding. It is not the JWK thumbprint defined in RFC 7638.
+    pub fn set_key_id_from_spki_hash(&mut self) -> Result<(), JoseError> {
+        let key_id = (|| -> anyhow::Result<String> {
+            let spki_der = self.to_openssl_public_key()?.public_key_to_der()?;
+            let hash = openssl::hash::hash(MessageDigest::sha256(), &spki_der)?;
+            Ok(base64::encode_config(hash, base64::URL_SAFE_NO_PAD))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwkFormat(err),
+        })?;
+
+        self.set_key_id(key_id);
+        Ok(())
+    }
+
+    /// Return the JWK thumbprint defined in RFC 7638.
+    ///
+    /// The SHA-256 hash is computed over the required members of the key type
+    /// and encoded by base64url without padding.
+    pub(crate) fn thumbprint(&self) -> Result<String, JoseError> {
+        (|| -> anyhow::Result<String> {
+            let names: &[&str] = match self.key_type() {
+                "EC" => &["crv", "kty", "x", "y"],
+                "RSA" => &["e", "kty", "n"],
+                "oct" => &["k", "kty"],
+                "OKP" => &["crv", "kty", "x"],
+                val => bail!("The key type is not supported for a thumbprint: {}", val),
+            };
+
+            // The members are inserted in lexicographic order.
+            let mut map = Map::new();
+            for name in names {
+                match self.map.get(*name) {
+                    Some(val) => map.insert(name.to_string(), val.clone()),
+                    None => bail!("The JWK has no {} parameter.", name),
+                };
+            }
+
+            let hash = openssl::hash::hash(
+                MessageDigest::sha256(),
+                serde_json::to_string(&map)?.as_bytes(),
+            )?;
+            Ok(base64::encode_config(hash, base64::URL_SAFE_NO_PAD))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwkFormat(err),
+        })
+    }
+
     /// Set a value for a x509 url parameter (x5u).
     ///
     /// # Arguments
@@ -384,6 +517,35 @@ impl Jwk {
         }
     }
 
+    /// Test a value for a x509 certificate SHA-256 thumbprint parameter (x5t#S256)
+    /// matches a thumbprint.
+    ///
+    /// The thumbprints are compared in constant time, so it is safe to select a key
+    /// by the x5t#S256 header claim of a untrusted token.
+    ///
+    /// # Arguments
+    /// * `sha256` - A x509 certificate SHA-256 thumbprint
+    pub fn matches_thumbprint(&self, sha256: &[u8]) -> bool {
+        match self.x509_certificate_sha256_thumbprint() {
+            Some(val) => util::constant_time_eq(&val, sha256),
+            None => false,
+        }
+    }
+
+    /// Test a value for a x509 certificate SHA-1 thumbprint parameter (x5t)
+    /// matches a thumbprint.
+    ///
+    /// The thumbprints are compared in constant time.
+    ///
+    /// # Arguments
+    /// * `sha1` - A x509 certificate SHA-1 thumbprint
+    pub fn matches_sha1_thumbprint(&self, sha1: &[u8]) -> bool {
+        match self.x509_certificate_sha1_thumbprint() {
+            Some(val) => util::constant_time_eq(&val, sha1),
+            None => false,
+        }
+    }
+
     /// Set values for a X.509 certificate chain parameter (x5c).
     ///
     /// # Arguments
@@ -421,6 +583,112 @@ impl Jwk {
         }
     }
 
+    /// Return X.509 certificates parsed from a X.509 certificate chain parameter (x5c).
+    ///
+    /// An empty list is returned if the parameter is not present.
+    /// The first certificate is the leaf certificate that contains the key of this JWK.
+    pub fn x509_certificates(&self) -> Result<Vec<X509>, JoseError> {
+        (|| -> anyhow::Result<Vec<X509>> {
+            let vals = match self.map.get("x5c") {
+                Some(Value::Array(vals)) => vals,
+                Some(_) => bail!("The parameter 'x5c' must be an array."),
+                None => return Ok(Vec::new()),
+            };
+
+            let mut vec = Vec::with_capacity(vals.len());
+            for (i, val) in vals.iter().enumerate() {
+                let der = match val {
+                    Value::String(val2) => match base64::decode_config(val2, base64::STANDARD)
+                        .or_else(|_| base64::decode_config(val2, base64::URL_SAFE_NO_PAD))
+                    {
+                        Ok(val3) => val3,
+                        Err(err) => bail!("The x5c[{}] is not base64 encoded: {}", i, err),
+                    },
+                    _ => bail!("The x5c[{}] must be a string.", i),
+                };
+                match X509::from_der(&der) {
+                    Ok(val2) => vec.push(val2),
+                    Err(err) => bail!("The x5c[{}] is not a X.509 certificate: {}", i, err),
+                }
+            }
+            Ok(vec)
+        })()
+        .map_err(JoseError::InvalidJwkFormat)
+    }
+
+    /// Test the public key of the leaf certificate in x5c matches the key parameters of this JWK.
+    ///
+    /// This function doesn't validate the certificate chain itself.
+    pub fn is_x509_leaf_key_matched(&self) -> Result<bool, JoseError> {
+        let certs = self.x509_certificates()?;
+        (|| -> anyhow::Result<bool> {
+            let leaf = match certs.first() {
+                Some(val) => val,
+                None => bail!("The parameter 'x5c' is required."),
+            };
+
+            let public_key = self.to_openssl_public_key()?;
+            Ok(leaf.public_key()?.public_eq(&public_key))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwkFormat(err),
+        })
+    }
+
+    fn to_openssl_public_key(&self) -> anyhow::Result<PKey<Public>> {
+        let decode = |key: &str| -> anyhow::Result<Vec<u8>> {
+            match self.map.get(key) {
+                Some(Value::String(val)) => {
+                    Ok(base64::decode_config(val, base64::URL_SAFE_NO_PAD)?)
+                }
+                Some(_) => bail!("The parameter '{}' must be a string.", key),
+                None => bail!("The parameter '{}' is required.", key),
+            }
+        };
+
+        let public_key = match self.key_type() {
+            "RSA" => {
+                let n = BigNum::from_slice(&decode("n")?)?;
+                let e = BigNum::from_slice(&decode("e")?)?;
+                PKey::from_rsa(Rsa::from_public_components(n, e)?)?
+            }
+            "EC" => {
+                let curve = match self.curve() {
+                    Some("P-256") => EcCurve::P256,
+                    Some("P-384") => EcCurve::P384,
+                    Some("P-521") => EcCurve::P521,
+                    Some("secp256k1") => EcCurve::Secp256k1,
+                    Some(val) => bail!("Unknown curve: {}", val),
+                    None => bail!("The key type 'EC' must have parameter 'crv'."),
+                };
+                let x = decode("x")?;
+                let y = match self.map.get("y") {
+                    Some(_) => Some(decode("y")?),
+                    None => None,
+                };
+
+                let vec = EcKeyPair::to_uncompressed_point(&x, y.as_deref(), curve)?;
+
+                PKey::public_key_from_der(&EcKeyPair::to_pkcs8(&vec, true, curve))?
+            }
+            "OKP" => {
+                let x = decode("x")?;
+                let pkcs8 = match self.curve() {
+                    Some("Ed25519") => EdKeyPair::to_pkcs8(&x, true, EdCurve::Ed25519),
+                    Some("Ed448") => EdKeyPair::to_pkcs8(&x, true, EdCurve::Ed448),
+                    Some("X25519") => EcxKeyPair::to_pkcs8(&x, true, EcxCurve::X25519),
+                    Some("X448") => EcxKeyPair::to_pkcs8(&x, true, EcxCurve::X448),
+                    Some(val) => bail!("Unknown curve: {}", val),
+                    None => bail!("The key type 'OKP' must have parameter 'crv'."),
+                };
+                PKey::public_key_from_der(&pkcs8)?
+            }
+            val => bail!("The key type '{}' doesn't have public key.", val),
+        };
+        Ok(public_key)
+    }
+
     /// Set a value for a curve parameter (crv).
     ///
     /// # Arguments
@@ -497,6 +765,42 @@ impl Jwk {
         self.map.get(key)
     }
 
+    /// Return the canonical JSON string of this JWK.
+    ///
+    /// All parameters are included. The members of all objects are sorted by key
+    /// recursively and no whitespace is included, so the result is stable
+    /// regardless of the order of parameters.
+    pub fn to_canonical_string(&self) -> String {
+        util::to_canonical_json(&Value::Object(self.map.clone()))
+    }
+
+    /// Test the logical content of this JWK equals to the other one.
+    ///
+    /// Unlike `==`, the trailing padding ("=") of base64url encoded parameters is
+    /// ignored. The order of parameters is ignored by both of them.
+    ///
+    /// # Arguments
+    /// * `other` - A JWK to be compared
+    pub fn semantically_eq(&self, other: &Jwk) -> bool {
+        self.normalized_map() == other.normalized_map()
+    }
+
+    fn normalized_map(&self) -> Map<String, Value> {
+        let mut map = self.map.clone();
+        for (key, value) in map.iter_mut() {
+            if let (
+                "n" | "e" | "x5t" | "x5t#S256" | "k" | "d" | "p" | "q" | "dp" | "dq" | "qi" | "x"
+                | "y",
+                Value::String(val),
+            ) = (key.as_str(), value)
+            {
+                let len = val.trim_end_matches('=').len();
+                val.truncate(len);
+            }
+        }
+        map
+    }
+
     pub(crate) fn check_map(map: &Map<String, Value>) -> Result<(), JoseError> {
         for (key, value) in map {
             Self::check_parameter(key, value)?;
@@ -569,6 +873,17 @@ impl Jwk {
     }
 }
 
+impl Jwk {
+    fn private_parameters(key_type: &str) -> &'static [&'static str] {
+        match key_type {
+            "oct" => &["k"],
+            "RSA" => &["d", "p", "q", "dp", "dq", "qi", "oth"],
+            "EC" | "OKP" => &["d"],
+            _ => &["k", "d", "p", "q", "dp", "dq", "qi", "oth"],
+        }
+    }
+}
+
 impl AsRef<Map<String, Value>> for Jwk {
     fn as_ref(&self) -> &Map<String, Value> {
         &self.map
@@ -587,3 +902,117 @@ impl Display for Jwk {
         fmt.write_str(&val)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::Jwk;
+    use crate::jwk::{Ed25519, P_256, X25519};
+    use crate::{Map, Value};
+
+    #[test]
+    fn test_jwk_is_private_and_to_public() -> Result<()> {
+        for private_key in vec![
+            Jwk::generate_oct_key(32)?,
+            Jwk::generate_rsa_key(2048)?,
+            Jwk::generate_ec_key(P_256)?,
+            Jwk::generate_ed_key(Ed25519)?,
+            Jwk::generate_ecx_key(X25519)?,
+        ] {
+            let mut private_key = private_key;
+            private_key.set_key_id("key-1");
+            assert!(private_key.is_private(), "{}", private_key.key_type());
+
+            let public_key = private_key.to_public();
+            assert!(!public_key.is_private(), "{}", public_key.key_type());
+            assert_eq!(public_key.key_id(), Some("key-1"));
+            for key in &["k", "d", "p", "q", "dp", "dq", "qi"] {
+                assert_eq!(public_key.parameter(key), None);
+            }
+
+            if private_key.key_type() != "oct" {
+                let mut expected = private_key.to_public_key()?;
+                expected.set_key_id("key-1");
+                assert!(public_key.semantically_eq(&expected));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_thumbprint() -> Result<()> {
+        // The example of RFC 7638 3.1
+        let jwk = Jwk::from_bytes(
+            br#"{
+                "kty": "RSA",
+                "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+                "e": "AQAB",
+                "alg": "RS256",
+                "kid": "2011-04-29"
+            }"#,
+        )?;
+        assert_eq!(
+            jwk.thumbprint()?,
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_to_canonical_string() -> Result<()> {
+        let mut jwk = Jwk::new("RSA");
+        jwk.set_parameter("n", Some(json!("AQAB")))?;
+        jwk.set_parameter("e", Some(json!("AQAB")))?;
+        jwk.set_parameter("ext", Some(json!({"z": [{"b": 1, "a": 2}], "y": null})))?;
+        jwk.set_key_id("kid");
+
+        assert_eq!(
+            jwk.to_canonical_string(),
+            r#"{"e":"AQAB","ext":{"y":null,"z":[{"a":2,"b":1}]},"kid":"kid","kty":"RSA","n":"AQAB"}"#
+        );
+
+        let mut map = Map::new();
+        for (key, value) in jwk.as_ref().iter().rev() {
+            map.insert(key.clone(), value.clone());
+        }
+        let reordered = Jwk::from_map(map)?;
+        assert_ne!(jwk.to_string(), reordered.to_string());
+        assert_eq!(jwk.to_canonical_string(), reordered.to_canonical_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwk_unknown_parameter() -> Result<()> {
+        let mut map: Map<String, Value> = Jwk::generate_ec_key(P_256)?.to_public().into();
+        map.insert(
+            "kms_arn".to_string(),
+            json!("arn:aws:kms:us-east-1:111122223333:key/example"),
+        );
+        let input = serde_json::to_string(&map)?;
+
+        let mut jwk = Jwk::from_bytes(&input)?;
+        assert_eq!(
+            jwk.parameter("kms_arn"),
+            Some(&json!("arn:aws:kms:us-east-1:111122223333:key/example"))
+        );
+        assert_eq!(jwk.to_string(), input);
+
+        jwk.set_key_id("key-1");
+        let jwk = Jwk::from_bytes(jwk.to_string())?;
+        assert_eq!(jwk.key_id(), Some("key-1"));
+        assert_eq!(
+            jwk.parameter("kms_arn"),
+            Some(&json!("arn:aws:kms:us-east-1:111122223333:key/example"))
+        );
+
+        map.insert("kid".to_string(), json!("key-1"));
+        assert_eq!(jwk.as_ref(), &map);
+
+        Ok(())
+    }
+}
@@ -1,13 +1,13 @@
 use std::collections::BTreeMap;
 use std::fmt::Display;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::ops::Bound::Included;
 use std::string::ToString;
 use std::sync::Arc;
 
 use anyhow::bail;
 
-use crate::jwk::Jwk;
+use crate::jwk::{Jwk, KeyPair};
 use crate::{JoseError, Map, Value};
 
 /// Represents JWK set.
@@ -20,9 +20,12 @@ pub struct JwkSet {
 
 impl JwkSet {
     pub fn new() -> Self {
+        let mut params = Map::new();
+        params.insert("keys".to_string(), Value::Array(Vec::new()));
+
         Self {
             keys: Vec::new(),
-            params: Map::new(),
+            params,
             kid_map: BTreeMap::new(),
         }
     }
@@ -65,6 +68,29 @@ impl JwkSet {
         })
     }
 
+    /// Return a JWK set of the public keys of key pairs (e.g. for a jwks_uri).
+    ///
+    /// Each JWK has a sig key use. If a key pair has no key ID,
+    /// the JWK thumbprint defined in RFC 7638 is set to the kid parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - key pairs
+    pub fn from_public_keys(keys: &[&dyn KeyPair]) -> Self {
+        let mut jwk_set = Self::new();
+        for key in keys {
+            let mut jwk = key.to_jwk_public_key();
+            if jwk.key_id().is_none() {
+                // A public JWK of a key pair always has the required members.
+                let key_id = jwk.thumbprint().unwrap();
+                jwk.set_key_id(key_id);
+            }
+            jwk.set_key_use("sig");
+            jwk_set.push_key(jwk);
+        }
+        jwk_set
+    }
+
     pub fn from_reader(input: &mut dyn Read) -> Result<Self, JoseError> {
         (|| -> anyhow::Result<Self> {
             let keys: Map<String, Value> = serde_json::from_reader(input)?;
@@ -76,6 +102,39 @@ impl JwkSet {
         })
     }
 
+    /// Return a JWK set from newline-delimited JSON (NDJSON) JWKs.
+    ///
+    /// Each line must be a JWK object. Blank lines are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a reader of JWKs separated by newlines
+    pub fn from_ndjson_reader(input: &mut dyn Read) -> Result<Self, JoseError> {
+        (|| -> anyhow::Result<Self> {
+            let mut keys = Vec::new();
+            for (i, line) in BufReader::new(input).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match serde_json::from_str(&line) {
+                    Ok(Value::Object(val)) => keys.push(Value::Object(val)),
+                    Ok(_) => bail!("The line {} must be a JWK object.", i + 1),
+                    Err(err) => bail!("The line {} is not a valid JSON: {}", i + 1, err),
+                }
+            }
+
+            let mut map = Map::new();
+            map.insert("keys".to_string(), Value::Array(keys));
+            Ok(Self::from_map(map)?)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwkFormat(err),
+        })
+    }
+
     pub fn from_bytes(input: impl AsRef<[u8]>) -> Result<Self, JoseError> {
         (|| -> anyhow::Result<Self> {
             let keys: Map<String, Value> = serde_json::from_slice(input.as_ref())?;
@@ -87,6 +146,15 @@ impl JwkSet {
         })
     }
 
+    /// Return the JWKs that have the key ID in the order of the keys parameter.
+    ///
+    /// A JWK set may have two or more JWKs with the same key ID (e.g. for key rotation
+    /// or different algorithms), so all of them are returned.
+    /// Use `get_unique` if the key ID must identify a single JWK.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_id` - a key ID
     pub fn get(&self, key_id: &str) -> Vec<&Jwk> {
         let mut vec = Vec::new();
         for (_, val) in self.kid_map.range((
@@ -99,6 +167,29 @@ impl JwkSet {
         vec
     }
 
+    /// Return the only JWK that has the key ID.
+    ///
+    /// None is returned if no JWK has the key ID,
+    /// and an error is returned if two or more JWKs have the same key ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_id` - a key ID
+    pub fn get_unique(&self, key_id: &str) -> Result<Option<&Jwk>, JoseError> {
+        (|| -> anyhow::Result<Option<&Jwk>> {
+            let mut vec = self.get(key_id);
+            if vec.len() > 1 {
+                bail!(
+                    "The key ID is ambiguous: {} keys have kid {}",
+                    vec.len(),
+                    key_id
+                );
+            }
+            Ok(vec.pop())
+        })()
+        .map_err(JoseError::InvalidJwkFormat)
+    }
+
     pub fn keys(&self) -> Vec<&Jwk> {
         self.keys.iter().map(|e| e.as_ref()).collect()
     }
@@ -168,6 +259,10 @@ impl Display for JwkSet {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jwk::alg::ec::EcKeyPair;
+    use crate::jwk::alg::ed::EdKeyPair;
+    use crate::jwk::alg::rsa::RsaKeyPair;
+    use crate::jwk::{Ed25519, P_256};
     use anyhow::Result;
     use std::fs::File;
     use std::path::PathBuf;
@@ -186,6 +281,100 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_unique() -> Result<()> {
+        let mut file = load_file("jwks/test.jwks")?;
+        let jwks = JwkSet::from_reader(&mut file)?;
+
+        let jwk = jwks.get_unique("1")?;
+        assert!(matches!(jwk.and_then(|val| val.key_id()), Some("1")));
+        assert!(jwks.get_unique("3")?.is_none());
+
+        let jwks = JwkSet::from_bytes(
+            br#"{"keys":[
+                {"kty":"oct","kid":"1","k":"AAAA"},
+                {"kty":"oct","kid":"1","k":"BBBB"}
+            ]}"#,
+        )?;
+        assert!(jwks.get_unique("1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_shared_key_id() -> Result<()> {
+        let mut jwks = JwkSet::from_bytes(
+            br#"{"keys":[
+                {"kty":"oct","kid":"1","k":"AAAA"},
+                {"kty":"oct","kid":"2","k":"BBBB"},
+                {"kty":"oct","kid":"1","k":"CCCC"}
+            ]}"#,
+        )?;
+
+        let keys: Vec<&Value> = jwks
+            .get("1")
+            .iter()
+            .map(|jwk| jwk.parameter("k").unwrap())
+            .collect();
+        assert_eq!(keys, vec!["AAAA", "CCCC"]);
+        assert_eq!(jwks.get("2").len(), 1);
+        assert!(jwks.get("3").is_empty());
+
+        let mut jwk = Jwk::new("oct");
+        jwk.set_key_id("1");
+        jwk.set_parameter("k", Some(Value::String("DDDD".to_string())))?;
+        jwks.push_key(jwk);
+        assert_eq!(jwks.get("1").len(), 3);
+        assert_eq!(jwks.get("1")[2].parameter("k").unwrap(), "DDDD");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_ndjson_reader() -> Result<()> {
+        let mut input: &[u8] = b"{\"kty\":\"oct\",\"kid\":\"1\",\"k\":\"AAAA\"}\n\
+            \n\
+            {\"kty\":\"oct\",\"kid\":\"2\",\"k\":\"BBBB\"}\r\n\
+            {\"kty\":\"oct\",\"kid\":\"3\",\"k\":\"CCCC\"}\n";
+        let jwks = JwkSet::from_ndjson_reader(&mut input)?;
+
+        assert_eq!(jwks.keys().len(), 3);
+        for kid in &["1", "2", "3"] {
+            assert!(jwks.get_unique(kid)?.is_some());
+        }
+
+        let mut input: &[u8] = b"{\"kty\":\"oct\",\"k\":\"AAAA\"}\n[]\n";
+        assert!(JwkSet::from_ndjson_reader(&mut input).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_public_keys() -> Result<()> {
+        let rsa_key = RsaKeyPair::generate(2048)?;
+        let mut ec_key = EcKeyPair::generate(P_256)?;
+        ec_key.set_key_id(Some("ec-key"));
+        let ed_key = EdKeyPair::generate(Ed25519)?;
+
+        let jwks = JwkSet::from_public_keys(&[&rsa_key, &ec_key, &ed_key]);
+        let jwks = JwkSet::from_bytes(jwks.to_string())?;
+        assert_eq!(jwks.keys().len(), 3);
+
+        for jwk in jwks.keys() {
+            assert!(jwk.key_id().is_some());
+            assert_eq!(jwk.key_use(), Some("sig"));
+            assert!(!jwk.is_private());
+        }
+        assert_eq!(
+            jwks.keys()[0].key_id(),
+            Some(rsa_key.to_jwk_public_key().thumbprint()?.as_str())
+        );
+        assert_eq!(jwks.keys()[1].key_id(), Some("ec-key"));
+        assert!(jwks.get_unique("ec-key")?.is_some());
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<File> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
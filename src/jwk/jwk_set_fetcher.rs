@@ -0,0 +1,391 @@
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::jwk::{Jwk, JwkSet};
+use crate::JoseError;
+
+/// Represents a fetcher of a remote JWK set (e.g. jwks_uri of OpenID Connect).
+///
+/// The fetched JWK set is cached until the max-age of the Cache-Control response header
+/// (or `default_max_age` if it is absent) is reached. When the cache is expired or
+/// a key ID is not found in the cached JWK set, the JWK set is fetched again,
+/// but not more often than `min_refresh_interval`. Until then, the cached JWK set
+/// is used even if it is expired (e.g. by a max-age=0 response).
+///
+/// This type is `Send` and `Sync`, so a single instance can be shared between tasks
+/// (e.g. in an `Arc`). Only one task fetches the JWK set at a time, and the cache
+/// is not locked while fetching, so lookups are never blocked by a slow endpoint.
+#[derive(Debug)]
+pub struct JwkSetFetcher {
+    url: String,
+    client: reqwest::Client,
+    default_max_age: Duration,
+    min_refresh_interval: Duration,
+    cache: RwLock<Option<CacheEntry>>,
+    // It is held while fetching, and has the time of the last attempt.
+    last_attempt: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    jwk_set: JwkSet,
+    expires_at: Instant,
+}
+
+impl JwkSetFetcher {
+    /// The timeout of a request used by `new`.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Return a new JwkSetFetcher.
+    ///
+    /// The HTTP client has a request timeout of `DEFAULT_TIMEOUT`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - a URL of the JWK set
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Self::DEFAULT_TIMEOUT)
+            .build()
+            .expect("A HTTP client cannot be initialized.");
+        Self::with_client(url, client)
+    }
+
+    /// Return a new JwkSetFetcher that uses a HTTP client.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - a URL of the JWK set
+    /// * `client` - a HTTP client
+    pub fn with_client(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Self {
+            url: url.into(),
+            client,
+            default_max_age: Duration::from_secs(300),
+            min_refresh_interval: Duration::from_secs(30),
+            cache: RwLock::new(None),
+            last_attempt: Mutex::new(None),
+        }
+    }
+
+    /// Return the URL of the JWK set.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Set a cache lifetime used when the response has no max-age.
+    /// The default is 5 minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a cache lifetime
+    pub fn set_default_max_age(&mut self, value: Duration) {
+        self.default_max_age = value;
+    }
+
+    /// Return the cache lifetime used when the response has no max-age.
+    pub fn default_max_age(&self) -> Duration {
+        self.default_max_age
+    }
+
+    /// Set a minimum interval of fetching the JWK set again.
+    /// The default is 30 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a minimum interval
+    pub fn set_min_refresh_interval(&mut self, value: Duration) {
+        self.min_refresh_interval = value;
+    }
+
+    /// Return the minimum interval of fetching the JWK set again.
+    pub fn min_refresh_interval(&self) -> Duration {
+        self.min_refresh_interval
+    }
+
+    /// Return the JWK that has the key ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_id` - a key ID
+    pub async fn get_key(&self, key_id: &str) -> Result<Option<Jwk>, JoseError> {
+        if let Some(jwk) = self.find_fresh_key(key_id).await? {
+            return Ok(Some(jwk));
+        }
+
+        let mut last_attempt = self.last_attempt.lock().await;
+
+        // Another task may have fetched the JWK set while waiting for the lock.
+        if let Some(jwk) = self.find_fresh_key(key_id).await? {
+            return Ok(Some(jwk));
+        }
+
+        let now = Instant::now();
+        let has_cache = self.cache.read().await.is_some();
+        let limited = match *last_attempt {
+            Some(val) => has_cache && now.duration_since(val) < self.min_refresh_interval,
+            None => false,
+        };
+        if !limited {
+            *last_attempt = Some(now);
+            let entry = self.fetch().await?;
+            *self.cache.write().await = Some(entry);
+        }
+
+        match self.cache.read().await.as_ref() {
+            Some(entry) => Ok(entry.jwk_set.get_unique(key_id)?.cloned()),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch the JWK set and replace the cache.
+    ///
+    /// It is not limited by `min_refresh_interval`.
+    pub async fn refresh(&self) -> Result<(), JoseError> {
+        let mut last_attempt = self.last_attempt.lock().await;
+        *last_attempt = Some(Instant::now());
+        let entry = self.fetch().await?;
+        *self.cache.write().await = Some(entry);
+        Ok(())
+    }
+
+    async fn find_fresh_key(&self, key_id: &str) -> Result<Option<Jwk>, JoseError> {
+        let cache = self.cache.read().await;
+        match cache.as_ref() {
+            Some(entry) if Instant::now() < entry.expires_at => {
+                Ok(entry.jwk_set.get_unique(key_id)?.cloned())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn fetch(&self) -> Result<CacheEntry, JoseError> {
+        let (body, max_age) = async {
+            let res = self.client.get(&self.url).send().await?;
+            if !res.status().is_success() {
+                bail!("Failed to fetch the JWK set: {}", res.status());
+            }
+
+            let max_age = match res.headers().get(reqwest::header::CACHE_CONTROL) {
+                Some(val) => parse_max_age(val.to_str()?),
+                None => None,
+            };
+            let body = res.bytes().await?;
+            Ok((body, max_age))
+        }
+        .await
+        .map_err(JoseError::InvalidJwkFormat)?;
+
+        let jwk_set = JwkSet::from_bytes(&body)?;
+        Ok(CacheEntry {
+            jwk_set,
+            expires_at: Instant::now() + max_age.unwrap_or(self.default_max_age),
+        })
+    }
+}
+
+fn parse_max_age(value: &str) -> Option<Duration> {
+    let mut max_age = None;
+    for directive in value.split(',') {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        {
+            return Some(Duration::from_secs(0));
+        }
+
+        let mut parts = directive.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("max-age") {
+            if let Some(Ok(secs)) = parts.next().map(|val| val.trim().trim_matches('"').parse()) {
+                max_age = Some(Duration::from_secs(secs));
+            }
+        }
+    }
+    max_age
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use anyhow::Result;
+
+    use super::{parse_max_age, JwkSetFetcher};
+
+    const JWK_SET: &str = r#"{"keys":[{"kty":"oct","kid":"1","k":"AAAA"}]}"#;
+
+    #[test]
+    fn test_parse_max_age() {
+        assert_eq!(
+            parse_max_age("public, max-age=3600"),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            parse_max_age("Max-Age=\"60\""),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_max_age("max-age=60, no-store"),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_max_age("public"), None);
+        assert_eq!(parse_max_age("max-age=abc"), None);
+    }
+
+    #[test]
+    fn test_fetcher_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<JwkSetFetcher>();
+    }
+
+    #[test]
+    fn test_fetcher_caches_jwk_set() -> Result<()> {
+        let (url, requests) = serve(JWK_SET, "max-age=60", Duration::from_millis(0))?;
+        let fetcher = JwkSetFetcher::new(url);
+
+        block_on(async {
+            assert!(fetcher.get_key("1").await?.is_some());
+            assert!(fetcher.get_key("1").await?.is_some());
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+            // A unknown key ID doesn't fetch again within the minimum interval.
+            assert!(fetcher.get_key("2").await?.is_none());
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+            fetcher.refresh().await?;
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fetcher_refreshes_unknown_key_id() -> Result<()> {
+        let (url, requests) = serve(JWK_SET, "max-age=60", Duration::from_millis(0))?;
+        let mut fetcher = JwkSetFetcher::new(url);
+        fetcher.set_min_refresh_interval(Duration::from_millis(0));
+
+        block_on(async {
+            assert!(fetcher.get_key("1").await?.is_some());
+            assert!(fetcher.get_key("2").await?.is_none());
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fetcher_limits_refresh_of_expired_cache() -> Result<()> {
+        let (url, requests) = serve(JWK_SET, "no-store", Duration::from_millis(0))?;
+        let fetcher = JwkSetFetcher::new(url);
+
+        block_on(async {
+            // The expired JWK set is used until the minimum interval has elapsed.
+            for _ in 0..3 {
+                assert!(fetcher.get_key("1").await?.is_some());
+                assert!(fetcher.get_key("2").await?.is_none());
+            }
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fetcher_fetches_once_for_concurrent_lookups() -> Result<()> {
+        let (url, requests) = serve(JWK_SET, "max-age=60", Duration::from_millis(200))?;
+        let fetcher = Arc::new(JwkSetFetcher::new(url));
+
+        block_on(async {
+            let mut handles = Vec::new();
+            for _ in 0..5 {
+                let fetcher = Arc::clone(&fetcher);
+                handles.push(tokio::spawn(async move { fetcher.get_key("1").await }));
+            }
+            for handle in handles {
+                assert!(handle.await??.is_some());
+            }
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fetcher_does_not_block_lookups_while_fetching() -> Result<()> {
+        let (url, requests) = serve(JWK_SET, "max-age=60", Duration::from_millis(500))?;
+        let mut fetcher = JwkSetFetcher::new(url);
+        fetcher.set_min_refresh_interval(Duration::from_millis(0));
+        let fetcher = Arc::new(fetcher);
+
+        block_on(async {
+            assert!(fetcher.get_key("1").await?.is_some());
+
+            let refreshing = Arc::clone(&fetcher);
+            let handle = tokio::spawn(async move { refreshing.get_key("2").await });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            // The cached key is returned while the slow endpoint is fetched.
+            let started = Instant::now();
+            assert!(fetcher.get_key("1").await?.is_some());
+            assert!(started.elapsed() < Duration::from_millis(300));
+
+            assert!(handle.await??.is_none());
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+            Ok(())
+        })
+    }
+
+    fn block_on<F: std::future::Future<Output = Result<()>>>(future: F) -> Result<()> {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?
+            .block_on(future)
+    }
+
+    /// Serve the JWK set by a local HTTP server, and return the URL and the request counter.
+    fn serve(
+        body: &'static str,
+        cache_control: &'static str,
+        delay: Duration,
+    ) -> Result<(String, Arc<AtomicUsize>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/jwks.json", listener.local_addr()?);
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(val) => val,
+                    Err(_) => break,
+                };
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|val| val == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(delay);
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Cache-Control: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    cache_control,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        Ok((url, requests))
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use crate::jwk::Jwk;
+use crate::jwk::{Jwk, PublicKeyPair};
 
 pub trait KeyPair: Debug + Send + Sync {
     /// Return the applicatable algorithm.
@@ -17,6 +17,16 @@ pub trait KeyPair: Debug + Send + Sync {
     fn to_jwk_public_key(&self) -> Jwk;
     fn to_jwk_key_pair(&self) -> Jwk;
 
+    /// Return a key pair that has only the public key of this key pair.
+    fn to_public_key_pair(&self) -> PublicKeyPair {
+        PublicKeyPair::new(
+            self.algorithm(),
+            self.key_id(),
+            &self.to_der_public_key(),
+            self.to_jwk_public_key(),
+        )
+    }
+
     fn box_clone(&self) -> Box<dyn KeyPair>;
 }
 
@@ -0,0 +1,68 @@
+use openssl::pkey::{PKey, Public};
+
+use crate::jwk::Jwk;
+
+/// Represents a key pair that has only the public key.
+///
+/// It is created by KeyPair::to_public_key_pair and has no private key material,
+/// so it can be shared with a component that must not be able to sign or decrypt.
+#[derive(Debug, Clone)]
+pub struct PublicKeyPair {
+    algorithm: Option<String>,
+    key_id: Option<String>,
+    public_key: PKey<Public>,
+    jwk: Jwk,
+}
+
+impl PublicKeyPair {
+    pub(crate) fn new(
+        algorithm: Option<&str>,
+        key_id: Option<&str>,
+        public_key_der: &[u8],
+        jwk: Jwk,
+    ) -> Self {
+        Self {
+            algorithm: algorithm.map(|val| val.to_string()),
+            key_id: key_id.map(|val| val.to_string()),
+            public_key: PKey::public_key_from_der(public_key_der).unwrap(),
+            jwk,
+        }
+    }
+
+    /// Return the applicatable algorithm.
+    pub fn algorithm(&self) -> Option<&str> {
+        self.algorithm.as_deref()
+    }
+
+    /// Return the applicatable key ID.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// Return the public key.
+    pub fn public_key(&self) -> &PKey<Public> {
+        &self.public_key
+    }
+
+    pub fn to_der_public_key(&self) -> Vec<u8> {
+        self.public_key.public_key_to_der().unwrap()
+    }
+
+    pub fn to_pem_public_key(&self) -> Vec<u8> {
+        self.public_key.public_key_to_pem().unwrap()
+    }
+
+    pub fn to_jwk_public_key(&self) -> Jwk {
+        self.jwk.clone()
+    }
+}
+
+impl PartialEq for PublicKeyPair {
+    fn eq(&self, other: &Self) -> bool {
+        self.algorithm == other.algorithm
+            && self.key_id == other.key_id
+            && self.public_key.public_eq(&other.public_key)
+    }
+}
+
+impl Eq for PublicKeyPair {}
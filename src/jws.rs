@@ -1,15 +1,22 @@
 //! JSON Web Signature (JWS) support.
 
 pub mod alg;
+mod external_signer;
+mod external_verifier;
 mod jws_algorithm;
 mod jws_context;
 mod jws_header;
 mod jws_header_set;
 
+use anyhow::bail;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 
-use crate::JoseError;
+use crate::jwk::Jwk;
+use crate::{JoseError, Map, SegmentCountError, Value};
 
+pub use crate::jws::external_signer::ExternalJwsSigner;
+pub use crate::jws::external_verifier::ExternalJwsVerifier;
 pub use crate::jws::jws_algorithm::JwsAlgorithm;
 pub use crate::jws::jws_algorithm::JwsSigner;
 pub use crate::jws::jws_algorithm::JwsVerifier;
@@ -43,6 +50,15 @@ pub use EddsaJwsAlgorithm::Eddsa as EdDSA;
 
 static DEFAULT_CONTEXT: Lazy<JwsContext> = Lazy::new(|| JwsContext::new());
 
+/// Return the JWS alg header claim values that are supported by this crate.
+///
+/// It is useful to build a metadata (e.g. id_token_signing_alg_values_supported of
+/// OpenID Connect Discovery). ES256K is contained even if it is disabled by
+/// `JwsContext::set_secp256k1_enabled`.
+pub fn supported_algorithms() -> &'static [&'static str] {
+    JwsContext::SUPPORTED_ALGORITHMS
+}
+
 /// Return a representation of the data that is formatted by compact serialization.
 ///
 /// # Arguments
@@ -173,6 +189,26 @@ where
     DEFAULT_CONTEXT.deserialize_compact_with_selector(input, selector)
 }
 
+/// Verify a detached compact serialization whose payload is a serializable value,
+/// and return the decoded JWS header.
+///
+/// # Arguments
+///
+/// * `header` - The first part of the compact serialization (base64url encoded).
+/// * `payload` - The detached payload value.
+/// * `signature` - The third part of the compact serialization (base64url encoded).
+/// * `canonical` - true if the payload is serialized to the canonical JSON.
+/// * `verifier` - The JWS verifier.
+pub fn verify_detached_typed<T: Serialize>(
+    header: &str,
+    payload: &T,
+    signature: &str,
+    canonical: bool,
+    verifier: &dyn JwsVerifier,
+) -> Result<JwsHeader, JoseError> {
+    DEFAULT_CONTEXT.verify_detached_typed(header, payload, signature, canonical, verifier)
+}
+
 /// Deserialize the input that is formatted by json serialization.
 ///
 /// # Arguments
@@ -204,15 +240,175 @@ where
     DEFAULT_CONTEXT.deserialize_json_with_selector(input, selector)
 }
 
+/// Deserialize the input that is formatted by general json serialization
+/// and requires that at least N signatures are verified by distinct verifiers.
+///
+/// Verifiers that have the same public key are counted once. A verifier that has
+/// no public key (e.g. HMAC) is distinguished only by its position in `verifiers`.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `verifiers` - The JWS verifiers.
+/// * `n` - The minimum number of verified signatures.
+pub fn deserialize_general_json_quorum(
+    input: impl AsRef<[u8]>,
+    verifiers: &[&dyn JwsVerifier],
+    n: usize,
+) -> Result<(Vec<u8>, Vec<JwsHeader>), JoseError> {
+    DEFAULT_CONTEXT.deserialize_general_json_quorum(input, verifiers, n)
+}
+
+/// Convert a compact serialization into a flattened json serialization without re-signing.
+///
+/// The segments are moved into the protected, payload and signature members as they are,
+/// so the signature is kept byte-identical.
+///
+/// # Arguments
+///
+/// * `input` - The input data that is formatted by compact serialization.
+pub fn compact_to_flattened(input: &str) -> Result<String, JoseError> {
+    (|| -> anyhow::Result<String> {
+        let parts: Vec<&str> = input.split('.').collect();
+        if parts.len() != 3 {
+            return Err(SegmentCountError {
+                expected: 3,
+                found: parts.len(),
+            }
+            .into());
+        }
+        if parts[0].is_empty() {
+            bail!("The protected header of compact serialization must not be empty.");
+        }
+
+        let mut map = Map::new();
+        map.insert("protected".to_string(), Value::String(parts[0].to_string()));
+        map.insert("payload".to_string(), Value::String(parts[1].to_string()));
+        map.insert("signature".to_string(), Value::String(parts[2].to_string()));
+        Ok(serde_json::to_string(&map)?)
+    })()
+    .map_err(JoseError::InvalidJwsFormat)
+}
+
+/// Convert a flattened json serialization into a compact serialization without re-signing.
+///
+/// A unprotected header cannot be represented by compact serialization,
+/// so a input that has the header member is rejected.
+///
+/// # Arguments
+///
+/// * `input` - The input data that is formatted by flattened json serialization.
+pub fn flattened_to_compact(input: &str) -> Result<String, JoseError> {
+    (|| -> anyhow::Result<String> {
+        let map: Map<String, Value> = serde_json::from_str(input)?;
+        if map.contains_key("signatures") {
+            bail!("The general json serialization cannot be converted to compact serialization.");
+        }
+        if map.contains_key("header") {
+            bail!("The unprotected header cannot be represented by compact serialization.");
+        }
+
+        let mut parts = Vec::with_capacity(3);
+        for key in &["protected", "payload", "signature"] {
+            match map.get(*key) {
+                Some(Value::String(val)) if val.contains('.') => {
+                    bail!("The {} member must not contain a period.", key)
+                }
+                Some(Value::String(val)) => parts.push(val.as_str()),
+                Some(_) => bail!("The {} member must be a string.", key),
+                None => bail!("The {} member is required.", key),
+            }
+        }
+        if parts[0].is_empty() {
+            bail!("The protected member must not be empty.");
+        }
+
+        Ok(parts.join("."))
+    })()
+    .map_err(JoseError::InvalidJwsFormat)
+}
+
+/// Return a verifier that is selected by the parameters of a JWK.
+///
+/// The algorithm is selected by the alg parameter. If it is absent,
+/// the algorithm is inferred from the kty and crv parameters
+/// (only EC and OKP keys can be inferred).
+///
+/// # Arguments
+///
+/// * `jwk` - A public key that is formatted by a JWK.
+pub fn verifier_from_jwk(jwk: &Jwk) -> Result<Box<dyn JwsVerifier>, JoseError> {
+    (|| -> anyhow::Result<Box<dyn JwsVerifier>> {
+        let alg = match jwk.algorithm() {
+            Some(val) => val,
+            None => match (jwk.key_type(), jwk.curve()) {
+                ("EC", Some("P-256")) => "ES256",
+                ("EC", Some("P-384")) => "ES384",
+                ("EC", Some("P-521")) => "ES512",
+                ("EC", Some("secp256k1")) => "ES256K",
+                ("OKP", Some("Ed25519")) | ("OKP", Some("Ed448")) => "EdDSA",
+                (kty, crv) => {
+                    bail!(
+                        "A JWS algorithm cannot be inferred from the JWK: kty={}, crv={}",
+                        kty,
+                        crv.unwrap_or("none")
+                    )
+                }
+            },
+        };
+
+        let verifier: Box<dyn JwsVerifier> = match alg {
+            "HS256" => Box::new(HS256.verifier_from_jwk(jwk)?),
+            "HS384" => Box::new(HS384.verifier_from_jwk(jwk)?),
+            "HS512" => Box::new(HS512.verifier_from_jwk(jwk)?),
+            "RS256" => Box::new(RS256.verifier_from_jwk(jwk)?),
+            "RS384" => Box::new(RS384.verifier_from_jwk(jwk)?),
+            "RS512" => Box::new(RS512.verifier_from_jwk(jwk)?),
+            "PS256" => Box::new(PS256.verifier_from_jwk(jwk)?),
+            "PS384" => Box::new(PS384.verifier_from_jwk(jwk)?),
+            "PS512" => Box::new(PS512.verifier_from_jwk(jwk)?),
+            "ES256" => Box::new(ES256.verifier_from_jwk(jwk)?),
+            "ES384" => Box::new(ES384.verifier_from_jwk(jwk)?),
+            "ES512" => Box::new(ES512.verifier_from_jwk(jwk)?),
+            "ES256K" => Box::new(ES256K.verifier_from_jwk(jwk)?),
+            "EdDSA" => Box::new(EdDSA.verifier_from_jwk(jwk)?),
+            _ => {
+                bail!(
+                    "The JWK is not supported for a JWS verifier: kty={}, crv={}, alg={}",
+                    jwk.key_type(),
+                    jwk.curve().unwrap_or("none"),
+                    alg
+                )
+            }
+        };
+        Ok(verifier)
+    })()
+    .map_err(|err| match err.downcast::<JoseError>() {
+        Ok(err) => err,
+        Err(err) => JoseError::InvalidKeyFormat(err),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::io::{self, Read};
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     use anyhow::Result;
+    use serde::Serialize;
+    use serde_json::json;
 
-    use crate::jws::{self, EdDSA, JwsHeader, JwsHeaderSet, ES256, RS256};
-    use crate::Value;
+    use crate::jwk::{Ed25519, Jwk, KeyPair, P_256};
+    use crate::jws::{
+        self, EdDSA, JwsAlgorithm, JwsContext, JwsHeader, JwsHeaderSet, ES256, ES256K, ES384,
+        ES512, HS256, HS384, HS512, PS256, PS384, PS512, RS256, RS384, RS512,
+    };
+    use crate::jwt::{self, JwtContext, JwtPayload};
+    use crate::util;
+    use crate::{JoseError, Map, Value};
 
     #[test]
     fn test_jws_compact_serialization() -> Result<()> {
@@ -237,6 +433,599 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jws_sign_reader() -> Result<()> {
+        let message = {
+            let mut message = Vec::new();
+            io::repeat(b'a')
+                .take(5 * 1024 * 1024)
+                .read_to_end(&mut message)?;
+            message
+        };
+
+        let signer = ES256.signer_from_pem(&load_file("pem/EC_P-256_private.pem")?)?;
+        let verifier = ES256.verifier_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+        let signature = signer.sign_reader(&mut io::repeat(b'a').take(5 * 1024 * 1024))?;
+        verifier.verify(&message, &signature)?;
+
+        let signer = EdDSA.signer_from_pem(&load_file("pem/ED25519_private.pem")?)?;
+        let verifier = EdDSA.verifier_from_pem(&load_file("pem/ED25519_public.pem")?)?;
+        let signature = signer.sign_reader(&mut io::repeat(b'a').take(5 * 1024 * 1024))?;
+        verifier.verify(&message, &signature)?;
+
+        let signer = RS256.signer_from_pem(&load_file("pem/RSA_2048bit_private.pem")?)?;
+        let signature = signer.sign_reader(&mut message.as_slice())?;
+        assert_eq!(signature, signer.sign(&message)?);
+
+        let signer = HS256.signer_from_bytes(&util::random_bytes(32))?;
+        let signature = signer.sign_reader(&mut message.as_slice())?;
+        assert_eq!(signature, signer.sign(&message)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_header_from_serialize() -> Result<()> {
+        #[derive(Serialize)]
+        struct Header {
+            alg: String,
+            kid: String,
+            typ: String,
+            crit: Vec<String>,
+            #[serde(rename = "x-tenant")]
+            tenant: String,
+        }
+
+        let header = JwsHeader::from_serialize(&Header {
+            alg: "HS256".to_string(),
+            kid: "key-1".to_string(),
+            typ: "JWT".to_string(),
+            crit: vec!["x-tenant".to_string()],
+            tenant: "tenant-a".to_string(),
+        })?;
+        assert_eq!(header.algorithm(), Some("HS256"));
+        assert_eq!(header.key_id(), Some("key-1"));
+        assert_eq!(header.token_type(), Some("JWT"));
+        assert_eq!(header.critical(), Some(vec!["x-tenant"]));
+        assert_eq!(
+            header.claim("x-tenant"),
+            Some(&Value::String("tenant-a".to_string()))
+        );
+
+        #[derive(Serialize)]
+        struct InvalidHeader {
+            kid: u32,
+        }
+        assert!(JwsHeader::from_serialize(&InvalidHeader { kid: 1 }).is_err());
+        assert!(matches!(
+            JwsHeader::from_serialize(&"HS256"),
+            Err(JoseError::InvalidJson(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_reject_algorithm_confusion() -> Result<()> {
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        // The RSA public key is used as a HMAC secret.
+        let signer = HS256.signer_from_bytes(&public_key)?;
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+
+        let verifier = RS256.verifier_from_pem(&public_key)?;
+        let err = jws::deserialize_compact(&jws, &verifier).unwrap_err();
+        assert!(matches!(err, JoseError::InvalidJwsFormat(_)));
+        let confusion = err.algorithm_confusion_error().unwrap();
+        assert_eq!(confusion.expected, "RS256");
+        assert_eq!(confusion.found, "HS256");
+
+        let err = jwt::decode_with_verifier(&jws, &verifier).unwrap_err();
+        assert!(err.algorithm_confusion_error().is_some());
+
+        // A mismatch between asymmetric algorithms is not a confusion.
+        let signer = RS256.signer_from_pem(&load_file("pem/RSA_2048bit_private.pem")?)?;
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+        let verifier = ES256.verifier_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+        let err = jws::deserialize_compact(&jws, &verifier).unwrap_err();
+        assert!(err.algorithm_confusion_error().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_secp256k1_enabled() -> Result<()> {
+        let signer = ES256K.signer_from_pem(&load_file("pem/EC_secp256k1_private.pem")?)?;
+        let verifier = ES256K.verifier_from_pem(&load_file("pem/EC_secp256k1_public.pem")?)?;
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+
+        let mut context = JwsContext::new();
+        assert!(context.is_secp256k1_enabled());
+        context.deserialize_compact(&jws, &verifier)?;
+
+        context.set_secp256k1_enabled(false);
+        assert!(context
+            .serialize_compact(b"test payload!", &JwsHeader::new(), &signer)
+            .is_err());
+        assert!(context.deserialize_compact(&jws, &verifier).is_err());
+
+        let mut header = JwsHeaderSet::new();
+        header.set_algorithm("ES256K", true);
+        assert!(context
+            .serialize_flattened_json(b"test payload!", &header, &signer)
+            .is_err());
+
+        let key_pair = ES256K.generate_key_pair()?;
+        assert!(context.check_jwk(&key_pair.to_jwk_public_key()).is_err());
+
+        let jwt = jwt::encode_with_signer(&JwtPayload::new(), &JwsHeader::new(), &signer)?;
+        let mut context = JwtContext::new();
+        context.set_allowed_algorithms(&["ES256K"]);
+        context.decode_with_verifier(&jwt, &verifier)?;
+        context.set_secp256k1_enabled(false);
+        assert!(context.decode_with_verifier(&jwt, &verifier).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_critical_must_be_present() -> Result<()> {
+        let signer = HS256.signer_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+        let verifier = HS256.verifier_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+
+        let mut context = JwsContext::new();
+        context.add_acceptable_critical("htm");
+        context.add_acceptable_critical("htu");
+
+        let mut header = JwsHeader::new();
+        header.set_critical(&vec!["htm", "htu", "htm"]);
+        assert_eq!(header.critical(), Some(vec!["htm", "htu"]));
+        header.set_claim("htm", Some(json!("POST")))?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        assert!(matches!(
+            context.deserialize_compact(&jws, &verifier),
+            Err(JoseError::InvalidJwsFormat(err)) if err.to_string().contains("htu")
+        ));
+
+        header.set_claim("htu", Some(json!("https://example.com/token")))?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        context.deserialize_compact(&jws, &verifier)?;
+
+        let mut header = JwsHeaderSet::new();
+        header.set_critical(&vec!["htm", "htm"]);
+        assert_eq!(header.critical(), Some(vec!["htm"]));
+        header.set_claim("htm", Some(json!("POST")), false)?;
+        let json = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        assert!(context.deserialize_json(&json, &verifier).is_err());
+
+        header.set_claim("htm", Some(json!("POST")), true)?;
+        let json = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        context.deserialize_json(&json, &verifier)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_supported_algorithms() -> Result<()> {
+        let algs: Vec<&dyn JwsAlgorithm> = vec![
+            &HS256, &HS384, &HS512, &RS256, &RS384, &RS512, &PS256, &PS384, &PS512, &ES256, &ES384,
+            &ES512, &ES256K, &EdDSA,
+        ];
+        let supported = jws::supported_algorithms();
+        assert_eq!(supported.len(), algs.len() + 1);
+        assert!(supported.contains(&"none"));
+        for alg in algs {
+            assert!(supported.contains(&alg.name()), "{}", alg.name());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_unsupported_algorithm() -> Result<()> {
+        let verifier = HS256.verifier_from_bytes(&util::random_bytes(32))?;
+
+        let header = serde_json::to_vec(&json!({ "alg": "HS1024" }))?;
+        let header_b64 = base64::encode_config(header, base64::URL_SAFE_NO_PAD);
+
+        let compact = format!("{}.e30.AAAA", header_b64);
+        match jws::deserialize_compact(&compact, &verifier) {
+            Err(JoseError::UnsupportedAlgorithm(val)) => assert_eq!(val, "HS1024"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let json = json!({ "protected": header_b64, "payload": "e30", "signature": "AAAA" });
+        match jws::deserialize_json(&serde_json::to_vec(&json)?, &verifier) {
+            Err(JoseError::UnsupportedAlgorithm(val)) => assert_eq!(val, "HS1024"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_jwk_alg_member() -> Result<()> {
+        let oct = Jwk::generate_oct_key(64)?;
+        let rsa = Jwk::generate_rsa_key(2048)?;
+        let ec = Jwk::generate_ec_key(P_256)?;
+        let ed = Jwk::generate_ed_key(Ed25519)?;
+
+        util::check_jwk_alg_member(&oct, "HS256", |jwk| {
+            HS256.signer_from_jwk(jwk)?;
+            HS256.verifier_from_jwk(jwk)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&rsa, "RS256", |jwk| {
+            RS256.signer_from_jwk(jwk)?;
+            RS256.verifier_from_jwk(&jwk.to_public_key()?)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&rsa, "PS256", |jwk| {
+            PS256.signer_from_jwk(jwk)?;
+            PS256.verifier_from_jwk(&jwk.to_public_key()?)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&ec, "ES256", |jwk| {
+            ES256.signer_from_jwk(jwk)?;
+            ES256.verifier_from_jwk(&jwk.to_public_key()?)?;
+            Ok(())
+        })?;
+        util::check_jwk_alg_member(&ed, "EdDSA", |jwk| {
+            EdDSA.signer_from_jwk(jwk)?;
+            EdDSA.verifier_from_jwk(&jwk.to_public_key()?)?;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_transcode_compact_and_flattened() -> Result<()> {
+        let signer = ES256.signer_from_pem(&load_file("pem/EC_P-256_private.pem")?)?;
+        let verifier = ES256.verifier_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+
+        let mut header = JwsHeader::new();
+        header.set_key_id("key-1");
+        let compact = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        let parts: Vec<&str> = compact.split('.').collect();
+
+        let flattened = jws::compact_to_flattened(&compact)?;
+        let map: Map<String, Value> = serde_json::from_str(&flattened)?;
+        assert_eq!(map.get("protected"), Some(&json!(parts[0])));
+        assert_eq!(map.get("payload"), Some(&json!(parts[1])));
+        assert_eq!(map.get("signature"), Some(&json!(parts[2])));
+        assert_eq!(map.get("header"), None);
+
+        let (payload, header) = jws::deserialize_json(&flattened, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+        assert_eq!(header.key_id(), Some("key-1"));
+
+        assert_eq!(jws::flattened_to_compact(&flattened)?, compact);
+
+        let mut header = JwsHeaderSet::new();
+        header.set_key_id("key-1", true);
+        let flattened = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        let compact = jws::flattened_to_compact(&flattened)?;
+        let (payload, _) = jws::deserialize_compact(&compact, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+        assert_eq!(jws::compact_to_flattened(&compact)?, flattened);
+
+        let mut header = JwsHeaderSet::new();
+        header.set_key_id("key-1", false);
+        let flattened = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        assert!(matches!(
+            jws::flattened_to_compact(&flattened),
+            Err(JoseError::InvalidJwsFormat(_))
+        ));
+
+        let err = jws::compact_to_flattened("a.b").unwrap_err();
+        assert!(err.segment_count_error().is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_json_duplicate_header_claim() -> Result<()> {
+        let signer = HS256.signer_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+        let verifier = HS256.verifier_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+
+        let mut header = JwsHeaderSet::new();
+        header.set_algorithm("HS256", true);
+        header.set_key_id("kid", false);
+        let json = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        jws::deserialize_json(&json, &verifier)?;
+
+        let mut map: Map<String, Value> = serde_json::from_str(&json)?;
+        map.insert("header".to_string(), json!({"kid": "kid", "alg": "HS256"}));
+        let json = serde_json::to_string(&map)?;
+        match jws::deserialize_json(&json, &verifier) {
+            Err(JoseError::InvalidJwsFormat(err)) => {
+                assert_eq!(err.to_string(), "A duplicate key exists: alg")
+            }
+            res => panic!("{:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_critical_handler() -> Result<()> {
+        let signer = HS256.signer_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+        let verifier = HS256.verifier_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+
+        let mut header = JwsHeader::new();
+        header.set_critical(&vec!["x-level"]);
+        header.set_claim("x-level", Some(Value::from(5)))?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+
+        let mut context = JwsContext::new();
+        assert!(context.deserialize_compact(&jws, &verifier).is_err());
+
+        // A acceptable critical header claim without a handler passes as is.
+        context.add_acceptable_critical("x-level");
+        context.deserialize_compact(&jws, &verifier)?;
+
+        context.set_critical_handler("x-level", |header| match header.claim("x-level") {
+            Some(Value::Number(val)) if val.as_u64().map_or(false, |val| val <= 3) => Ok(()),
+            _ => Err(JoseError::InvalidJwsFormat(anyhow::anyhow!(
+                "The x-level header claim is invalid."
+            ))),
+        });
+        assert!(matches!(
+            context.deserialize_compact(&jws, &verifier),
+            Err(JoseError::InvalidJwsFormat(_))
+        ));
+
+        header.set_claim("x-level", Some(Value::from(2)))?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        context.deserialize_compact(&jws, &verifier)?;
+
+        let mut header = JwsHeaderSet::new();
+        header.set_claim("crit", Some(json!(["x-level"])), true)?;
+        header.set_claim("x-level", Some(Value::from(5)), true)?;
+        let json = jws::serialize_flattened_json(b"test payload!", &header, &signer)?;
+        assert!(context.deserialize_json(&json, &verifier).is_err());
+
+        context.remove_critical_handler("x-level");
+        context.deserialize_json(&json, &verifier)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_critical_handler_after_verification() -> Result<()> {
+        let signer = HS256.signer_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+        let verifier = HS256.verifier_from_bytes(b"0123456789ABCDEF0123456789ABCDEF")?;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut context = JwsContext::new();
+        context.add_acceptable_critical("x-level");
+        let handler_calls = Arc::clone(&calls);
+        context.set_critical_handler("x-level", move |header| {
+            handler_calls.fetch_add(1, Ordering::SeqCst);
+            match header.claim("x-level") {
+                Some(Value::Number(val)) if val.as_u64().map_or(false, |val| val <= 3) => Ok(()),
+                _ => Err(JoseError::InvalidJwsFormat(anyhow::anyhow!(
+                    "The x-level header claim is invalid."
+                ))),
+            }
+        });
+
+        // A handler is not called with a header whose signature is invalid.
+        let mut header = JwsHeader::new();
+        header.set_critical(&vec!["x-level"]);
+        header.set_claim("x-level", Some(Value::from(2)))?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        let last = if jws.ends_with('A') { "B" } else { "A" };
+        let tampered = format!("{}{}", &jws[..jws.len() - 1], last);
+        assert!(context.deserialize_compact(&tampered, &verifier).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // A signature rejected by a handler is skipped in general json serialization.
+        let mut rejected_header = JwsHeaderSet::new();
+        rejected_header.set_claim("crit", Some(json!(["x-level"])), true)?;
+        rejected_header.set_claim("x-level", Some(Value::from(5)), true)?;
+        let mut accepted_header = JwsHeaderSet::new();
+        accepted_header.set_claim("crit", Some(json!(["x-level"])), true)?;
+        accepted_header.set_claim("x-level", Some(Value::from(2)), true)?;
+        let json = jws::serialize_general_json(
+            b"test payload!",
+            &[(&rejected_header, &signer), (&accepted_header, &signer)],
+        )?;
+        let (payload, header) = context.deserialize_json(&json, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+        assert_eq!(header.claim("x-level"), Some(&Value::from(2)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // The error of the handler is returned if no signature is accepted.
+        let json = jws::serialize_general_json(b"test payload!", &[(&rejected_header, &signer)])?;
+        match context.deserialize_json(&json, &verifier) {
+            Err(JoseError::InvalidJwsFormat(err)) => {
+                assert_eq!(err.to_string(), "The x-level header claim is invalid.")
+            }
+            res => panic!("{:?}", res),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_verify_detached_typed() -> Result<()> {
+        #[derive(Serialize)]
+        struct Order {
+            quantity: u32,
+            item: String,
+            options: Map<String, Value>,
+        }
+
+        let mut options = Map::new();
+        options.insert("wrap".to_string(), json!(true));
+        options.insert("color".to_string(), json!("red"));
+        let order = Order {
+            quantity: 2,
+            item: "book".to_string(),
+            options,
+        };
+
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+
+        let canonical = util::to_canonical_json(&serde_json::to_value(&order)?);
+        assert_eq!(
+            canonical,
+            r#"{"item":"book","options":{"color":"red","wrap":true},"quantity":2}"#
+        );
+        let jws = jws::serialize_compact(canonical.as_bytes(), &JwsHeader::new(), &signer)?;
+        let parts: Vec<&str> = jws.split('.').collect();
+
+        let header = jws::verify_detached_typed(parts[0], &order, parts[2], true, &verifier)?;
+        assert_eq!(header.algorithm(), Some("HS256"));
+        assert!(jws::verify_detached_typed(parts[0], &order, parts[2], false, &verifier).is_err());
+
+        let tampered = Order {
+            quantity: 3,
+            ..order
+        };
+        assert!(
+            jws::verify_detached_typed(parts[0], &tampered, parts[2], true, &verifier).is_err()
+        );
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_base64url_encode_payload(false);
+        src_header.set_critical(&vec!["b64"]);
+        let plain = serde_json::to_vec(&tampered)?;
+        let jws = jws::serialize_compact(&plain, &src_header, &signer)?;
+        let parts: Vec<&str> = jws.split('.').collect();
+
+        let mut context = JwsContext::new();
+        context.add_acceptable_critical("b64");
+        context.verify_detached_typed(parts[0], &tampered, parts[2], false, &verifier)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_verify_with_decoded_header() -> Result<()> {
+        let alg = HS256;
+
+        let private_key = util::random_bytes(64);
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        let src_payload = b"test payload!";
+        let signer = alg.signer_from_bytes(&private_key)?;
+        let jwt = jws::serialize_compact(src_payload, &src_header, &signer)?;
+
+        let verifier = alg.verifier_from_bytes(&private_key)?;
+        let (expected_payload, expected_header) = jws::deserialize_compact(&jwt, &verifier)?;
+
+        let parts: Vec<&str> = jwt.split('.').collect();
+        let header = base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?;
+        let header: Map<String, Value> = serde_json::from_slice(&header)?;
+        let header = JwsHeader::from_map(header)?;
+        assert_eq!(expected_header, header);
+
+        let signature = base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD)?;
+
+        let context = JwsContext::new();
+        let payload = context.verify_with_decoded_header(
+            &header,
+            parts[0].as_bytes(),
+            parts[1].as_bytes(),
+            &signature,
+            &verifier,
+        )?;
+        assert_eq!(expected_payload, payload);
+
+        let mut tampered = signature.clone();
+        tampered[0] ^= 1;
+        assert!(context
+            .verify_with_decoded_header(
+                &header,
+                parts[0].as_bytes(),
+                parts[1].as_bytes(),
+                &tampered,
+                &verifier,
+            )
+            .is_err());
+
+        // A payload that is not signed must not be returned as verified.
+        let other_payload = base64::encode_config(b"other payload", base64::URL_SAFE_NO_PAD);
+        assert!(context
+            .verify_with_decoded_header(
+                &header,
+                parts[0].as_bytes(),
+                other_payload.as_bytes(),
+                &signature,
+                &verifier,
+            )
+            .is_err());
+
+        // The decoded header must match the header part.
+        let mut other_header = header.clone();
+        other_header.set_key_id("other");
+        assert!(context
+            .verify_with_decoded_header(
+                &other_header,
+                parts[0].as_bytes(),
+                parts[1].as_bytes(),
+                &signature,
+                &verifier,
+            )
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_compact_serialization_with_empty_payload() -> Result<()> {
+        let alg = HS256;
+
+        let private_key = util::random_bytes(64);
+
+        let src_header = JwsHeader::new();
+        let src_payload = b"";
+        let signer = alg.signer_from_bytes(&private_key)?;
+        let jwt = jws::serialize_compact(src_payload, &src_header, &signer)?;
+
+        let parts: Vec<&str> = jwt.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1], "");
+
+        let verifier = alg.verifier_from_bytes(&private_key)?;
+        let (dst_payload, _) = jws::deserialize_compact(&jwt, &verifier)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_compact_serialization_with_private_header_claim() -> Result<()> {
+        let alg = HS256;
+
+        let private_key = util::random_bytes(64);
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        src_header.set_claim(
+            "x-private",
+            Some(json!({ "nested": { "list": [1, "two", null] }, "flag": true })),
+        )?;
+        let src_payload = b"test payload!";
+        let signer = alg.signer_from_bytes(&private_key)?;
+        let jwt = jws::serialize_compact(src_payload, &src_header, &signer)?;
+
+        let verifier = alg.verifier_from_bytes(&private_key)?;
+        let (_, dst_header) = jws::deserialize_compact(&jwt, &verifier)?;
+        assert_eq!(src_header.claim("x-private"), dst_header.claim("x-private"));
+
+        let jwt2 = jws::serialize_compact(src_payload, &dst_header, &signer)?;
+        assert_eq!(jwt.split('.').next(), jwt2.split('.').next());
+
+        Ok(())
+    }
+
     #[test]
     fn test_jws_json_serialization() -> Result<()> {
         let alg = RS256;
@@ -307,6 +1096,263 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jws_general_json_quorum() -> Result<()> {
+        let private_key_1 = load_file("pem/RSA_2048bit_private.pem")?;
+        let private_key_2 = load_file("pem/EC_P-256_private.pem")?;
+        let private_key_3 = load_file("pem/ED25519_private.pem")?;
+
+        let public_key_1 = load_file("pem/RSA_2048bit_public.pem")?;
+        let public_key_2 = load_file("pem/EC_P-256_public.pem")?;
+        let public_key_3 = load_file("pem/ED25519_public.pem")?;
+
+        let src_payload = b"test payload!";
+
+        let mut src_header_1 = JwsHeaderSet::new();
+        src_header_1.set_key_id("xxx-1", true);
+        let signer_1 = RS256.signer_from_pem(&private_key_1)?;
+
+        let mut src_header_2 = JwsHeaderSet::new();
+        src_header_2.set_key_id("xxx-2", true);
+        let signer_2 = ES256.signer_from_pem(&private_key_2)?;
+
+        let mut src_header_3 = JwsHeaderSet::new();
+        src_header_3.set_key_id("xxx-3", true);
+        let signer_3 = EdDSA.signer_from_pem(&private_key_3)?;
+
+        let json = jws::serialize_general_json(
+            src_payload,
+            &[
+                (&src_header_1, &*signer_1),
+                (&src_header_2, &*signer_2),
+                (&src_header_3, &*signer_3),
+            ],
+        )?;
+
+        let verifier_1 = RS256.verifier_from_pem(&public_key_1)?;
+        let verifier_2 = ES256.verifier_from_pem(&public_key_2)?;
+        let verifier_3 = EdDSA.verifier_from_pem(&public_key_3)?;
+        let invalid_verifier_3 = EdDSA.verifier_from_pem(&load_file("pem/ED448_public.pem")?)?;
+
+        let (dst_payload, dst_headers) =
+            jws::deserialize_general_json_quorum(&json, &[&*verifier_1, &*verifier_2], 2)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+        assert_eq!(dst_headers.len(), 2);
+        assert_eq!(dst_headers[0].key_id(), Some("xxx-1"));
+        assert_eq!(dst_headers[1].key_id(), Some("xxx-2"));
+
+        let (_, dst_headers) = jws::deserialize_general_json_quorum(
+            &json,
+            &[&*verifier_1, &*verifier_2, &*verifier_3],
+            2,
+        )?;
+        assert_eq!(dst_headers.len(), 3);
+
+        assert!(jws::deserialize_general_json_quorum(
+            &json,
+            &[&*verifier_1, &*invalid_verifier_3],
+            2
+        )
+        .is_err());
+        assert!(
+            jws::deserialize_general_json_quorum(&json, &[&*verifier_1, &*verifier_1], 2).is_err()
+        );
+
+        // Two verifiers of the same public key are counted once.
+        let verifier_1_copy = RS256.verifier_from_pem(&public_key_1)?;
+        assert!(
+            jws::deserialize_general_json_quorum(&json, &[&*verifier_1, &*verifier_1_copy], 2)
+                .is_err()
+        );
+
+        // The error contains the error of each signature that is not verified.
+        let mut tampered: Map<String, Value> = serde_json::from_str(&json)?;
+        if let Some(Value::Array(vals)) = tampered.get_mut("signatures") {
+            vals[1]["signature"] = Value::String(vals[0]["signature"].as_str().unwrap().into());
+        }
+        let err = jws::deserialize_general_json_quorum(
+            &serde_json::to_string(&tampered)?,
+            &[&*verifier_1, &*verifier_2],
+            2,
+        )
+        .unwrap_err();
+        assert!(format!("{}", err).contains("signatures[1]"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_context_key_policy() -> Result<()> {
+        let rsa_jwk = Jwk::from_bytes(&load_file("jwk/RSA_public.jwk")?)?;
+        let ec_jwk = Jwk::from_bytes(&load_file("jwk/EC_P-256_public.jwk")?)?;
+        let ed_jwk = Jwk::from_bytes(&load_file("jwk/OKP_Ed25519_public.jwk")?)?;
+
+        let mut context = JwsContext::new();
+        assert_eq!(context.minimum_rsa_bits(), 2048);
+        context.check_jwk(&rsa_jwk)?;
+        context.check_jwk(&ec_jwk)?;
+        context.check_jwk(&ed_jwk)?;
+
+        context.set_minimum_rsa_bits(3072);
+        assert!(context.check_jwk(&rsa_jwk).is_err());
+
+        context.set_allowed_curves(&["P-384", "Ed25519"]);
+        assert!(context.check_jwk(&ec_jwk).is_err());
+        context.check_jwk(&ed_jwk)?;
+
+        context.remove_allowed_curves();
+        context.check_jwk(&ec_jwk)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_context_key_policy_on_verification() -> Result<()> {
+        let rsa_private = Jwk::from_bytes(&load_file("jwk/RSA_private.jwk")?)?;
+        let rsa_public = Jwk::from_bytes(&load_file("jwk/RSA_public.jwk")?)?;
+        let ec_private = Jwk::from_bytes(&load_file("jwk/EC_P-256_private.jwk")?)?;
+        let ec_public = Jwk::from_bytes(&load_file("jwk/EC_P-256_public.jwk")?)?;
+
+        let rsa_signer = RS256.signer_from_jwk(&rsa_private)?;
+        let rsa_verifier = RS256.verifier_from_jwk(&rsa_public)?;
+        let ec_signer = ES256.signer_from_jwk(&ec_private)?;
+        let ec_verifier = ES256.verifier_from_jwk(&ec_public)?;
+
+        let header = JwsHeader::new();
+        let rsa_compact = jws::serialize_compact(b"test payload!", &header, &rsa_signer)?;
+        let ec_compact = jws::serialize_compact(b"test payload!", &header, &ec_signer)?;
+        let mut header_set = JwsHeaderSet::new();
+        header_set.set_algorithm("RS256", true);
+        let rsa_json = jws::serialize_flattened_json(b"test payload!", &header_set, &rsa_signer)?;
+
+        let mut context = JwsContext::new();
+        context.deserialize_compact(&rsa_compact, &rsa_verifier)?;
+        context.deserialize_json(&rsa_json, &rsa_verifier)?;
+        context.deserialize_compact(&ec_compact, &ec_verifier)?;
+
+        context.set_minimum_rsa_bits(3072);
+        let err = context
+            .deserialize_compact(&rsa_compact, &rsa_verifier)
+            .unwrap_err();
+        assert!(err.key_policy_error().is_some(), "{}", err);
+        let err = context
+            .deserialize_json(&rsa_json, &rsa_verifier)
+            .unwrap_err();
+        assert!(err.key_policy_error().is_some(), "{}", err);
+
+        context.set_allowed_curves(&["P-384"]);
+        let err = context
+            .deserialize_compact(&ec_compact, &ec_verifier)
+            .unwrap_err();
+        assert!(err.key_policy_error().is_some(), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_context_allow_short_hmac_key() -> Result<()> {
+        let key = util::random_bytes(16);
+        let jwk = HS256.to_jwk(&key);
+
+        let mut context = JwsContext::new();
+        assert!(!context.is_allow_short_hmac_key());
+        assert!(context.hmac_signer_from_bytes(HS256, &key).is_err());
+        assert!(context.hmac_signer_from_jwk(HS256, &jwk).is_err());
+        assert!(context.hmac_verifier_from_bytes(HS256, &key).is_err());
+        assert!(context.hmac_verifier_from_jwk(HS256, &jwk).is_err());
+
+        context.set_allow_short_hmac_key(true);
+        let signer = context.hmac_signer_from_bytes(HS256, &key)?;
+        let jws = context.serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+        let verifier = context.hmac_verifier_from_jwk(HS256, &jwk)?;
+        let (payload, _) = context.deserialize_compact(&jws, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+
+        assert!(HS256.verifier_from_bytes(&key).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_verify_x5c_matches_key() -> Result<()> {
+        let key_pair_1 = RS256.generate_key_pair(2048)?;
+        let key_pair_2 = RS256.generate_key_pair(2048)?;
+
+        let mut header = JwsHeader::new();
+        header.set_x509_certificate_chain(&vec![util::self_signed_certificate(
+            &key_pair_1.clone().into_private_key(),
+        )?]);
+        let signer = RS256.signer_from_key_pair(&key_pair_1)?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+
+        let verifier = RS256.verifier_from_pem(key_pair_1.to_pem_public_key())?;
+        let mut context = JwsContext::new();
+        context.set_verify_x5c_matches_key(true);
+        context.deserialize_compact(&jws, &verifier)?;
+
+        header.set_x509_certificate_chain(&vec![util::self_signed_certificate(
+            &key_pair_2.into_private_key(),
+        )?]);
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        assert!(context.deserialize_compact(&jws, &verifier).is_err());
+
+        context.set_verify_x5c_matches_key(false);
+        context.deserialize_compact(&jws, &verifier)?;
+
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let jws = jws::serialize_compact(b"test payload!", &header, &signer)?;
+        context.deserialize_compact(&jws, &verifier)?;
+        context.set_verify_x5c_matches_key(true);
+        assert!(context.deserialize_compact(&jws, &verifier).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_verifier_from_jwk() -> Result<()> {
+        let src_payload = b"test payload!";
+
+        let private_jwk = Jwk::from_bytes(load_file("jwk/EC_P-256_private.jwk")?)?;
+        let public_jwk = Jwk::from_bytes(load_file("jwk/EC_P-256_public.jwk")?)?;
+        let signer = ES256.signer_from_jwk(&private_jwk)?;
+        let jws = jws::serialize_compact(src_payload, &JwsHeader::new(), &signer)?;
+        let verifier = jws::verifier_from_jwk(&public_jwk)?;
+        assert_eq!(verifier.algorithm().name(), "ES256");
+        let (dst_payload, _) = jws::deserialize_compact(&jws, &*verifier)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let private_jwk = Jwk::from_bytes(load_file("jwk/OKP_Ed25519_private.jwk")?)?;
+        let public_jwk = Jwk::from_bytes(load_file("jwk/OKP_Ed25519_public.jwk")?)?;
+        let signer = EdDSA.signer_from_jwk(&private_jwk)?;
+        let jws = jws::serialize_compact(src_payload, &JwsHeader::new(), &signer)?;
+        let verifier = jws::verifier_from_jwk(&public_jwk)?;
+        let (dst_payload, _) = jws::deserialize_compact(&jws, &*verifier)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        let mut public_jwk = Jwk::from_bytes(load_file("jwk/RSA_public.jwk")?)?;
+        let err = jws::verifier_from_jwk(&public_jwk).unwrap_err();
+        assert!(format!("{}", err).contains("kty=RSA"));
+        public_jwk.set_algorithm("PS384");
+        assert_eq!(
+            jws::verifier_from_jwk(&public_jwk)?.algorithm().name(),
+            "PS384"
+        );
+
+        let mut oct_jwk = Jwk::from_bytes(load_file("jwk/oct_512bit_private.jwk")?)?;
+        oct_jwk.set_algorithm("HS512");
+        assert_eq!(
+            jws::verifier_from_jwk(&oct_jwk)?.algorithm().name(),
+            "HS512"
+        );
+        oct_jwk.set_algorithm("HS1024");
+        let err = jws::verifier_from_jwk(&oct_jwk).unwrap_err();
+        assert!(format!("{}", err).contains("alg=HS1024"));
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -1,13 +1,15 @@
 use std::fmt::Display;
+use std::io::{self, Read};
 use std::ops::Deref;
 
 use anyhow::bail;
+use openssl::ecdsa::EcdsaSig;
 use openssl::pkey::{PKey, Private, Public};
 use openssl::sign::{Signer, Verifier};
 
 use crate::jwk::{
     alg::ec::{EcCurve, EcKeyPair},
-    Jwk,
+    Jwk, KeyPair,
 };
 use crate::jws::{JwsAlgorithm, JwsSigner, JwsVerifier};
 use crate::util::der::{DerBuilder, DerReader, DerType};
@@ -92,6 +94,43 @@ impl EcdsaJwsAlgorithm {
         })
     }
 
+    /// Return a signer from a EC key pair.
+    ///
+    /// The key pair is not parsed again, so many signers can be created from a parsed key pair cheaply.
+    ///
+    /// # Arguments
+    /// * `key_pair` - A EC key pair.
+    pub fn signer_from_key_pair(&self, key_pair: &EcKeyPair) -> Result<EcdsaJwsSigner, JoseError> {
+        (|| -> anyhow::Result<EcdsaJwsSigner> {
+            match key_pair.algorithm() {
+                Some(val) if val == self.name() => {}
+                None => {}
+                Some(val) => bail!(
+                    "A algorithm of key pair must be {} but {}",
+                    self.name(),
+                    val
+                ),
+            }
+            if key_pair.curve() != self.curve() {
+                bail!(
+                    "A curve of key pair must be {} but {}",
+                    self.curve(),
+                    key_pair.curve()
+                );
+            }
+
+            let private_key = key_pair.clone().into_private_key();
+            let key_id = key_pair.key_id().map(|val| val.to_string());
+
+            Ok(EcdsaJwsSigner {
+                algorithm: *self,
+                private_key,
+                key_id,
+            })
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
+
     /// Return a signer from a private key that is formatted by a JWK of EC type.
     ///
     /// # Arguments
@@ -113,7 +152,11 @@ impl EcdsaJwsAlgorithm {
             }
             match jwk.curve() {
                 Some(val) if val == self.curve().name() => {}
-                Some(val) => bail!("A parameter crv must be {} but {}", self.name(), val),
+                Some(val) => bail!(
+                    "A parameter crv must be {} but {}",
+                    self.curve().name(),
+                    val
+                ),
                 None => bail!("A parameter crv is required."),
             }
 
@@ -232,15 +275,14 @@ impl EcdsaJwsAlgorithm {
                 None => bail!("A parameter x is required."),
             };
             let y = match jwk.parameter("y") {
-                Some(Value::String(val)) => base64::decode_config(val, base64::URL_SAFE_NO_PAD)?,
+                Some(Value::String(val)) => {
+                    Some(base64::decode_config(val, base64::URL_SAFE_NO_PAD)?)
+                }
                 Some(_) => bail!("A parameter y must be a string."),
-                None => bail!("A parameter y is required."),
+                None => None,
             };
 
-            let mut vec = Vec::with_capacity(1 + x.len() + y.len());
-            vec.push(0x04);
-            vec.extend_from_slice(&x);
-            vec.extend_from_slice(&y);
+            let vec = EcKeyPair::to_uncompressed_point(&x, y.as_deref(), self.curve())?;
 
             let pkcs8 = EcKeyPair::to_pkcs8(&vec, true, self.curve());
             let public_key = PKey::public_key_from_der(&pkcs8)?;
@@ -326,6 +368,65 @@ impl EcdsaJwsSigner {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return a signature of a externally computed digest.
+    ///
+    /// The digest must be a hash value of the message by the hash algorithm of this signer.
+    ///
+    /// # Arguments
+    ///
+    /// * `digest` - The digest of the message to sign.
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let hash_algorithm = self.algorithm.hash_algorithm();
+            if digest.len() != hash_algorithm.output_len() {
+                bail!(
+                    "The length of {} digest must be {}: {}",
+                    hash_algorithm,
+                    hash_algorithm.output_len(),
+                    digest.len()
+                );
+            }
+
+            let ec_key = self.private_key.ec_key()?;
+            let ecdsa_sig = EcdsaSig::sign(digest, &ec_key)?;
+
+            let signature_len = self.signature_len();
+            let sep = signature_len / 2;
+
+            let mut signature = Vec::with_capacity(signature_len);
+            signature.extend_from_slice(&util::num_to_vec(ecdsa_sig.r(), sep));
+            signature.extend_from_slice(&util::num_to_vec(ecdsa_sig.s(), sep));
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
+
+    fn to_signature(&self, der_signature: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let signature_len = self.signature_len();
+        let sep = signature_len / 2;
+
+        let mut signature = Vec::with_capacity(signature_len);
+        let mut reader = DerReader::from_bytes(&der_signature);
+        match reader.next()? {
+            Some(DerType::Sequence) => {}
+            _ => unreachable!("A generated signature is invalid."),
+        }
+        match reader.next()? {
+            Some(DerType::Integer) => {
+                signature.extend_from_slice(&reader.to_be_bytes(false, sep));
+            }
+            _ => unreachable!("A generated signature is invalid."),
+        }
+        match reader.next()? {
+            Some(DerType::Integer) => {
+                signature.extend_from_slice(&reader.to_be_bytes(false, sep));
+            }
+            _ => unreachable!("A generated signature is invalid."),
+        }
+
+        Ok(signature)
+    }
 }
 
 impl JwsSigner for EcdsaJwsSigner {
@@ -351,30 +452,19 @@ impl JwsSigner for EcdsaJwsSigner {
             let mut signer = Signer::new(md, &self.private_key)?;
             signer.update(message)?;
             let der_signature = signer.sign_to_vec()?;
+            self.to_signature(&der_signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
 
-            let signature_len = self.signature_len();
-            let sep = signature_len / 2;
-
-            let mut signature = Vec::with_capacity(signature_len);
-            let mut reader = DerReader::from_bytes(&der_signature);
-            match reader.next()? {
-                Some(DerType::Sequence) => {}
-                _ => unreachable!("A generated signature is invalid."),
-            }
-            match reader.next()? {
-                Some(DerType::Integer) => {
-                    signature.extend_from_slice(&reader.to_be_bytes(false, sep));
-                }
-                _ => unreachable!("A generated signature is invalid."),
-            }
-            match reader.next()? {
-                Some(DerType::Integer) => {
-                    signature.extend_from_slice(&reader.to_be_bytes(false, sep));
-                }
-                _ => unreachable!("A generated signature is invalid."),
-            }
+    fn sign_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let md = self.algorithm.hash_algorithm().message_digest();
 
-            Ok(signature)
+            let mut signer = Signer::new(md, &self.private_key)?;
+            io::copy(reader, &mut signer)?;
+            let der_signature = signer.sign_to_vec()?;
+            self.to_signature(&der_signature)
         })()
         .map_err(|err| JoseError::InvalidSignature(err))
     }
@@ -457,6 +547,10 @@ impl JwsVerifier for EcdsaJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn public_key(&self) -> Option<&PKey<Public>> {
+        Some(&self.public_key)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -544,6 +638,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_ecdsa_prehashed() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            EcdsaJwsAlgorithm::Es256,
+            EcdsaJwsAlgorithm::Es384,
+            EcdsaJwsAlgorithm::Es512,
+            EcdsaJwsAlgorithm::Es256k,
+        ] {
+            let key_pair = alg.generate_key_pair()?;
+
+            let signer = alg.signer_from_pem(key_pair.to_pem_private_key())?;
+            let md = alg.hash_algorithm().message_digest();
+            let digest = openssl::hash::hash(md, input)?;
+            let signature = signer.sign_prehashed(&digest)?;
+            assert_eq!(signature.len(), signer.signature_len());
+
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+            verifier.verify(input, &signature)?;
+
+            signer
+                .sign_prehashed(&digest[1..])
+                .expect_err("Invalid digest length did not fail");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_ecdsa_generated_traditional_pem() -> Result<()> {
         let input = b"abcde12345";
@@ -656,6 +779,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_ecdsa_traditional_pem() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            EcdsaJwsAlgorithm::Es256,
+            EcdsaJwsAlgorithm::Es384,
+            EcdsaJwsAlgorithm::Es512,
+            EcdsaJwsAlgorithm::Es256k,
+        ] {
+            println!("{}", alg);
+
+            let private_key = load_file(match alg {
+                EcdsaJwsAlgorithm::Es256 => "pem/EC_P-256_traditional_private.pem",
+                EcdsaJwsAlgorithm::Es384 => "pem/EC_P-384_traditional_private.pem",
+                EcdsaJwsAlgorithm::Es512 => "pem/EC_P-521_traditional_private.pem",
+                EcdsaJwsAlgorithm::Es256k => "pem/EC_secp256k1_traditional_private.pem",
+            })?;
+            let public_key = load_file(match alg {
+                EcdsaJwsAlgorithm::Es256 => "pem/EC_P-256_public.pem",
+                EcdsaJwsAlgorithm::Es384 => "pem/EC_P-384_public.pem",
+                EcdsaJwsAlgorithm::Es512 => "pem/EC_P-521_public.pem",
+                EcdsaJwsAlgorithm::Es256k => "pem/EC_secp256k1_public.pem",
+            })?;
+
+            let signer = alg.signer_from_pem(&private_key)?;
+            let signature = signer.sign(input)?;
+
+            let verifier = alg.verifier_from_pem(&public_key)?;
+            verifier.verify(input, &signature)?;
+        }
+
+        let private_key = load_file("pem/EC_P-256_traditional_private.pem")?;
+        assert!(EcdsaJwsAlgorithm::Es384
+            .signer_from_pem(&private_key)
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_ecdsa_pkcs8_der() -> Result<()> {
         let input = b"abcde12345";
@@ -689,6 +852,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_ecdsa_key_pair() -> Result<()> {
+        for alg in &[
+            EcdsaJwsAlgorithm::Es256,
+            EcdsaJwsAlgorithm::Es384,
+            EcdsaJwsAlgorithm::Es512,
+            EcdsaJwsAlgorithm::Es256k,
+        ] {
+            let key_pair = alg.generate_key_pair()?;
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+
+            for i in 0..3 {
+                let input = format!("abcde12345-{}", i);
+                let signer = alg.signer_from_key_pair(&key_pair)?;
+                let signature = signer.sign(input.as_bytes())?;
+                verifier.verify(input.as_bytes(), &signature)?;
+            }
+
+            let other = if alg == &EcdsaJwsAlgorithm::Es256 {
+                EcdsaJwsAlgorithm::Es384
+            } else {
+                EcdsaJwsAlgorithm::Es256
+            };
+            other
+                .signer_from_key_pair(&key_pair)
+                .expect_err("Unmatched algorithm did not fail");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_ecdsa_mismatch() -> Result<()> {
         let input = b"abcde12345";
@@ -7,7 +7,7 @@ use openssl::sign::{Signer, Verifier};
 
 use crate::jwk::{
     alg::ed::{EdCurve, EdKeyPair},
-    Jwk,
+    Jwk, KeyPair,
 };
 use crate::jws::{JwsAlgorithm, JwsSigner, JwsVerifier};
 use crate::util;
@@ -90,6 +90,38 @@ impl EddsaJwsAlgorithm {
         })
     }
 
+    /// Return a signer from a Ed key pair.
+    ///
+    /// The key pair is not parsed again, so many signers can be created from a parsed key pair cheaply.
+    ///
+    /// # Arguments
+    /// * `key_pair` - A Ed key pair.
+    pub fn signer_from_key_pair(&self, key_pair: &EdKeyPair) -> Result<EddsaJwsSigner, JoseError> {
+        (|| -> anyhow::Result<EddsaJwsSigner> {
+            match key_pair.algorithm() {
+                Some(val) if val == self.name() => {}
+                None => {}
+                Some(val) => bail!(
+                    "A algorithm of key pair must be {} but {}",
+                    self.name(),
+                    val
+                ),
+            }
+
+            let curve = key_pair.curve();
+            let private_key = key_pair.clone().into_private_key();
+            let key_id = key_pair.key_id().map(|val| val.to_string());
+
+            Ok(EddsaJwsSigner {
+                algorithm: *self,
+                curve,
+                private_key,
+                key_id,
+            })
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
+
     /// Return a signer from a private key that is formatted by a JWK of OKP type.
     ///
     /// # Arguments
@@ -358,6 +390,10 @@ impl JwsVerifier for EddsaJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn public_key(&self) -> Option<&PKey<Public>> {
+        Some(&self.public_key)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -469,6 +505,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_eddsa_ed448_jwk() -> Result<()> {
+        let input = b"abcde12345";
+
+        let alg = EddsaJwsAlgorithm::Eddsa;
+
+        let private_key = load_file("jwk/OKP_Ed448_private.jwk")?;
+        let public_key = load_file("jwk/OKP_Ed448_public.jwk")?;
+
+        let signer = alg.signer_from_jwk(&Jwk::from_bytes(&private_key)?)?;
+        let signature = signer.sign(input)?;
+
+        let verifier = alg.verifier_from_jwk(&Jwk::from_bytes(&public_key)?)?;
+        verifier.verify(input, &signature)?;
+
+        let public_key = load_file("jwk/OKP_Ed25519_public.jwk")?;
+        let verifier = alg.verifier_from_jwk(&Jwk::from_bytes(&public_key)?)?;
+        verifier
+            .verify(input, &signature)
+            .expect_err("Ed25519 key did not fail on Ed448 signature");
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_eddsa_pkcs8_pem() -> Result<()> {
         let input = b"abcde12345";
@@ -509,6 +569,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_eddsa_key_pair() -> Result<()> {
+        for crv in &[EdCurve::Ed25519, EdCurve::Ed448] {
+            let alg = EddsaJwsAlgorithm::Eddsa;
+
+            let key_pair = alg.generate_key_pair(*crv)?;
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+
+            for i in 0..3 {
+                let input = format!("abcde12345-{}", i);
+                let signer = alg.signer_from_key_pair(&key_pair)?;
+                let signature = signer.sign(input.as_bytes())?;
+                verifier.verify(input.as_bytes(), &signature)?;
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_eddsa_mismatch() -> Result<()> {
         let input = b"abcde12345";
@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::io::{self, Read};
 use std::ops::Deref;
 
 use anyhow::bail;
@@ -39,20 +40,24 @@ impl HmacJwsAlgorithm {
 
     /// Return a signer from a secret key.
     ///
+    /// The key must be at least as long as the hash output (RFC 7518 3.2).
+    /// See `JwsContext::set_allow_short_hmac_key` to accept a shorter key.
+    ///
     /// # Arguments
     /// * `data` - A secret key.
     pub fn signer_from_bytes(&self, input: impl AsRef<[u8]>) -> Result<HmacJwsSigner, JoseError> {
+        self.signer_from_bytes_with(input, false)
+    }
+
+    pub(crate) fn signer_from_bytes_with(
+        &self,
+        input: impl AsRef<[u8]>,
+        allow_short_key: bool,
+    ) -> Result<HmacJwsSigner, JoseError> {
         (|| -> anyhow::Result<HmacJwsSigner> {
             let input = input.as_ref();
 
-            let min_key_len = self.hash_algorithm().output_len();
-            if input.len() < min_key_len {
-                bail!(
-                    "Secret key size must be larger than or equal to {}: {}",
-                    min_key_len,
-                    input.len()
-                );
-            }
+            self.check_key_len(input.len(), allow_short_key)?;
 
             let private_key = PKey::hmac(input)?;
 
@@ -67,9 +72,20 @@ impl HmacJwsAlgorithm {
 
     /// Return a signer from a secret key that is formatted by a JWK of oct type.
     ///
+    /// The key must be at least as long as the hash output (RFC 7518 3.2).
+    /// See `JwsContext::set_allow_short_hmac_key` to accept a shorter key.
+    ///
     /// # Arguments
     /// * `jwk` - A secret key that is formatted by a JWK of oct type.
     pub fn signer_from_jwk(&self, jwk: &Jwk) -> Result<HmacJwsSigner, JoseError> {
+        self.signer_from_jwk_with(jwk, false)
+    }
+
+    pub(crate) fn signer_from_jwk_with(
+        &self,
+        jwk: &Jwk,
+        allow_short_key: bool,
+    ) -> Result<HmacJwsSigner, JoseError> {
         (|| -> anyhow::Result<HmacJwsSigner> {
             match jwk.key_type() {
                 val if val == "oct" => {}
@@ -94,14 +110,7 @@ impl HmacJwsAlgorithm {
                 None => bail!("A parameter k is required."),
             };
 
-            let min_key_len = self.hash_algorithm().output_len();
-            if k.len() < min_key_len {
-                bail!(
-                    "Secret key size must be larger than or equal to {}: {}",
-                    min_key_len,
-                    k.len()
-                );
-            }
+            self.check_key_len(k.len(), allow_short_key)?;
 
             let private_key = PKey::hmac(&k)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -117,23 +126,27 @@ impl HmacJwsAlgorithm {
 
     /// Return a verifier from a secret key.
     ///
+    /// The key must be at least as long as the hash output (RFC 7518 3.2).
+    /// See `JwsContext::set_allow_short_hmac_key` to accept a shorter key.
+    ///
     /// # Arguments
     /// * `input` - A secret key.
     pub fn verifier_from_bytes(
         &self,
         input: impl AsRef<[u8]>,
+    ) -> Result<HmacJwsVerifier, JoseError> {
+        self.verifier_from_bytes_with(input, false)
+    }
+
+    pub(crate) fn verifier_from_bytes_with(
+        &self,
+        input: impl AsRef<[u8]>,
+        allow_short_key: bool,
     ) -> Result<HmacJwsVerifier, JoseError> {
         (|| -> anyhow::Result<HmacJwsVerifier> {
             let input = input.as_ref();
 
-            let min_key_len = self.hash_algorithm().output_len();
-            if input.len() < min_key_len {
-                bail!(
-                    "Secret key size must be larger than or equal to {}: {}",
-                    min_key_len,
-                    input.len()
-                );
-            }
+            self.check_key_len(input.len(), allow_short_key)?;
 
             let private_key = PKey::hmac(input)?;
 
@@ -148,9 +161,20 @@ impl HmacJwsAlgorithm {
 
     /// Return a verifier from a secret key that is formatted by a JWK of oct type.
     ///
+    /// The key must be at least as long as the hash output (RFC 7518 3.2).
+    /// See `JwsContext::set_allow_short_hmac_key` to accept a shorter key.
+    ///
     /// # Arguments
     /// * `jwk` - A secret key that is formatted by a JWK of oct type.
     pub fn verifier_from_jwk(&self, jwk: &Jwk) -> Result<HmacJwsVerifier, JoseError> {
+        self.verifier_from_jwk_with(jwk, false)
+    }
+
+    pub(crate) fn verifier_from_jwk_with(
+        &self,
+        jwk: &Jwk,
+        allow_short_key: bool,
+    ) -> Result<HmacJwsVerifier, JoseError> {
         (|| -> anyhow::Result<HmacJwsVerifier> {
             match jwk.key_type() {
                 val if val == "oct" => {}
@@ -176,14 +200,7 @@ impl HmacJwsAlgorithm {
                 None => bail!("A parameter k is required."),
             };
 
-            let min_key_len = self.hash_algorithm().output_len();
-            if k.len() < min_key_len {
-                bail!(
-                    "Secret key size must be larger than or equal to {}: {}",
-                    min_key_len,
-                    k.len()
-                );
-            }
+            self.check_key_len(k.len(), allow_short_key)?;
 
             let private_key = PKey::hmac(&k)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -197,6 +214,18 @@ impl HmacJwsAlgorithm {
         .map_err(|err| JoseError::InvalidKeyFormat(err))
     }
 
+    fn check_key_len(&self, len: usize, allow_short_key: bool) -> anyhow::Result<()> {
+        let min_key_len = self.hash_algorithm().output_len();
+        if len < min_key_len && !allow_short_key {
+            bail!(
+                "Secret key size must be larger than or equal to {}: {}",
+                min_key_len,
+                len
+            );
+        }
+        Ok(())
+    }
+
     fn hash_algorithm(&self) -> HashAlgorithm {
         match self {
             Self::Hs256 => HashAlgorithm::Sha256,
@@ -279,6 +308,18 @@ impl JwsSigner for HmacJwsSigner {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn sign_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let md = self.algorithm.hash_algorithm().message_digest();
+
+            let mut signer = Signer::new(md, &self.private_key)?;
+            io::copy(reader, &mut signer)?;
+            let signature = signer.sign_to_vec()?;
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsSigner> {
         Box::new(self.clone())
     }
@@ -381,6 +422,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn reject_hmac_key_shorter_than_hash_output() -> Result<()> {
+        for (alg, min_key_len) in &[
+            (HmacJwsAlgorithm::Hs256, 32),
+            (HmacJwsAlgorithm::Hs384, 48),
+            (HmacJwsAlgorithm::Hs512, 64),
+        ] {
+            let key = util::random_bytes(*min_key_len);
+            let signature = alg.signer_from_bytes(&key)?.sign(b"abcde12345")?;
+            alg.verifier_from_bytes(&key)?
+                .verify(b"abcde12345", &signature)?;
+            alg.signer_from_jwk(&alg.to_jwk(&key))?;
+            alg.verifier_from_jwk(&alg.to_jwk(&key))?
+                .verify(b"abcde12345", &signature)?;
+
+            let key = &key[..min_key_len - 1];
+            let expected = format!(
+                "Secret key size must be larger than or equal to {}: {}",
+                min_key_len,
+                min_key_len - 1
+            );
+            for res in vec![
+                alg.signer_from_bytes(key).map(|_| ()),
+                alg.verifier_from_bytes(key).map(|_| ()),
+                alg.signer_from_jwk(&alg.to_jwk(key)).map(|_| ()),
+                alg.verifier_from_jwk(&alg.to_jwk(key)).map(|_| ()),
+            ] {
+                match res {
+                    Err(JoseError::InvalidKeyFormat(err)) => {
+                        assert_eq!(err.to_string(), expected)
+                    }
+                    res => panic!("{}: {:?}", alg, res),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_hmac_jwk() -> Result<()> {
         let input = b"abcde12345";
@@ -1,13 +1,16 @@
 use std::fmt::Display;
+use std::io::{self, Read};
 use std::ops::Deref;
 
 use anyhow::bail;
 use openssl::pkey::{PKey, Private, Public};
+use openssl::rsa::Padding;
 use openssl::sign::{Signer, Verifier};
 
-use crate::jwk::{alg::rsa::RsaKeyPair, Jwk};
+use crate::jwk::{alg::rsa::RsaKeyPair, Jwk, KeyPair};
 use crate::jws::{JwsAlgorithm, JwsSigner, JwsVerifier};
 use crate::util::der::{DerBuilder, DerType};
+use crate::util::oid::{OID_SHA256, OID_SHA384, OID_SHA512};
 use crate::util::{self, HashAlgorithm};
 use crate::{JoseError, Value};
 
@@ -124,6 +127,42 @@ impl RsassaJwsAlgorithm {
         })
     }
 
+    /// Return a signer from a RSA key pair.
+    ///
+    /// The key pair is not parsed again, so many signers can be created from a parsed key pair cheaply.
+    ///
+    /// # Arguments
+    /// * `key_pair` - A RSA key pair.
+    pub fn signer_from_key_pair(
+        &self,
+        key_pair: &RsaKeyPair,
+    ) -> Result<RsassaJwsSigner, JoseError> {
+        (|| -> anyhow::Result<RsassaJwsSigner> {
+            match key_pair.algorithm() {
+                Some(val) if val == self.name() => {}
+                None => {}
+                Some(val) => bail!(
+                    "A algorithm of key pair must be {} but {}",
+                    self.name(),
+                    val
+                ),
+            }
+            if key_pair.key_len() * 8 < 2048 {
+                bail!("key length must be 2048 or more.");
+            }
+
+            let private_key = key_pair.clone().into_private_key();
+            let key_id = key_pair.key_id().map(|val| val.to_string());
+
+            Ok(RsassaJwsSigner {
+                algorithm: *self,
+                private_key,
+                key_id,
+            })
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
+
     /// Return a signer from a private key that is formatted by a JWK of RSA type.
     ///
     /// # Arguments
@@ -354,6 +393,55 @@ impl RsassaJwsSigner {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return a signature of a externally computed digest.
+    ///
+    /// The digest must be a hash value of the message by the hash algorithm of this signer.
+    ///
+    /// # Arguments
+    ///
+    /// * `digest` - The digest of the message to sign.
+    pub fn sign_prehashed(&self, digest: &[u8]) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let hash_algorithm = self.algorithm.hash_algorithm();
+            if digest.len() != hash_algorithm.output_len() {
+                bail!(
+                    "The length of {} digest must be {}: {}",
+                    hash_algorithm,
+                    hash_algorithm.output_len(),
+                    digest.len()
+                );
+            }
+
+            let oid = match hash_algorithm {
+                HashAlgorithm::Sha256 => &*OID_SHA256,
+                HashAlgorithm::Sha384 => &*OID_SHA384,
+                HashAlgorithm::Sha512 => &*OID_SHA512,
+                _ => unreachable!(),
+            };
+
+            let mut builder = DerBuilder::new();
+            builder.begin(DerType::Sequence);
+            {
+                builder.begin(DerType::Sequence);
+                {
+                    builder.append_object_identifier(oid);
+                    builder.append_null();
+                }
+                builder.end();
+                builder.append_octed_string_from_bytes(digest);
+            }
+            builder.end();
+            let digest_info = builder.build();
+
+            let rsa = self.private_key.rsa()?;
+            let mut signature = vec![0; rsa.size() as usize];
+            let len = rsa.private_encrypt(&digest_info, &mut signature, Padding::PKCS1)?;
+            signature.truncate(len);
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
 }
 
 impl JwsSigner for RsassaJwsSigner {
@@ -384,6 +472,18 @@ impl JwsSigner for RsassaJwsSigner {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn sign_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let md = self.algorithm.hash_algorithm().message_digest();
+
+            let mut signer = Signer::new(md, &self.private_key)?;
+            io::copy(reader, &mut signer)?;
+            let signature = signer.sign_to_vec()?;
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsSigner> {
         Box::new(self.clone())
     }
@@ -440,6 +540,10 @@ impl JwsVerifier for RsassaJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn public_key(&self) -> Option<&PKey<Public>> {
+        Some(&self.public_key)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -524,6 +628,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_rsassa_prehashed() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            RsassaJwsAlgorithm::Rs256,
+            RsassaJwsAlgorithm::Rs384,
+            RsassaJwsAlgorithm::Rs512,
+        ] {
+            let key_pair = alg.generate_key_pair(2048)?;
+
+            let signer = alg.signer_from_pem(key_pair.to_pem_private_key())?;
+            let md = alg.hash_algorithm().message_digest();
+            let digest = openssl::hash::hash(md, input)?;
+            let signature = signer.sign_prehashed(&digest)?;
+            assert_eq!(signature, signer.sign(input)?);
+
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+            verifier.verify(input, &signature)?;
+
+            signer
+                .sign_prehashed(&digest[1..])
+                .expect_err("Invalid digest length did not fail");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_rsassa_generated_traditional_pem() -> Result<()> {
         let input = b"abcde12345";
@@ -610,6 +742,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_rsassa_traditional_pem() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            RsassaJwsAlgorithm::Rs256,
+            RsassaJwsAlgorithm::Rs384,
+            RsassaJwsAlgorithm::Rs512,
+        ] {
+            let private_key = load_file("pem/RSA_2048bit_traditional_private.pem")?;
+            let public_key = load_file("pem/RSA_2048bit_traditional_public.pem")?;
+
+            let signer = alg.signer_from_pem(&private_key)?;
+            let signature = signer.sign(input)?;
+
+            let verifier = alg.verifier_from_pem(&public_key)?;
+            verifier.verify(input, &signature)?;
+
+            let verifier = alg.verifier_from_pem(&load_file("pem/RSA_2048bit_public.pem")?)?;
+            verifier.verify(input, &signature)?;
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_rsassa_pkcs8_der() -> Result<()> {
         let input = b"abcde12345";
@@ -676,6 +833,63 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_rsassa_pkcs1_public_der_jwk() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            RsassaJwsAlgorithm::Rs256,
+            RsassaJwsAlgorithm::Rs384,
+            RsassaJwsAlgorithm::Rs512,
+        ] {
+            let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+            let public_key = load_file("der/RSA_2048bit_raw_public.der")?;
+
+            let signer = alg.signer_from_pem(&private_key)?;
+            let signature = signer.sign(input)?;
+
+            let jwk = Jwk::from_pkcs1_public_der(&public_key)?;
+            assert_eq!(jwk.key_type(), "RSA");
+            let verifier = alg.verifier_from_jwk(&jwk)?;
+            verifier.verify(input, &signature)?;
+        }
+
+        Jwk::from_pkcs1_public_der(&load_file("der/RSA_2048bit_spki_public.der")?)
+            .expect_err("SPKI public key did not fail");
+
+        Ok(())
+    }
+
+    #[test]
+    fn sign_and_verify_rsassa_key_pair() -> Result<()> {
+        for alg in &[
+            RsassaJwsAlgorithm::Rs256,
+            RsassaJwsAlgorithm::Rs384,
+            RsassaJwsAlgorithm::Rs512,
+        ] {
+            let key_pair = alg.generate_key_pair(2048)?;
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+
+            for i in 0..3 {
+                let input = format!("abcde12345-{}", i);
+                let signer = alg.signer_from_key_pair(&key_pair)?;
+                let signature = signer.sign(input.as_bytes())?;
+                verifier.verify(input.as_bytes(), &signature)?;
+            }
+
+            let other = if alg == &RsassaJwsAlgorithm::Rs256 {
+                RsassaJwsAlgorithm::Rs384
+            } else {
+                RsassaJwsAlgorithm::Rs256
+            };
+            other
+                .signer_from_key_pair(&key_pair)
+                .expect_err("Unmatched algorithm did not fail");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_rsassa_mismatch() -> Result<()> {
         let input = b"abcde12345";
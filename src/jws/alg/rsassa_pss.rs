@@ -1,12 +1,13 @@
 use std::fmt::Display;
+use std::io::{self, Read};
 use std::ops::Deref;
 
 use anyhow::bail;
 use openssl::pkey::{PKey, Private, Public};
-use openssl::rsa::Rsa;
-use openssl::sign::{Signer, Verifier};
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::{RsaPssSaltlen, Signer, Verifier};
 
-use crate::jwk::{alg::rsa::RsaKeyPair, alg::rsapss::RsaPssKeyPair, Jwk};
+use crate::jwk::{alg::rsa::RsaKeyPair, alg::rsapss::RsaPssKeyPair, Jwk, KeyPair};
 use crate::jws::{JwsAlgorithm, JwsSigner, JwsVerifier};
 use crate::util::der::{DerBuilder, DerType};
 use crate::util::{self, HashAlgorithm};
@@ -144,6 +145,57 @@ impl RsassaPssJwsAlgorithm {
         })
     }
 
+    /// Return a signer from a RSA-PSS key pair.
+    ///
+    /// The key pair is not parsed again, so many signers can be created from a parsed key pair cheaply.
+    ///
+    /// # Arguments
+    /// * `key_pair` - A RSA-PSS key pair.
+    pub fn signer_from_key_pair(
+        &self,
+        key_pair: &RsaPssKeyPair,
+    ) -> Result<RsassaPssJwsSigner, JoseError> {
+        (|| -> anyhow::Result<RsassaPssJwsSigner> {
+            match key_pair.algorithm() {
+                Some(val) if val == self.name() => {}
+                None => {}
+                Some(val) => bail!(
+                    "A algorithm of key pair must be {} but {}",
+                    self.name(),
+                    val
+                ),
+            }
+            if key_pair.hash_algorithm() != self.hash_algorithm() {
+                bail!(
+                    "The message digest parameter is mismatched: {}",
+                    key_pair.hash_algorithm()
+                );
+            }
+            if key_pair.mgf1_hash_algorithm() != self.hash_algorithm() {
+                bail!(
+                    "The mgf1 message digest parameter is mismatched: {}",
+                    key_pair.mgf1_hash_algorithm()
+                );
+            }
+            if key_pair.salt_len() != self.salt_len() {
+                bail!("The salt length is mismatched: {}", key_pair.salt_len());
+            }
+            if key_pair.key_len() * 8 < 2048 {
+                bail!("key length must be 2048 or more.");
+            }
+
+            let private_key = key_pair.clone().into_private_key();
+            let key_id = key_pair.key_id().map(|val| val.to_string());
+
+            Ok(RsassaPssJwsSigner {
+                algorithm: *self,
+                private_key,
+                key_id,
+            })
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
+
     /// Return a signer from a private key that is formatted by a JWK of RSA type.
     ///
     /// # Arguments
@@ -460,6 +512,9 @@ impl JwsSigner for RsassaPssJwsSigner {
             let md = self.algorithm.hash_algorithm().message_digest();
 
             let mut signer = Signer::new(md, &self.private_key)?;
+            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+            signer.set_rsa_pss_saltlen(RsaPssSaltlen::custom(self.algorithm.salt_len() as i32))?;
+            signer.set_rsa_mgf1_md(md)?;
             signer.update(message)?;
             let signature = signer.sign_to_vec()?;
             Ok(signature)
@@ -467,6 +522,21 @@ impl JwsSigner for RsassaPssJwsSigner {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn sign_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let md = self.algorithm.hash_algorithm().message_digest();
+
+            let mut signer = Signer::new(md, &self.private_key)?;
+            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+            signer.set_rsa_pss_saltlen(RsaPssSaltlen::custom(self.algorithm.salt_len() as i32))?;
+            signer.set_rsa_mgf1_md(md)?;
+            io::copy(reader, &mut signer)?;
+            let signature = signer.sign_to_vec()?;
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsSigner> {
         Box::new(self.clone())
     }
@@ -523,6 +593,10 @@ impl JwsVerifier for RsassaPssJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn public_key(&self) -> Option<&PKey<Public>> {
+        Some(&self.public_key)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -751,6 +825,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_and_verify_rsassa_pss_key_pair() -> Result<()> {
+        for alg in &[
+            RsassaPssJwsAlgorithm::Ps256,
+            RsassaPssJwsAlgorithm::Ps384,
+            RsassaPssJwsAlgorithm::Ps512,
+        ] {
+            let key_pair = alg.generate_key_pair(2048)?;
+            let verifier = alg.verifier_from_pem(key_pair.to_pem_public_key())?;
+
+            for i in 0..3 {
+                let input = format!("abcde12345-{}", i);
+                let signer = alg.signer_from_key_pair(&key_pair)?;
+                let signature = signer.sign(input.as_bytes())?;
+                verifier.verify(input.as_bytes(), &signature)?;
+            }
+
+            let other = if alg == &RsassaPssJwsAlgorithm::Ps256 {
+                RsassaPssJwsAlgorithm::Ps384
+            } else {
+                RsassaPssJwsAlgorithm::Ps256
+            };
+            other
+                .signer_from_key_pair(&key_pair)
+                .expect_err("Unmatched algorithm did not fail");
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_rsassa_pss_mismatch() -> Result<()> {
         let input = b"abcde12345";
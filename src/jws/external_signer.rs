@@ -0,0 +1,275 @@
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use anyhow::bail;
+use openssl::hash;
+use openssl::pkey::{Id, PKey, Public};
+
+use crate::jws::{
+    JwsAlgorithm, JwsSigner, ES256, ES256K, ES384, ES512, PS256, PS384, PS512, RS256, RS384, RS512,
+};
+use crate::util::HashAlgorithm;
+use crate::JoseError;
+
+type SignFn = dyn Fn(&[u8]) -> Result<Vec<u8>, JoseError> + Send + Sync;
+
+/// Represents a signer that delegates signing of a digest to a external function
+/// (e.g. a HSM or a remote signing service).
+///
+/// The signing input and its digest are computed by this crate, and the function
+/// receives the digest. The function must return a signature of the JWS format:
+/// a RSA signature for RS* and PS*, or a concatenated R and S for ES*.
+#[derive(Clone)]
+pub struct ExternalJwsSigner {
+    algorithm: Box<dyn JwsAlgorithm>,
+    hash_algorithm: HashAlgorithm,
+    signature_len: usize,
+    check_signature_len: bool,
+    key_id: Option<String>,
+    sign: Arc<SignFn>,
+}
+
+impl ExternalJwsSigner {
+    /// Return a signer that signs a digest by the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - a JWS alg header claim value (RS*, PS*, ES* or ES256K).
+    /// * `sign` - a function that returns a signature of a digest.
+    pub fn new<F>(algorithm: &str, sign: F) -> Result<Self, JoseError>
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, JoseError> + Send + Sync + 'static,
+    {
+        (|| -> anyhow::Result<Self> {
+            let (algorithm, hash_algorithm, signature_len): (Box<dyn JwsAlgorithm>, _, _) =
+                match algorithm {
+                    "RS256" => (Box::new(RS256), HashAlgorithm::Sha256, 256),
+                    "RS384" => (Box::new(RS384), HashAlgorithm::Sha384, 256),
+                    "RS512" => (Box::new(RS512), HashAlgorithm::Sha512, 256),
+                    "PS256" => (Box::new(PS256), HashAlgorithm::Sha256, 256),
+                    "PS384" => (Box::new(PS384), HashAlgorithm::Sha384, 256),
+                    "PS512" => (Box::new(PS512), HashAlgorithm::Sha512, 256),
+                    "ES256" => (Box::new(ES256), HashAlgorithm::Sha256, 64),
+                    "ES256K" => (Box::new(ES256K), HashAlgorithm::Sha256, 64),
+                    "ES384" => (Box::new(ES384), HashAlgorithm::Sha384, 96),
+                    "ES512" => (Box::new(ES512), HashAlgorithm::Sha512, 132),
+                    val => bail!("The algorithm cannot sign a digest: {}", val),
+                };
+
+            let check_signature_len = algorithm.name().starts_with("ES");
+
+            Ok(Self {
+                algorithm,
+                hash_algorithm,
+                signature_len,
+                check_signature_len,
+                key_id: None,
+                sign: Arc::new(sign),
+            })
+        })()
+        .map_err(JoseError::UnsupportedSignatureAlgorithm)
+    }
+
+    /// Return the hash algorithm of the digest that is passed to the function.
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Set the public key of the external signing key.
+    ///
+    /// For RS* and PS*, the signature length is taken from the modulus of the key,
+    /// and a signature of the other length is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `public_key` - the public key of the external signing key.
+    pub fn set_public_key(&mut self, public_key: &PKey<Public>) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            let name = self.algorithm.name();
+            match public_key.id() {
+                Id::RSA | Id::RSA_PSS if name.starts_with("RS") || name.starts_with("PS") => {
+                    if public_key.bits() < 2048 {
+                        bail!("key length must be 2048 or more.");
+                    }
+                    self.signature_len = public_key.size();
+                    self.check_signature_len = true;
+                }
+                Id::EC if name.starts_with("ES") => {}
+                _ => bail!("The key type is not matched to the algorithm: {}", name),
+            }
+            Ok(())
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
+
+    pub fn set_key_id(&mut self, value: impl Into<String>) {
+        self.key_id = Some(value.into());
+    }
+
+    pub fn remove_key_id(&mut self) {
+        self.key_id = None;
+    }
+}
+
+impl JwsSigner for ExternalJwsSigner {
+    fn algorithm(&self) -> &dyn JwsAlgorithm {
+        self.algorithm.as_ref()
+    }
+
+    /// Return the signature length of JWS.
+    ///
+    /// The length of a RSA signature depends on the key, so the length
+    /// of a 2048 bit key is returned for RS* and PS* unless `set_public_key` is called.
+    fn signature_len(&self) -> usize {
+        self.signature_len
+    }
+
+    fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError> {
+        let digest = hash::hash(self.hash_algorithm.message_digest(), message)
+            .map_err(|err| JoseError::InvalidSignature(err.into()))?;
+        let signature = (self.sign)(&digest)?;
+
+        (|| -> anyhow::Result<Vec<u8>> {
+            if self.check_signature_len && signature.len() != self.signature_len {
+                bail!(
+                    "The length of {} signature must be {}: {}",
+                    self.algorithm.name(),
+                    self.signature_len,
+                    signature.len()
+                );
+            }
+            Ok(signature)
+        })()
+        .map_err(JoseError::InvalidSignature)
+    }
+
+    fn box_clone(&self) -> Box<dyn JwsSigner> {
+        Box::new(self.clone())
+    }
+}
+
+impl Debug for ExternalJwsSigner {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ExternalJwsSigner")
+            .field("algorithm", &self.algorithm)
+            .field("hash_algorithm", &self.hash_algorithm)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl Deref for ExternalJwsSigner {
+    type Target = dyn JwsSigner;
+
+    fn deref(&self) -> &Self::Target {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use anyhow::Result;
+    use openssl::md::Md;
+    use openssl::pkey::PKey;
+    use openssl::pkey_ctx::PkeyCtx;
+    use openssl::rsa::Padding;
+
+    use super::ExternalJwsSigner;
+    use crate::jws::{self, JwsHeader, JwsSigner, ES256, RS256};
+    use crate::JoseError;
+
+    #[test]
+    fn sign_and_verify_external_rsassa() -> Result<()> {
+        let private_key = PKey::private_key_from_pem(&load_file("pem/RSA_2048bit_private.pem")?)?;
+        let signer = ExternalJwsSigner::new("RS256", move |digest| {
+            (|| -> anyhow::Result<Vec<u8>> {
+                let mut ctx = PkeyCtx::new(&private_key)?;
+                ctx.sign_init()?;
+                ctx.set_rsa_padding(Padding::PKCS1)?;
+                ctx.set_signature_md(Md::sha256())?;
+                let mut signature = Vec::new();
+                ctx.sign_to_vec(digest, &mut signature)?;
+                Ok(signature)
+            })()
+            .map_err(JoseError::InvalidSignature)
+        })?;
+        assert_eq!(signer.algorithm().name(), "RS256");
+
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+
+        let verifier = RS256.verifier_from_pem(&load_file("pem/RSA_2048bit_public.pem")?)?;
+        let (payload, header) = jws::deserialize_compact(&jws, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+        assert_eq!(header.algorithm(), Some("RS256"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn external_rsassa_signature_len_from_public_key() -> Result<()> {
+        let key_pair = RS256.generate_key_pair(3072)?;
+        let public_key = PKey::public_key_from_pem(&key_pair.to_pem_public_key())?;
+        let key_signer = RS256.signer_from_key_pair(&key_pair)?;
+
+        let mut signer =
+            ExternalJwsSigner::new("RS256", move |digest| key_signer.sign_prehashed(digest))?;
+        assert_eq!(signer.signature_len(), 256);
+        signer.set_public_key(&public_key)?;
+        assert_eq!(signer.signature_len(), 384);
+
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+        let verifier = RS256.verifier_from_pem(&key_pair.to_pem_public_key())?;
+        let (payload, _) = jws::deserialize_compact(&jws, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+
+        let mut signer = ExternalJwsSigner::new("RS256", |_digest| Ok(vec![0; 256]))?;
+        signer.set_public_key(&public_key)?;
+        assert!(signer.sign(b"test payload!").is_err());
+
+        let ec_public_key = PKey::public_key_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+        assert!(signer.set_public_key(&ec_public_key).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sign_and_verify_external_ecdsa() -> Result<()> {
+        let key_signer = ES256.signer_from_pem(&load_file("pem/EC_P-256_private.pem")?)?;
+        let mut signer =
+            ExternalJwsSigner::new("ES256", move |digest| key_signer.sign_prehashed(digest))?;
+        signer.set_key_id("hsm-key");
+
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+
+        let verifier = ES256.verifier_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+        let (payload, header) = jws::deserialize_compact(&jws, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+        assert_eq!(header.algorithm(), Some("ES256"));
+        assert_eq!(header.key_id(), Some("hsm-key"));
+
+        let signer = ExternalJwsSigner::new("ES256", |_digest| Ok(vec![0; 10]))?;
+        assert!(signer.sign(b"test payload!").is_err());
+
+        assert!(ExternalJwsSigner::new("EdDSA", |_digest| Ok(Vec::new())).is_err());
+        assert!(ExternalJwsSigner::new("HS256", |_digest| Ok(Vec::new())).is_err());
+
+        Ok(())
+    }
+
+    fn load_file(path: &str) -> Result<Vec<u8>> {
+        let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        pb.push("data");
+        pb.push(path);
+
+        let data = fs::read(&pb)?;
+        Ok(data)
+    }
+}
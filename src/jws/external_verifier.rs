@@ -0,0 +1,180 @@
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::jws::{JwsAlgorithm, JwsVerifier};
+use crate::JoseError;
+
+type VerifyFn = dyn Fn(&[u8], &[u8]) -> Result<(), JoseError> + Send + Sync;
+
+/// Represents a JWS algorithm that is named by a external verifier.
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct ExternalJwsAlgorithm {
+    name: String,
+}
+
+impl JwsAlgorithm for ExternalJwsAlgorithm {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn box_clone(&self) -> Box<dyn JwsAlgorithm> {
+        Box::new(self.clone())
+    }
+}
+
+/// Represents a verifier that delegates verification to a external function
+/// (e.g. a remote verification service or a key that cannot be loaded into OpenSSL).
+///
+/// The function receives the JWS signing input and the decoded signature.
+/// The algorithm name is compared with the alg header claim as well as the other verifiers,
+/// so a algorithm that is not supported by this crate can be used.
+#[derive(Clone)]
+pub struct ExternalJwsVerifier {
+    algorithm: ExternalJwsAlgorithm,
+    key_id: Option<String>,
+    verify: Arc<VerifyFn>,
+}
+
+impl ExternalJwsVerifier {
+    /// Return a verifier that verifies a signature by the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - a JWS alg header claim value.
+    /// * `verify` - a function that verifies a signing input by a signature.
+    pub fn new<F>(algorithm: impl Into<String>, verify: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> Result<(), JoseError> + Send + Sync + 'static,
+    {
+        Self {
+            algorithm: ExternalJwsAlgorithm {
+                name: algorithm.into(),
+            },
+            key_id: None,
+            verify: Arc::new(verify),
+        }
+    }
+
+    pub fn set_key_id(&mut self, value: impl Into<String>) {
+        self.key_id = Some(value.into());
+    }
+
+    pub fn remove_key_id(&mut self) {
+        self.key_id = None;
+    }
+}
+
+impl JwsVerifier for ExternalJwsVerifier {
+    fn algorithm(&self) -> &dyn JwsAlgorithm {
+        &self.algorithm
+    }
+
+    fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError> {
+        (self.verify)(message, signature)
+    }
+
+    fn box_clone(&self) -> Box<dyn JwsVerifier> {
+        Box::new(self.clone())
+    }
+}
+
+impl Debug for ExternalJwsVerifier {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ExternalJwsVerifier")
+            .field("algorithm", &self.algorithm)
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl Deref for ExternalJwsVerifier {
+    type Target = dyn JwsVerifier;
+
+    fn deref(&self) -> &Self::Target {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use anyhow::Result;
+    use openssl::hash::{hash, MessageDigest};
+
+    use super::ExternalJwsVerifier;
+    use crate::jws::{self, JwsHeader, JwsVerifier, ES256};
+    use crate::jwt::{self, JwtPayload};
+    use crate::JoseError;
+
+    #[test]
+    fn verify_external_ecdsa() -> Result<()> {
+        let signer = ES256.signer_from_pem(&load_file("pem/EC_P-256_private.pem")?)?;
+        let jws = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+
+        let key_verifier = ES256.verifier_from_pem(&load_file("pem/EC_P-256_public.pem")?)?;
+        let verifier = ExternalJwsVerifier::new("ES256", move |message, signature| {
+            key_verifier.verify(message, signature)
+        });
+        let (payload, _) = jws::deserialize_compact(&jws, &verifier)?;
+        assert_eq!(payload, b"test payload!");
+
+        let mut tampered = jws.clone();
+        tampered.push('A');
+        assert!(jws::deserialize_compact(&tampered, &verifier).is_err());
+
+        // The algorithm of the verifier is compared with the alg header claim.
+        let verifier = ExternalJwsVerifier::new("ES384", |_message, _signature| Ok(()));
+        assert!(jws::deserialize_compact(&jws, &verifier).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_external_custom_algorithm() -> Result<()> {
+        let header = base64::encode_config(r#"{"alg":"X-SHA256"}"#, base64::URL_SAFE_NO_PAD);
+        let payload = base64::encode_config(r#"{"sub":"user"}"#, base64::URL_SAFE_NO_PAD);
+        let signing_input = format!("{}.{}", header, payload);
+        let signature = hash(MessageDigest::sha256(), signing_input.as_bytes())?;
+        let jwt = format!(
+            "{}.{}",
+            signing_input,
+            base64::encode_config(&signature, base64::URL_SAFE_NO_PAD)
+        );
+
+        let verifier = ExternalJwsVerifier::new("X-SHA256", |message, signature| {
+            let expected = hash(MessageDigest::sha256(), message)
+                .map_err(|err| JoseError::InvalidSignature(err.into()))?;
+            if expected.as_ref() != signature {
+                return Err(JoseError::InvalidSignature(anyhow::anyhow!(
+                    "The signature is not matched."
+                )));
+            }
+            Ok(())
+        });
+        let (payload, header) = jwt::decode_with_verifier(&jwt, &verifier)?;
+        assert_eq!(payload, {
+            let mut payload = JwtPayload::new();
+            payload.set_subject("user");
+            payload
+        });
+        assert_eq!(header.algorithm(), Some("X-SHA256"));
+
+        Ok(())
+    }
+
+    fn load_file(path: &str) -> Result<Vec<u8>> {
+        let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        pb.push("data");
+        pb.push(path);
+
+        let data = fs::read(&pb)?;
+        Ok(data)
+    }
+}
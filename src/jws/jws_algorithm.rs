@@ -1,4 +1,7 @@
 use std::fmt::Debug;
+use std::io::Read;
+
+use openssl::pkey::{PKey, Public};
 
 use crate::JoseError;
 
@@ -41,6 +44,23 @@ pub trait JwsSigner: Debug + Send + Sync {
     /// * `message` - The message data to sign.
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError>;
 
+    /// Return a signature of the data that is read from a reader.
+    ///
+    /// The default implementation reads all of the data into memory and calls `sign`.
+    /// The signers that compute a digest of the data (HMAC, RSA, RSA-PSS and ECDSA)
+    /// override it to sign the data as a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader of the message data to sign.
+    fn sign_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>, JoseError> {
+        let mut message = Vec::new();
+        reader
+            .read_to_end(&mut message)
+            .map_err(|err| JoseError::InvalidSignature(err.into()))?;
+        self.sign(&message)
+    }
+
     fn box_clone(&self) -> Box<dyn JwsSigner>;
 }
 
@@ -66,6 +86,12 @@ pub trait JwsVerifier: Debug + Send + Sync {
     /// * `signature` - a signature data.
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError>;
 
+    /// Return the public key used for verifying.
+    /// The default value is None for the algorithm that doesn't use a public key.
+    fn public_key(&self) -> Option<&PKey<Public>> {
+        None
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier>;
 }
 
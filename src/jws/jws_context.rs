@@ -1,21 +1,65 @@
-use std::collections::BTreeSet;
-use std::fmt::Debug;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::sync::Arc;
 
 use anyhow::bail;
+use openssl::nid::Nid;
+use openssl::pkey::Id;
+use openssl::x509::X509;
+use serde::Serialize;
 
+use crate::jwk::Jwk;
+use crate::jws::alg::hmac::{HmacJwsAlgorithm, HmacJwsSigner, HmacJwsVerifier};
 use crate::jws::{JwsHeader, JwsHeaderSet, JwsSigner, JwsVerifier};
 use crate::util;
-use crate::{JoseError, Map, Value};
+use crate::{AlgorithmConfusionError, JoseError, JoseHeaderMerge, KeyPolicyError, Map, Value};
+
+type CriticalHandlerFn = dyn Fn(&JwsHeader) -> Result<(), JoseError> + Send + Sync;
+
+#[derive(Clone)]
+struct CriticalHandler(Arc<CriticalHandlerFn>);
+
+impl Debug for CriticalHandler {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("CriticalHandler")
+    }
+}
+
+impl PartialEq for CriticalHandler {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CriticalHandler {}
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct JwsContext {
     acceptable_criticals: BTreeSet<String>,
+    critical_handlers: BTreeMap<String, CriticalHandler>,
+    minimum_rsa_bits: u32,
+    allowed_curves: Option<BTreeSet<String>>,
+    verify_x5c_matches_key: bool,
+    secp256k1_enabled: bool,
+    allow_short_hmac_key: bool,
 }
 
 impl JwsContext {
+    /// The JWS alg header claim values that are supported by this crate.
+    pub(crate) const SUPPORTED_ALGORITHMS: &'static [&'static str] = &[
+        "none", "HS256", "HS384", "HS512", "RS256", "RS384", "RS512", "PS256", "PS384", "PS512",
+        "ES256", "ES384", "ES512", "ES256K", "EdDSA",
+    ];
+
     pub fn new() -> Self {
         Self {
             acceptable_criticals: BTreeSet::new(),
+            critical_handlers: BTreeMap::new(),
+            minimum_rsa_bits: 2048,
+            allowed_curves: None,
+            verify_x5c_matches_key: false,
+            secp256k1_enabled: true,
+            allow_short_hmac_key: false,
         }
     }
 
@@ -46,6 +90,339 @@ impl JwsContext {
         self.acceptable_criticals.remove(name);
     }
 
+    /// Remove all acceptable critical header claim names
+    pub fn clear_acceptable_criticals(&mut self) {
+        self.acceptable_criticals.clear();
+    }
+
+    /// Set a handler that validates a critical header claim.
+    ///
+    /// The handler is called with the JWS header after the signature is verified
+    /// when the critical header claim is contained in the crit header claim and is
+    /// acceptable, and the token is rejected when the handler returns a error.
+    /// For json serialization, the signature is skipped instead. A acceptable critical header claim
+    /// that has no handler is accepted as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a acceptable critical header claim name
+    /// * `handler` - a function for validating the critical header claim.
+    pub fn set_critical_handler<F>(&mut self, name: &str, handler: F)
+    where
+        F: Fn(&JwsHeader) -> Result<(), JoseError> + Send + Sync + 'static,
+    {
+        self.critical_handlers
+            .insert(name.to_string(), CriticalHandler(Arc::new(handler)));
+    }
+
+    /// Remove a handler of a critical header claim.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a acceptable critical header claim name
+    pub fn remove_critical_handler(&mut self, name: &str) {
+        self.critical_handlers.remove(name);
+    }
+
+    /// Return the minimum RSA key length in bits.
+    pub fn minimum_rsa_bits(&self) -> u32 {
+        self.minimum_rsa_bits
+    }
+
+    /// Set the minimum RSA key length in bits.
+    ///
+    /// The default is 2048. A value less than 2048 has no effect
+    /// because the algorithms always reject such a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - a minimum RSA key length
+    pub fn set_minimum_rsa_bits(&mut self, bits: u32) {
+        self.minimum_rsa_bits = bits;
+    }
+
+    /// Test a curve name is allowed for EC and OKP keys.
+    ///
+    /// All curves are allowed until a list is set by `set_allowed_curves`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a curve name (e.g. "P-256", "secp256k1" or "Ed25519")
+    pub fn is_allowed_curve(&self, name: &str) -> bool {
+        match &self.allowed_curves {
+            Some(vals) => vals.contains(name),
+            None => true,
+        }
+    }
+
+    /// Set curve names that are allowed for EC and OKP keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `curves` - allowed curve names
+    pub fn set_allowed_curves(&mut self, curves: &[&str]) {
+        self.allowed_curves = Some(curves.iter().map(|val| val.to_string()).collect());
+    }
+
+    /// Remove the list of allowed curve names.
+    pub fn remove_allowed_curves(&mut self) {
+        self.allowed_curves = None;
+    }
+
+    /// Check a JWK satisfies the key policy of this context.
+    ///
+    /// A RSA key must have a modulus of at least `minimum_rsa_bits` bits,
+    /// and a EC or OKP key must use a allowed curve. The same policy is applied
+    /// to the public key of a verifier when a JWS is verified.
+    ///
+    /// # Arguments
+    ///
+    /// * `jwk` - a JWK to be used for building a signer or verifier.
+    pub fn check_jwk(&self, jwk: &Jwk) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            match jwk.key_type() {
+                "RSA" => {
+                    let n = match jwk.parameter("n") {
+                        Some(Value::String(val)) => {
+                            base64::decode_config(val, base64::URL_SAFE_NO_PAD)?
+                        }
+                        Some(_) => bail!("A parameter n must be a string."),
+                        None => bail!("A parameter n is required."),
+                    };
+                    let bits = match n.iter().position(|b| *b != 0) {
+                        Some(i) => (n.len() - i) as u32 * 8 - n[i].leading_zeros(),
+                        None => 0,
+                    };
+                    self.check_rsa_bits(bits)?;
+                }
+                "EC" | "OKP" => {
+                    let curve = match jwk.curve() {
+                        Some(val) => val,
+                        None => bail!("A parameter crv is required."),
+                    };
+                    self.check_curve(curve)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+
+    fn check_rsa_bits(&self, bits: u32) -> Result<(), KeyPolicyError> {
+        if bits < self.minimum_rsa_bits {
+            return Err(KeyPolicyError {
+                reason: format!("key length must be {} or more.", self.minimum_rsa_bits),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_curve(&self, curve: &str) -> Result<(), KeyPolicyError> {
+        if !self.is_allowed_curve(curve) {
+            return Err(KeyPolicyError {
+                reason: format!("The curve is not allowed: {}", curve),
+            });
+        }
+        if curve == "secp256k1" && !self.secp256k1_enabled {
+            return Err(KeyPolicyError {
+                reason: "The secp256k1 curve is disabled.".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_verifier_key(&self, verifier: &dyn JwsVerifier) -> anyhow::Result<()> {
+        let public_key = match verifier.public_key() {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+
+        match public_key.id() {
+            Id::RSA | Id::RSA_PSS => self.check_rsa_bits(public_key.bits())?,
+            Id::EC => {
+                let curve = match public_key.ec_key()?.group().curve_name() {
+                    Some(Nid::X9_62_PRIME256V1) => "P-256",
+                    Some(Nid::SECP384R1) => "P-384",
+                    Some(Nid::SECP521R1) => "P-521",
+                    Some(Nid::SECP256K1) => "secp256k1",
+                    _ => bail!("The curve of the verifying key is unknown."),
+                };
+                self.check_curve(curve)?;
+            }
+            Id::ED25519 => self.check_curve("Ed25519")?,
+            Id::ED448 => self.check_curve("Ed448")?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Test the leaf certificate of a x5c header claim is checked against the verifying key.
+    pub fn is_verify_x5c_matches_key(&self) -> bool {
+        self.verify_x5c_matches_key
+    }
+
+    /// Set whether the leaf certificate of a x5c header claim is checked against the verifying key.
+    ///
+    /// When it is true and the JWS header has a x5c claim, the public key of the leaf
+    /// certificate must be equal to the public key of the selected verifier.
+    /// A verifier that has no public key (e.g. HMAC) is rejected in that case.
+    /// The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the x5c header claim is checked
+    pub fn set_verify_x5c_matches_key(&mut self, value: bool) {
+        self.verify_x5c_matches_key = value;
+    }
+
+    /// Test the ES256K algorithm (ECDSA using secp256k1 curve) is enabled.
+    pub fn is_secp256k1_enabled(&self) -> bool {
+        self.secp256k1_enabled
+    }
+
+    /// Set whether the ES256K algorithm (ECDSA using secp256k1 curve) is enabled.
+    ///
+    /// When it is false, serializing with a ES256K signer, deserializing with a ES256K
+    /// verifier or a JWS that has a ES256K alg header claim, and checking a secp256k1 JWK
+    /// are rejected. The default is true.
+    ///
+    /// This switch is independent of `JwtContext::set_allowed_algorithms`:
+    /// a JWT is decoded only when its alg header claim passes both checks,
+    /// so ES256K is rejected even if it is in the list of allowed algorithms.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the ES256K algorithm is enabled
+    pub fn set_secp256k1_enabled(&mut self, value: bool) {
+        self.secp256k1_enabled = value;
+    }
+
+    /// Test a HMAC key shorter than the hash output is accepted.
+    pub fn is_allow_short_hmac_key(&self) -> bool {
+        self.allow_short_hmac_key
+    }
+
+    /// Set whether a HMAC key shorter than the hash output is accepted.
+    ///
+    /// RFC 7518 3.2 requires a key of the same size as the hash output or larger,
+    /// so it is rejected by default. This switch only applies to the signers and
+    /// verifiers that are created by `hmac_signer_from_bytes`, `hmac_signer_from_jwk`,
+    /// `hmac_verifier_from_bytes` and `hmac_verifier_from_jwk` of this context.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if a short HMAC key is accepted
+    pub fn set_allow_short_hmac_key(&mut self, value: bool) {
+        self.allow_short_hmac_key = value;
+    }
+
+    /// Return a HMAC signer from a secret key.
+    ///
+    /// # Arguments
+    ///
+    /// * `alg` - a HMAC algorithm
+    /// * `input` - a secret key
+    pub fn hmac_signer_from_bytes(
+        &self,
+        alg: HmacJwsAlgorithm,
+        input: impl AsRef<[u8]>,
+    ) -> Result<HmacJwsSigner, JoseError> {
+        alg.signer_from_bytes_with(input, self.allow_short_hmac_key)
+    }
+
+    /// Return a HMAC signer from a secret key that is formatted by a JWK of oct type.
+    ///
+    /// # Arguments
+    ///
+    /// * `alg` - a HMAC algorithm
+    /// * `jwk` - a secret key that is formatted by a JWK of oct type
+    pub fn hmac_signer_from_jwk(
+        &self,
+        alg: HmacJwsAlgorithm,
+        jwk: &Jwk,
+    ) -> Result<HmacJwsSigner, JoseError> {
+        alg.signer_from_jwk_with(jwk, self.allow_short_hmac_key)
+    }
+
+    /// Return a HMAC verifier from a secret key.
+    ///
+    /// # Arguments
+    ///
+    /// * `alg` - a HMAC algorithm
+    /// * `input` - a secret key
+    pub fn hmac_verifier_from_bytes(
+        &self,
+        alg: HmacJwsAlgorithm,
+        input: impl AsRef<[u8]>,
+    ) -> Result<HmacJwsVerifier, JoseError> {
+        alg.verifier_from_bytes_with(input, self.allow_short_hmac_key)
+    }
+
+    /// Return a HMAC verifier from a secret key that is formatted by a JWK of oct type.
+    ///
+    /// # Arguments
+    ///
+    /// * `alg` - a HMAC algorithm
+    /// * `jwk` - a secret key that is formatted by a JWK of oct type
+    pub fn hmac_verifier_from_jwk(
+        &self,
+        alg: HmacJwsAlgorithm,
+        jwk: &Jwk,
+    ) -> Result<HmacJwsVerifier, JoseError> {
+        alg.verifier_from_jwk_with(jwk, self.allow_short_hmac_key)
+    }
+
+    fn check_secp256k1_enabled(&self, alg: &str) -> anyhow::Result<()> {
+        if alg == "ES256K" && !self.secp256k1_enabled {
+            bail!("The ES256K algorithm is disabled.");
+        }
+        Ok(())
+    }
+
+    /// Test a JWS alg header claim value is supported by this crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a JWS alg header claim value
+    pub(crate) fn is_supported_algorithm(name: &str) -> bool {
+        Self::SUPPORTED_ALGORITHMS.contains(&name)
+    }
+
+    /// Test a JWS alg header claim value is a symmetric (HMAC) algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a JWS alg header claim value
+    pub(crate) fn is_symmetric_algorithm(name: &str) -> bool {
+        matches!(name, "HS256" | "HS384" | "HS512")
+    }
+
+    /// Return a error if one of the algorithms is symmetric and the other is asymmetric.
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` - a algorithm of the verifier
+    /// * `found` - a JWS alg header claim value
+    pub(crate) fn check_algorithm_confusion(
+        expected: &str,
+        found: &str,
+    ) -> Result<(), AlgorithmConfusionError> {
+        if expected != "none"
+            && found != "none"
+            && Self::is_symmetric_algorithm(expected) != Self::is_symmetric_algorithm(found)
+        {
+            return Err(AlgorithmConfusionError {
+                expected: expected.to_string(),
+                found: found.to_string(),
+            });
+        }
+        Ok(())
+    }
+
     /// Return a representation of the data that is formatted by compact serialization.
     ///
     /// # Arguments
@@ -92,6 +469,7 @@ impl JwsContext {
                 Some(val) => val,
                 None => bail!("A signer is not found."),
             };
+            self.check_secp256k1_enabled(signer.algorithm().name())?;
 
             let mut header = header.claims_set().clone();
             header.insert(
@@ -191,6 +569,7 @@ impl JwsContext {
                     Some(val) => val,
                     None => bail!("A signer is not found."),
                 };
+                self.check_secp256k1_enabled(signer.algorithm().name())?;
 
                 let mut protected_map = header.claims_set(true).clone();
 
@@ -307,6 +686,7 @@ impl JwsContext {
                 Some(val) => val,
                 None => bail!("A signer is not found."),
             };
+            self.check_secp256k1_enabled(signer.algorithm().name())?;
 
             let mut protected_map = protected_map.clone();
 
@@ -401,79 +781,171 @@ impl JwsContext {
     {
         (|| -> anyhow::Result<(Vec<u8>, JwsHeader)> {
             let input = input.as_ref();
-            let indexies: Vec<usize> = input
-                .iter()
-                .enumerate()
-                .filter(|(_, b)| **b == b'.' as u8)
-                .map(|(pos, _)| pos)
-                .collect();
-            if indexies.len() != 2 {
-                bail!(
-                    "The compact serialization form of JWS must be three parts separated by colon."
-                );
-            }
-
-            let header = &input[0..indexies[0]];
-            let payload = &input[(indexies[0] + 1)..(indexies[1])];
-            let signature = &input[(indexies[1] + 1)..];
-
-            let header = base64::decode_config(header, base64::URL_SAFE_NO_PAD)?;
-            let header: Map<String, Value> = serde_json::from_slice(&header)?;
+            let (header, parts) = util::split_and_decode_segments(input, &[3])?;
             let header = JwsHeader::from_map(header)?;
 
+            // Unsupported critical header claims are rejected before the selector is called.
+            self.check_critical(&header)?;
+
             let verifier = match selector(&header)? {
                 Some(val) => val,
                 None => bail!("A verifier is not found."),
             };
 
-            match header.claim("alg") {
-                Some(Value::String(val)) => {
-                    let expected_alg = verifier.algorithm().name();
-                    if val != expected_alg {
-                        bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
-                    }
-                }
-                Some(_) => bail!("The JWS alg header claim must be a string."),
-                None => bail!("The JWS alg header claim is required."),
-            }
+            let signature = util::decode_segment(&parts, 2)?;
+            let payload =
+                self.verify_compact_parts(&header, parts[0], parts[1], &signature, verifier)?;
+
+            Ok((payload, header))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
 
-            match verifier.key_id() {
-                Some(expected) => match header.key_id() {
-                    Some(actual) if expected == actual => {}
-                    Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
-                    None => bail!("The JWS kid header claim is required."),
-                },
-                None => {}
+    /// Verify the components of a compact serialization whose header is already decoded,
+    /// and return the payload.
+    ///
+    /// This is useful to decode the header once, make a routing decision with it,
+    /// and then verify with the decoded header. The signing input is built from the
+    /// header and payload parts, and the header part must be decoded to the same
+    /// header claims, so the returned payload is always the signed one.
+    /// The same checks as `deserialize_compact` are applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The decoded JWS header claims.
+    /// * `header_part` - The header part of the compact serialization (base64url encoded).
+    /// * `payload` - The payload part of the compact serialization (base64url encoded unless b64 is false).
+    /// * `signature` - The decoded JWS signature.
+    /// * `verifier` - The JWS verifier.
+    pub fn verify_with_decoded_header(
+        &self,
+        header: &JwsHeader,
+        header_part: &[u8],
+        payload: &[u8],
+        signature: &[u8],
+        verifier: &dyn JwsVerifier,
+    ) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let header_bytes = base64::decode_config(header_part, base64::URL_SAFE_NO_PAD)?;
+            let header_map: Map<String, Value> = serde_json::from_slice(&header_bytes)?;
+            if &header_map != header.claims_set() {
+                bail!("The header part is not decoded to the JWS header claims.");
             }
 
-            let mut b64 = true;
-            if let Some(Value::Array(vals)) = header.claim("crit") {
-                for val in vals {
-                    if let Value::String(val2) = val {
-                        if !self.is_acceptable_critical(val2) {
-                            bail!("The critical name '{}' is not supported.", val2);
-                        }
+            self.verify_compact_parts(header, header_part, payload, signature, verifier)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
 
-                        if val2 == "b64" {
-                            if let Some(val) = header.base64url_encode_payload() {
-                                b64 = val;
-                            }
-                        }
+    fn verify_compact_parts(
+        &self,
+        header: &JwsHeader,
+        header_part: &[u8],
+        payload: &[u8],
+        signature: &[u8],
+        verifier: &dyn JwsVerifier,
+    ) -> anyhow::Result<Vec<u8>> {
+        let b64 = self.check_critical(header)?;
+
+        match header.claim("alg") {
+            Some(Value::String(val)) => {
+                let expected_alg = verifier.algorithm().name();
+                if val != expected_alg {
+                    if !Self::is_supported_algorithm(val) {
+                        return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into());
                     }
+                    Self::check_algorithm_confusion(expected_alg, val)?;
+                    bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
                 }
             }
+            Some(_) => bail!("The JWS alg header claim must be a string."),
+            None => bail!("The JWS alg header claim is required."),
+        }
+        self.check_secp256k1_enabled(verifier.algorithm().name())?;
+        self.check_verifier_key(verifier)?;
+
+        if let Some(expected) = verifier.key_id() {
+            match header.key_id() {
+                Some(actual) if expected == actual => {}
+                Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
+                None => bail!("The JWS kid header claim is required."),
+            }
+        }
 
-            let message = &input[..(indexies[1])];
-            let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
-            verifier.verify(message, &signature)?;
+        self.check_x5c_matches_key(header, verifier)?;
 
-            let payload = if b64 {
-                base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?
+        let mut signing_input = Vec::with_capacity(header_part.len() + 1 + payload.len());
+        signing_input.extend_from_slice(header_part);
+        signing_input.push(b'.');
+        signing_input.extend_from_slice(payload);
+        verifier.verify(&signing_input, signature)?;
+
+        // The handlers are only called with a header that is verified.
+        self.handle_criticals(header)?;
+
+        let payload = if b64 {
+            base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?
+        } else {
+            payload.to_vec()
+        };
+
+        Ok(payload)
+    }
+
+    /// Verify a detached compact serialization whose payload is a serializable value,
+    /// and return the decoded JWS header.
+    ///
+    /// The signing input is reconstructed by serializing the payload to JSON.
+    /// If `canonical` is true, the members of all objects are sorted by key and
+    /// no whitespace is included, so the signer must sign the same canonical form.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The first part of the compact serialization (base64url encoded).
+    /// * `payload` - The detached payload value.
+    /// * `signature` - The third part of the compact serialization (base64url encoded).
+    /// * `canonical` - true if the payload is serialized to the canonical JSON.
+    /// * `verifier` - The JWS verifier.
+    pub fn verify_detached_typed<T: Serialize>(
+        &self,
+        header: &str,
+        payload: &T,
+        signature: &str,
+        canonical: bool,
+        verifier: &dyn JwsVerifier,
+    ) -> Result<JwsHeader, JoseError> {
+        (|| -> anyhow::Result<JwsHeader> {
+            let payload = if canonical {
+                util::to_canonical_json(&serde_json::to_value(payload)?).into_bytes()
             } else {
-                payload.to_vec()
+                serde_json::to_vec(payload)?
             };
 
-            Ok((payload, header))
+            let header_bytes = base64::decode_config(header, base64::URL_SAFE_NO_PAD)?;
+            let header_map: Map<String, Value> = serde_json::from_slice(&header_bytes)?;
+            let decoded_header = JwsHeader::from_map(header_map)?;
+
+            let payload_part = match decoded_header.base64url_encode_payload() {
+                Some(false) => payload,
+                _ => base64::encode_config(&payload, base64::URL_SAFE_NO_PAD).into_bytes(),
+            };
+
+            let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
+            self.verify_compact_parts(
+                &decoded_header,
+                header.as_bytes(),
+                &payload_part,
+                &signature,
+                verifier,
+            )?;
+
+            Ok(decoded_header)
         })()
         .map_err(|err| match err.downcast::<JoseError>() {
             Ok(err) => err,
@@ -494,25 +966,7 @@ impl JwsContext {
         verifier: &'a dyn JwsVerifier,
     ) -> Result<(Vec<u8>, JwsHeader), JoseError> {
         self.deserialize_json_with_selector(input, |header| {
-            match header.algorithm() {
-                Some(val) => {
-                    let expected_alg = verifier.algorithm().name();
-                    if val != expected_alg {
-                        return Ok(None);
-                    }
-                }
-                _ => return Ok(None),
-            }
-
-            match verifier.key_id() {
-                Some(expected) => match header.key_id() {
-                    Some(actual) if expected == actual => {}
-                    _ => return Ok(None),
-                },
-                None => {}
-            }
-
-            Ok(Some(verifier))
+            Ok(Self::match_verifier(header, verifier))
         })
     }
 
@@ -532,141 +986,378 @@ impl JwsContext {
         F: Fn(&JwsHeader) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
     {
         (|| -> anyhow::Result<(Vec<u8>, JwsHeader)> {
-            let input = input.as_ref();
-            let mut map: Map<String, Value> = serde_json::from_slice(input)?;
+            let (payload_b64, signatures) = Self::parse_json(input.as_ref())?;
+
+            let mut rejected = Vec::new();
+            for sig in signatures {
+                if let Some(val) =
+                    self.verify_json_signature(sig, &payload_b64, &selector, &mut rejected)?
+                {
+                    return Ok(val);
+                }
+            }
 
-            let payload_b64 = match map.remove("payload") {
-                Some(Value::String(val)) => val,
-                Some(_) => bail!("The payload field must be string."),
-                None => bail!("The payload field is required."),
-            };
+            if let Some(err) = rejected.into_iter().next() {
+                return Err(err.into());
+            }
+            bail!("A signature that matched the header claims is not found.");
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
 
-            let signatures = match map.remove("signatures") {
-                Some(Value::Array(vals)) => {
-                    let mut vec = Vec::with_capacity(vals.len());
-                    for val in vals {
-                        if let Value::Object(val) = val {
-                            vec.push(val);
-                        } else {
-                            bail!("The signatures field must be a array of object.");
+    /// Deserialize the input that is formatted by general json serialization
+    /// and requires that at least N signatures are verified by distinct verifiers.
+    ///
+    /// Verifiers that have the same public key are counted once. A verifier that has
+    /// no public key (e.g. HMAC) is distinguished only by its position in `verifiers`.
+    /// When the quorum is not reached, the error contains the first error of each
+    /// signature that is not verified.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `verifiers` - The JWS verifiers.
+    /// * `n` - The minimum number of verified signatures.
+    pub fn deserialize_general_json_quorum(
+        &self,
+        input: impl AsRef<[u8]>,
+        verifiers: &[&dyn JwsVerifier],
+        n: usize,
+    ) -> Result<(Vec<u8>, Vec<JwsHeader>), JoseError> {
+        (|| -> anyhow::Result<(Vec<u8>, Vec<JwsHeader>)> {
+            if n == 0 {
+                bail!("The quorum must be 1 or more.");
+            }
+
+            let (payload_b64, signatures) = Self::parse_json(input.as_ref())?;
+
+            let mut identities = Vec::with_capacity(verifiers.len());
+            for (i, verifier) in verifiers.iter().enumerate() {
+                identities.push(match verifier.public_key() {
+                    Some(val) => (Some(val.public_key_to_der()?), 0),
+                    None => (None, i),
+                });
+            }
+
+            let mut used = BTreeSet::new();
+            let mut payload = None;
+            let mut headers = Vec::new();
+            let mut errors = Vec::new();
+            for (index, sig) in signatures.into_iter().enumerate() {
+                let mut error = None;
+                let mut verified = false;
+                for (verifier, identity) in verifiers.iter().zip(&identities) {
+                    if used.contains(identity) {
+                        continue;
+                    }
+
+                    let selector = |header: &JwsHeader| Ok(Self::match_verifier(header, *verifier));
+                    let mut rejected = Vec::new();
+                    match self.verify_json_signature(
+                        sig.clone(),
+                        &payload_b64,
+                        &selector,
+                        &mut rejected,
+                    ) {
+                        Ok(Some((val, header))) => {
+                            used.insert(identity.clone());
+                            payload = Some(val);
+                            headers.push(header);
+                            verified = true;
+                            break;
+                        }
+                        Ok(None) => {
+                            if error.is_none() {
+                                error = rejected.into_iter().next().map(anyhow::Error::from);
+                            }
+                        }
+                        Err(err) => {
+                            if error.is_none() {
+                                error = Some(err);
+                            }
                         }
                     }
-                    vec
                 }
-                Some(_) => bail!("The signatures field must be a array."),
-                None => {
-                    let mut vec = Vec::with_capacity(1);
-                    vec.push(map);
-                    vec
+                if let (false, Some(err)) = (verified, error) {
+                    errors.push(format!("signatures[{}]: {:#}", index, err));
                 }
-            };
+            }
 
-            for mut sig in signatures {
-                let header = sig.remove("header");
+            match payload {
+                Some(val) if headers.len() >= n => Ok((val, headers)),
+                _ if errors.is_empty() => bail!(
+                    "The number of verified signatures is less than {}: {}",
+                    n,
+                    headers.len()
+                ),
+                _ => bail!(
+                    "The number of verified signatures is less than {}: {} ({})",
+                    n,
+                    headers.len(),
+                    errors.join("; ")
+                ),
+            }
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
 
-                let protected_b64 = match sig.get("protected") {
-                    Some(Value::String(val)) => val,
-                    Some(_) => bail!("The protected field must be a string."),
-                    None => bail!("The JWS alg header claim must be in protected."),
-                };
+    fn parse_json(input: &[u8]) -> anyhow::Result<(String, Vec<Map<String, Value>>)> {
+        let mut map: Map<String, Value> = serde_json::from_slice(input)?;
 
-                let protected_vec = base64::decode_config(&protected_b64, base64::URL_SAFE_NO_PAD)?;
-                let protected_map: Map<String, Value> = serde_json::from_slice(&protected_vec)?;
+        let payload_b64 = match map.remove("payload") {
+            Some(Value::String(val)) => val,
+            Some(_) => bail!("The payload field must be string."),
+            None => bail!("The payload field is required."),
+        };
 
-                let mut b64 = true;
-                if let Some(Value::Array(vals)) = protected_map.get("critical") {
-                    for val in vals {
-                        match val {
-                            Value::String(name) => {
-                                if !self.is_acceptable_critical(name) {
-                                    bail!("The critical name '{}' is not supported.", name);
-                                }
+        let signatures = match map.remove("signatures") {
+            Some(Value::Array(vals)) => {
+                let mut vec = Vec::with_capacity(vals.len());
+                for val in vals {
+                    if let Value::Object(val) = val {
+                        vec.push(val);
+                    } else {
+                        bail!("The signatures field must be a array of object.");
+                    }
+                }
+                vec
+            }
+            Some(_) => bail!("The signatures field must be a array."),
+            None => {
+                let mut vec = Vec::with_capacity(1);
+                vec.push(map);
+                vec
+            }
+        };
+
+        Ok((payload_b64, signatures))
+    }
 
-                                if name == "b64" {
-                                    match protected_map.get("b64") {
-                                        Some(Value::Bool(b64_val)) => {
-                                            b64 = *b64_val;
-                                        }
-                                        Some(_) => bail!("The JWS b64 header claim must be bool."),
-                                        None => {}
-                                    }
+    /// Verify a signature of a json serialization.
+    ///
+    /// None is returned if no verifier is selected, or if a critical handler rejects
+    /// the verified header. The error of the handler, or a unsupported algorithm
+    /// error when no verifier is selected, is pushed to `rejected`.
+    fn verify_json_signature<'a, F>(
+        &self,
+        mut sig: Map<String, Value>,
+        payload_b64: &str,
+        selector: &F,
+        rejected: &mut Vec<JoseError>,
+    ) -> anyhow::Result<Option<(Vec<u8>, JwsHeader)>>
+    where
+        F: Fn(&JwsHeader) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
+    {
+        let header = sig.remove("header");
+
+        let protected_b64 = match sig.get("protected") {
+            Some(Value::String(val)) => val,
+            Some(_) => bail!("The protected field must be a string."),
+            None => bail!("The JWS alg header claim must be in protected."),
+        };
+
+        let protected_vec = base64::decode_config(&protected_b64, base64::URL_SAFE_NO_PAD)?;
+        let protected_map: Map<String, Value> = serde_json::from_slice(&protected_vec)?;
+
+        let mut b64 = true;
+        if let Some(Value::Array(vals)) = protected_map.get("crit") {
+            for val in vals {
+                match val {
+                    Value::String(name) => {
+                        if !self.is_acceptable_critical(name) {
+                            bail!("The critical name '{}' is not supported.", name);
+                        }
+                        // The critical header claims must be integrity protected.
+                        if !protected_map.contains_key(name) {
+                            bail!(
+                                "The critical header claim '{}' is not found in protected.",
+                                name
+                            );
+                        }
+
+                        if name == "b64" {
+                            match protected_map.get("b64") {
+                                Some(Value::Bool(b64_val)) => {
+                                    b64 = *b64_val;
                                 }
+                                Some(_) => bail!("The JWS b64 header claim must be bool."),
+                                None => {}
                             }
-                            _ => bail!("The JWS critical header claim must be a array of string."),
                         }
                     }
+                    _ => bail!("The JWS critical header claim must be a array of string."),
                 }
+            }
+        }
 
-                let merged_map = match header {
-                    Some(Value::Object(mut val)) => {
-                        for (key, value) in protected_map {
-                            if val.contains_key(&key) {
-                                bail!("A duplicate key exists: {}", key);
-                            } else {
-                                val.insert(key.clone(), value.clone());
-                            }
-                        }
-                        val
-                    }
-                    Some(_) => bail!("The protected field must be a object."),
-                    None => protected_map.clone(),
-                };
+        let merged_map = match header {
+            Some(Value::Object(val)) => {
+                let protected = JwsHeader::from_map(protected_map)?;
+                JwsHeader::from_map(val)?.merged_with(&protected)?
+            }
+            Some(_) => bail!("The protected field must be a object."),
+            None => protected_map,
+        };
 
-                if let None = merged_map.get("alg") {
-                    bail!("The JWS alg header claim must be in protected.");
-                }
+        if let None = merged_map.get("alg") {
+            bail!("The JWS alg header claim must be in protected.");
+        }
 
-                let signature = match sig.get("signature") {
-                    Some(Value::String(val)) => {
-                        base64::decode_config(val, base64::URL_SAFE_NO_PAD)?
+        let signature = match sig.get("signature") {
+            Some(Value::String(val)) => base64::decode_config(val, base64::URL_SAFE_NO_PAD)?,
+            Some(_) => bail!("The signature field must be string."),
+            None => bail!("The signature field is required."),
+        };
+
+        let merged = JwsHeader::from_map(merged_map)?;
+        let verifier = match selector(&merged)? {
+            Some(val) => val,
+            None => {
+                if let Some(val) = merged.algorithm() {
+                    if !Self::is_supported_algorithm(val) {
+                        rejected.push(JoseError::UnsupportedAlgorithm(val.to_string()));
                     }
-                    Some(_) => bail!("The signature field must be string."),
-                    None => bail!("The signature field is required."),
-                };
+                }
+                return Ok(None);
+            }
+        };
+
+        match merged.claim("alg") {
+            Some(Value::String(val)) => {
+                let expected_alg = verifier.algorithm().name();
+                if val != expected_alg {
+                    if !Self::is_supported_algorithm(val) {
+                        return Err(JoseError::UnsupportedAlgorithm(val.to_string()).into());
+                    }
+                    Self::check_algorithm_confusion(expected_alg, val)?;
+                    bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
+                }
+            }
+            Some(_) => bail!("The JWS alg header claim must be a string."),
+            None => bail!("The JWS alg header claim is required."),
+        }
+        self.check_secp256k1_enabled(verifier.algorithm().name())?;
+        self.check_verifier_key(verifier)?;
+
+        match verifier.key_id() {
+            Some(expected) => match merged.key_id() {
+                Some(actual) if expected == actual => {}
+                Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
+                None => bail!("The JWS kid header claim is required."),
+            },
+            None => {}
+        }
 
-                let merged = JwsHeader::from_map(merged_map)?;
-                let verifier = match selector(&merged)? {
-                    Some(val) => val,
-                    None => continue,
-                };
+        self.check_x5c_matches_key(&merged, verifier)?;
+
+        let message = format!("{}.{}", &protected_b64, payload_b64);
+        verifier.verify(message.as_bytes(), &signature)?;
 
-                match merged.claim("alg") {
-                    Some(Value::String(val)) => {
-                        let expected_alg = verifier.algorithm().name();
-                        if val != expected_alg {
-                            bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
+        // The handlers are only called with a header that is verified,
+        // and a error rejects this signature only.
+        if let Err(err) = self.handle_criticals(&merged) {
+            rejected.push(err);
+            return Ok(None);
+        }
+
+        let payload = if b64 {
+            base64::decode_config(payload_b64, base64::URL_SAFE_NO_PAD)?
+        } else {
+            payload_b64.as_bytes().to_vec()
+        };
+
+        Ok(Some((payload, merged)))
+    }
+
+    fn check_critical(&self, header: &JwsHeader) -> anyhow::Result<bool> {
+        let mut b64 = true;
+        if let Some(Value::Array(vals)) = header.claim("crit") {
+            for val in vals {
+                if let Value::String(val2) = val {
+                    if !self.is_acceptable_critical(val2) {
+                        bail!("The critical name '{}' is not supported.", val2);
+                    }
+                    if header.claim(val2).is_none() {
+                        bail!("The critical header claim '{}' is not found.", val2);
+                    }
+
+                    if val2 == "b64" {
+                        if let Some(val) = header.base64url_encode_payload() {
+                            b64 = val;
                         }
                     }
-                    Some(_) => bail!("The JWS alg header claim must be a string."),
-                    None => bail!("The JWS alg header claim is required."),
                 }
+            }
+        }
+        Ok(b64)
+    }
 
-                match verifier.key_id() {
-                    Some(expected) => match merged.key_id() {
-                        Some(actual) if expected == actual => {}
-                        Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
-                        None => bail!("The JWS kid header claim is required."),
-                    },
-                    None => {}
+    fn handle_criticals(&self, header: &JwsHeader) -> Result<(), JoseError> {
+        if let Some(vals) = header.critical() {
+            for name in vals {
+                if !self.is_acceptable_critical(name) {
+                    continue;
                 }
+                if let Some(handler) = self.critical_handlers.get(name) {
+                    (handler.0)(header)?;
+                }
+            }
+        }
+        Ok(())
+    }
 
-                let message = format!("{}.{}", &protected_b64, &payload_b64);
-                verifier.verify(message.as_bytes(), &signature)?;
+    fn check_x5c_matches_key(
+        &self,
+        header: &JwsHeader,
+        verifier: &dyn JwsVerifier,
+    ) -> anyhow::Result<()> {
+        if !self.verify_x5c_matches_key || header.claim("x5c").is_none() {
+            return Ok(());
+        }
 
-                let payload = if b64 {
-                    base64::decode_config(&payload_b64, base64::URL_SAFE_NO_PAD)?
-                } else {
-                    payload_b64.into_bytes()
-                };
+        let leaf = match header.x509_certificate_chain() {
+            Some(vals) if !vals.is_empty() => X509::from_der(&vals[0])?,
+            _ => bail!("The JWS x5c header claim must be a non-empty array of certificates."),
+        };
+        let public_key = match verifier.public_key() {
+            Some(val) => val,
+            None => bail!("The verifier doesn't have a public key to match the x5c header claim."),
+        };
+        if !leaf.public_key()?.public_eq(public_key) {
+            bail!("The leaf certificate of the x5c header claim doesn't match the verifying key.");
+        }
+
+        Ok(())
+    }
 
-                return Ok((payload, merged));
+    fn match_verifier<'a>(
+        header: &JwsHeader,
+        verifier: &'a dyn JwsVerifier,
+    ) -> Option<&'a dyn JwsVerifier> {
+        match header.algorithm() {
+            Some(val) => {
+                let expected_alg = verifier.algorithm().name();
+                if val != expected_alg {
+                    return None;
+                }
             }
+            _ => return None,
+        }
 
-            bail!("A signature that matched the header claims is not found.");
-        })()
-        .map_err(|err| match err.downcast::<JoseError>() {
-            Ok(err) => err,
-            Err(err) => JoseError::InvalidJwsFormat(err),
-        })
+        match verifier.key_id() {
+            Some(expected) => match header.key_id() {
+                Some(actual) if expected == actual => {}
+                _ => return None,
+            },
+            None => {}
+        }
+
+        Some(verifier)
     }
 }
@@ -2,10 +2,12 @@ use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
 use anyhow::bail;
+use serde::Serialize;
 
+use crate::jose_header::private::Sealed;
 use crate::jwk::Jwk;
 use crate::util;
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::{JoseError, JoseHeader, JoseHeaderMerge, Map, Value};
 
 /// Represent JWS header claims
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -35,6 +37,25 @@ impl JwsHeader {
         Ok(header)
     }
 
+    /// Return a new header instance from a serializable value.
+    ///
+    /// The known header claims are checked in the same way as `from_map`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a value that is serialized to a JSON object.
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Self, JoseError> {
+        let claims = (|| -> anyhow::Result<Map<String, Value>> {
+            match serde_json::to_value(value)? {
+                Value::Object(val) => Ok(val),
+                _ => bail!("The JWS header must be a JSON object."),
+            }
+        })()
+        .map_err(JoseError::InvalidJson)?;
+
+        Self::from_map(claims)
+    }
+
     /// Return a new header instance from map.
     ///
     /// # Arguments
@@ -276,15 +297,20 @@ impl JwsHeader {
 
     /// Set values for critical header claim (crit).
     ///
+    /// A duplicate name is set only once.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
     pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
         let key = "crit";
-        let vec = values
-            .iter()
-            .map(|v| Value::String(v.as_ref().to_string()))
-            .collect();
+        let mut vec = Vec::with_capacity(values.len());
+        for val in values {
+            let val = Value::String(val.as_ref().to_string());
+            if !vec.contains(&val) {
+                vec.push(val);
+            }
+        }
         self.claims.insert(key.to_string(), Value::Array(vec));
     }
 
@@ -469,6 +495,24 @@ impl JoseHeader for JwsHeader {
     }
 }
 
+impl JoseHeaderMerge for JwsHeader {
+    fn to_map(&self) -> Map<String, Value> {
+        self.claims.clone()
+    }
+}
+
+impl Sealed for JwsHeader {
+    fn format_error(&self, err: anyhow::Error) -> JoseError {
+        JoseError::InvalidJwsFormat(err)
+    }
+}
+
+impl Default for JwsHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AsRef<Map<String, Value>> for JwsHeader {
     fn as_ref(&self) -> &Map<String, Value> {
         &self.claims
@@ -501,8 +545,10 @@ mod tests {
     use anyhow::Result;
     use serde_json::json;
 
+    use crate::jwe::JweHeader;
     use crate::jwk::Jwk;
-    use crate::jws::JwsHeader;
+    use crate::jws::{JwsHeader, JwsHeaderSet};
+    use crate::{JoseError, JoseHeader, JoseHeaderMerge};
 
     #[test]
     fn test_new_jws_header() -> Result<()> {
@@ -547,4 +593,56 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jws_header_merged_with() -> Result<()> {
+        let mut protected = JwsHeader::new();
+        protected.set_algorithm("ES256");
+        protected.set_critical(&vec!["exp"]);
+        let mut unprotected = JwsHeader::new();
+        unprotected.set_key_id("kid");
+
+        let merged = protected.merged_with(&unprotected)?;
+        assert_eq!(
+            merged.keys().collect::<Vec<_>>(),
+            vec!["alg", "crit", "kid"]
+        );
+        assert_eq!(merged.get("kid"), Some(&json!("kid")));
+
+        let mut header_set = JwsHeaderSet::new();
+        header_set.set_algorithm("ES256", true);
+        header_set.set_token_type("JWT", false);
+        let merged = header_set.merged_with(&unprotected)?;
+        assert_eq!(merged.len(), 3);
+
+        unprotected.set_algorithm("HS256");
+        assert!(matches!(
+            protected.merged_with(&unprotected),
+            Err(JoseError::InvalidJwsFormat(err)) if err.to_string().contains("alg")
+        ));
+        assert!(header_set.merged_with(&unprotected).is_err());
+
+        let mut jwe_header = JweHeader::new();
+        jwe_header.set_claim("kid", Some(json!("kid")))?;
+        assert!(header_set.merged_with(&jwe_header).is_ok());
+        jwe_header.set_algorithm("dir");
+        assert!(matches!(
+            jwe_header.merged_with(&protected),
+            Err(JoseError::InvalidJweFormat(_))
+        ));
+        assert_eq!(protected.merged_with(&JwsHeader::new())?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_jws_header() {
+        #[derive(Default)]
+        struct Holder {
+            header: JwsHeader,
+        }
+
+        let holder = Holder::default();
+        assert_eq!(holder.header, JwsHeader::new());
+    }
 }
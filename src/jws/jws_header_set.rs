@@ -1,9 +1,10 @@
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
+use crate::jose_header::private::Sealed;
 use crate::jwk::Jwk;
 use crate::jws::JwsHeader;
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::{JoseError, JoseHeader, JoseHeaderMerge, Map, Value};
 
 /// Represent JWS protected and unprotected header claims
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -317,15 +318,20 @@ impl JwsHeaderSet {
 
     /// Set values for critical header claim (crit).
     ///
+    /// A duplicate name is set only once.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
     pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
         let key = "crit";
-        let vec = values
-            .iter()
-            .map(|v| Value::String(base64::encode_config(v.as_ref(), base64::URL_SAFE_NO_PAD)))
-            .collect();
+        let mut vec = Vec::with_capacity(values.len());
+        for val in values {
+            let val = Value::String(val.as_ref().to_string());
+            if !vec.contains(&val) {
+                vec.push(val);
+            }
+        }
         self.unprotected.remove(key);
         self.protected.insert(key.to_string(), Value::Array(vec));
     }
@@ -483,6 +489,18 @@ impl JoseHeader for JwsHeaderSet {
     }
 }
 
+impl JoseHeaderMerge for JwsHeaderSet {
+    fn to_map(&self) -> Map<String, Value> {
+        Self::to_map(self)
+    }
+}
+
+impl Sealed for JwsHeaderSet {
+    fn format_error(&self, err: anyhow::Error) -> JoseError {
+        JoseError::InvalidJwsFormat(err)
+    }
+}
+
 impl Display for JwsHeaderSet {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let protected = serde_json::to_string(&self.protected).map_err(|_e| std::fmt::Error {})?;
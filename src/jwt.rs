@@ -1,16 +1,24 @@
 //! JSON Web Token (JWT) support.
 
 pub mod alg;
+mod jose_format;
 mod jwt_context;
 mod jwt_payload;
 mod jwt_payload_validator;
+mod signer_registry;
+mod token_policy;
 
+pub use crate::jwt::jose_format::JoseFormat;
 pub use crate::jwt::jwt_context::JwtContext;
 pub use crate::jwt::jwt_payload::JwtPayload;
 pub use crate::jwt::jwt_payload_validator::JwtPayloadValidator;
+pub use crate::jwt::signer_registry::SignerRegistry;
+pub use crate::jwt::token_policy::TokenPolicy;
 
 pub use crate::jwt::alg::unsecured::UnsecuredJwsAlgorithm::None;
 
+use std::time::SystemTime;
+
 use once_cell::sync::Lazy;
 
 use crate::jwe::{JweDecrypter, JweEncrypter, JweHeader};
@@ -69,6 +77,27 @@ pub fn decode_header(input: impl AsRef<[u8]>) -> Result<Box<dyn JoseHeader>, Jos
     DEFAULT_CONTEXT.decode_header(input)
 }
 
+/// Return the serialization format of a JWS or a JWE without decoding it.
+///
+/// # Arguments
+///
+/// * `input` - a JWS or JWE string representation.
+pub fn detect_format(input: &str) -> Result<JoseFormat, JoseError> {
+    JoseFormat::detect(input)
+}
+
+/// Test the JWT has expired at a base time.
+///
+/// The signature is not verified, so the result must not be used for any authorization decision.
+///
+/// # Arguments
+///
+/// * `input` - a JWT string representation.
+/// * `base_time` - a time to be compared with the exp payload claim.
+pub fn is_expired(input: impl AsRef<[u8]>, base_time: &SystemTime) -> Result<bool, JoseError> {
+    DEFAULT_CONTEXT.is_expired(input, base_time)
+}
+
 /// Return the JWT object decoded with the "none" algorithm.
 ///
 /// # Arguments
@@ -80,6 +109,9 @@ pub fn decode_unsecured(input: impl AsRef<[u8]>) -> Result<(JwtPayload, JwsHeade
 
 /// Return the JWT object decoded by the selected verifier.
 ///
+/// A verifier holds the parsed key, so many JWTs signed by the same key can be
+/// decoded with one verifier without parsing the key again.
+///
 /// # Arguments
 ///
 /// * `verifier` - a verifier of the signing algorithm.
@@ -91,6 +123,32 @@ pub fn decode_with_verifier(
     DEFAULT_CONTEXT.decode_with_verifier(input, verifier)
 }
 
+/// Return the JWT object decoded by the selected verifier and the protected header bytes.
+///
+/// # Arguments
+///
+/// * `input` - a JWT string representation.
+/// * `verifier` - a verifier of the signing algorithm.
+pub fn decode_with_verifier_raw(
+    input: impl AsRef<[u8]>,
+    verifier: &dyn JwsVerifier,
+) -> Result<(JwtPayload, JwsHeader, Vec<u8>), JoseError> {
+    DEFAULT_CONTEXT.decode_with_verifier_raw(input, verifier)
+}
+
+/// Return the JWT object decoded by the first verifier that succeeds and its label.
+///
+/// # Arguments
+///
+/// * `input` - a JWT string representation.
+/// * `keys` - pairs of a label (e.g. a key ID) and a verifier.
+pub fn decode_with_keys(
+    input: impl AsRef<[u8]>,
+    keys: &[(&str, &dyn JwsVerifier)],
+) -> Result<(JwtPayload, JwsHeader, String), JoseError> {
+    DEFAULT_CONTEXT.decode_with_keys(input, keys)
+}
+
 /// Return the JWT object decoded with a selected verifying algorithm.
 ///
 /// # Arguments
@@ -114,17 +172,40 @@ where
 /// * `input` - a JWT string representation.
 /// * `jwk_set` - a JWK set.
 /// * `selector` - a function for selecting the verifying algorithm.
-pub fn decode_with_verifier_in_jwk_set<F>(
+pub fn decode_with_verifier_in_jwk_set<'a, F>(
     input: impl AsRef<[u8]>,
     jwk_set: &JwkSet,
     selector: F,
 ) -> Result<(JwtPayload, JwsHeader), JoseError>
 where
-    F: Fn(&Jwk) -> Result<Option<&dyn JwsVerifier>, JoseError>,
+    F: Fn(&Jwk) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
 {
     DEFAULT_CONTEXT.decode_with_verifier_in_jwk_set(input, jwk_set, selector)
 }
 
+/// Return the JWT object decoded with a selected verifying algorithm,
+/// or with the JWK embedded in the jwk header claim if no verifier is selected.
+///
+/// See [`JwtContext::decode_with_verifier_selector_or_embedded_jwk`] for the security caveats.
+/// The embedded JWK must not be trusted just because it is present in the token.
+///
+/// # Arguments
+///
+/// * `input` - a JWT string representation.
+/// * `selector` - a function for selecting the verifying algorithm.
+/// * `accept` - a function for testing a embedded JWK is trusted.
+pub fn decode_with_verifier_selector_or_embedded_jwk<'a, F, P>(
+    input: impl AsRef<[u8]>,
+    selector: F,
+    accept: P,
+) -> Result<(JwtPayload, JwsHeader), JoseError>
+where
+    F: Fn(&JwsHeader) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
+    P: Fn(&Jwk) -> bool,
+{
+    DEFAULT_CONTEXT.decode_with_verifier_selector_or_embedded_jwk(input, selector, accept)
+}
+
 /// Return the JWT object decoded by the selected decrypter.
 ///
 /// # Arguments
@@ -156,24 +237,27 @@ where
 
 /// Return the JWT object decoded by using a JWK set.
 ///
+/// See [`JwtContext::decode_with_decrypter_in_jwk_set`] for how the JWKs are selected.
+///
 /// # Arguments
 ///
 /// * `input` - a JWT string representation.
 /// * `jwk_set` - a JWK set.
 /// * `selector` - a function for selecting the decrypting algorithm.
-pub fn decode_with_decrypter_in_jwk_set<F>(
+pub fn decode_with_decrypter_in_jwk_set<'a, F>(
     input: impl AsRef<[u8]>,
     jwk_set: &JwkSet,
     selector: F,
 ) -> Result<(JwtPayload, JweHeader), JoseError>
 where
-    F: Fn(&Jwk) -> Result<Option<&dyn JweDecrypter>, JoseError>,
+    F: Fn(&Jwk) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
 {
     DEFAULT_CONTEXT.decode_with_decrypter_in_jwk_set(input, jwk_set, selector)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
     use std::fs;
     use std::path::PathBuf;
     use std::time::{Duration, SystemTime};
@@ -183,18 +267,18 @@ mod tests {
 
     #[allow(deprecated)]
     use crate::jwe::{
-        Dir, A128GCMKW, A128KW, A192GCMKW, A192KW, A256GCMKW, A256KW, ECDH_ES, ECDH_ES_A128KW,
-        ECDH_ES_A192KW, ECDH_ES_A256KW, PBES2_HS256_A128KW, PBES2_HS384_A192KW, PBES2_HS512_A256KW,
-        RSA1_5, RSA_OAEP, RSA_OAEP_256,
+        Dir, JweContext, JweDecrypter, JweHeader, A128GCMKW, A128KW, A192GCMKW, A192KW, A256GCMKW,
+        A256KW, ECDH_ES, ECDH_ES_A128KW, ECDH_ES_A192KW, ECDH_ES_A256KW, PBES2_HS256_A128KW,
+        PBES2_HS384_A192KW, PBES2_HS512_A256KW, RSA1_5, RSA_OAEP, RSA_OAEP_256,
     };
-    use crate::jwk::Jwk;
+    use crate::jwk::{Jwk, JwkSet, P_256};
     use crate::jws::{
-        EdDSA, JwsHeader, ES256, ES256K, ES384, ES512, HS256, HS384, HS512, PS256, PS384, PS512,
-        RS256, RS384, RS512,
+        self, EdDSA, JwsHeader, JwsVerifier, ES256, ES256K, ES384, ES512, HS256, HS384, HS512,
+        PS256, PS384, PS512, RS256, RS384, RS512,
     };
-    use crate::jwt::{self, JwtPayload};
+    use crate::jwt::{self, JwtContext, JwtPayload};
     use crate::util;
-    use crate::Value;
+    use crate::{JoseError, Map, SegmentCountError, SegmentDecodeError, Value};
 
     #[test]
     fn test_decode_header() -> Result<()> {
@@ -224,6 +308,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_decode_many_tokens_with_one_verifier() -> Result<()> {
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let signer = RS256.signer_from_pem(&private_key)?;
+        let verifier = RS256.verifier_from_pem(&public_key)?;
+
+        let mut tokens = Vec::new();
+        for i in 0..3 {
+            let mut payload = JwtPayload::new();
+            payload.set_subject(format!("user{}", i));
+            tokens.push(jwt::encode_with_signer(
+                &payload,
+                &JwsHeader::new(),
+                &signer,
+            )?);
+        }
+
+        // The payload of the first token with the signature of the second token.
+        let parts0: Vec<&str> = tokens[0].split('.').collect();
+        let parts1: Vec<&str> = tokens[1].split('.').collect();
+        let tampered = format!("{}.{}.{}", parts0[0], parts0[1], parts1[2]);
+
+        let results: Vec<_> = [
+            tokens[0].as_str(),
+            &tampered,
+            "not a token",
+            &tokens[1],
+            &tokens[2],
+        ]
+        .iter()
+        .map(|token| jwt::decode_with_verifier(token, &verifier))
+        .collect();
+
+        assert!(matches!(&results[0], Ok((payload, _)) if payload.subject() == Some("user0")));
+        assert!(matches!(&results[1], Err(JoseError::InvalidSignature(_))));
+        assert!(matches!(&results[2], Err(_)));
+        assert!(matches!(&results[3], Ok((payload, _)) if payload.subject() == Some("user1")));
+        assert!(matches!(&results[4], Ok((payload, _)) if payload.subject() == Some("user2")));
+
+        Ok(())
+    }
+
     #[test]
     fn test_jwt_none() -> Result<()> {
         let alg = jwt::None;
@@ -265,6 +392,826 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwt_audience_always_array() -> Result<()> {
+        let private_key = util::random_bytes(64);
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("JWT");
+        let mut payload = JwtPayload::new();
+        payload.set_audience(vec!["aud0"]);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+
+        let mut context = JwtContext::new();
+        assert!(!context.is_audience_always_array());
+        let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+        let (dst_payload, _) = context.decode_with_verifier(&jwt_string, &verifier)?;
+        assert_eq!(dst_payload.claim("aud"), Some(&json!("aud0")));
+
+        context.set_audience_always_array(true);
+        let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+        let (dst_payload, _) = context.decode_with_verifier(&jwt_string, &verifier)?;
+        assert_eq!(dst_payload.claim("aud"), Some(&json!(["aud0"])));
+        assert_eq!(payload.claim("aud"), Some(&json!("aud0")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_verify_with_embedded_jwk() -> Result<()> {
+        let alg = ES256;
+        let key_pair = alg.generate_key_pair()?;
+        let mut public_key = key_pair.to_jwk_public_key();
+        public_key.set_key_use("sig");
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_jwk(public_key.clone());
+        let mut src_payload = JwtPayload::new();
+        src_payload.set_subject("sub");
+        let signer = alg.signer_from_jwk(&key_pair.to_jwk_private_key())?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+
+        // The embedded JWK is not used by default.
+        assert!(jwt::decode_with_verifier_selector(&jwt_string, |_header| Ok(None)).is_err());
+
+        let (dst_payload, _) = jwt::decode_with_verifier_selector_or_embedded_jwk(
+            &jwt_string,
+            |_header| Ok(None),
+            |jwk| jwk.key_use() == Some("sig"),
+        )?;
+        assert_eq!(src_payload, dst_payload);
+
+        let result = jwt::decode_with_verifier_selector_or_embedded_jwk(
+            &jwt_string,
+            |_header| Ok(None),
+            |jwk| jwk.key_use() == Some("enc"),
+        );
+        assert!(result.is_err());
+
+        // A selected verifier takes priority over the embedded JWK.
+        let other_key_pair = alg.generate_key_pair()?;
+        let other_verifier = alg.verifier_from_jwk(&other_key_pair.to_jwk_public_key())?;
+        let result = jwt::decode_with_verifier_selector_or_embedded_jwk(
+            &jwt_string,
+            |_header| Ok(Some(&other_verifier)),
+            |_jwk| true,
+        );
+        assert!(result.is_err());
+
+        // A embedded oct key is always rejected.
+        let private_key = util::random_bytes(64);
+        let mut src_header = JwsHeader::new();
+        src_header.set_jwk(Jwk::from_map({
+            let mut map = Map::new();
+            map.insert("kty".to_string(), json!("oct"));
+            map.insert(
+                "k".to_string(),
+                json!(base64::encode_config(&private_key, base64::URL_SAFE_NO_PAD)),
+            );
+            map
+        })?);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+        let result = jwt::decode_with_verifier_selector_or_embedded_jwk(
+            &jwt_string,
+            |_header| Ok(None),
+            |_jwk| true,
+        );
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_required_type() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let payload = JwtPayload::new();
+
+        let mut context = JwtContext::new();
+        assert_eq!(context.required_type(), None);
+        context.set_required_type(Some("JWT"));
+        assert_eq!(context.required_type(), Some("JWT"));
+
+        for typ in &["JWT", "jwt", "application/jwt", "Application/JWT"] {
+            let mut header = JwsHeader::new();
+            header.set_token_type(*typ);
+            let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+            context.decode_with_verifier(&jwt_string, &verifier)?;
+
+            let jwt_string = context.encode_unsecured(&payload, &header)?;
+            context.decode_unsecured(&jwt_string)?;
+        }
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("at+jwt");
+        let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+        match context.decode_with_verifier(&jwt_string, &verifier) {
+            Err(err) => assert!(format!("{}", err).contains("not JWT: at+jwt")),
+            Ok(_) => unreachable!(),
+        }
+
+        for typ in &["foo/jwt", "application/foo/jwt"] {
+            let mut header = JwsHeader::new();
+            header.set_token_type(*typ);
+            let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+            assert!(context
+                .decode_with_verifier(&jwt_string, &verifier)
+                .is_err());
+        }
+
+        context.set_required_type(Some("foo/jwt"));
+        for (typ, valid) in &[
+            ("Foo/JWT", true),
+            ("jwt", false),
+            ("application/foo/jwt", false),
+        ] {
+            let mut header = JwsHeader::new();
+            header.set_token_type(*typ);
+            let jwt_string = context.encode_with_signer(&payload, &header, &signer)?;
+            assert_eq!(
+                context.decode_with_verifier(&jwt_string, &verifier).is_ok(),
+                *valid,
+                "{}",
+                typ
+            );
+        }
+        context.set_required_type(Some("JWT"));
+
+        let jwt_string = context.encode_unsecured(&payload, &JwsHeader::new())?;
+        assert!(context.decode_unsecured(&jwt_string).is_err());
+
+        context.set_required_type(None);
+        context.decode_unsecured(&jwt_string)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_decode_with_verifier_raw() -> Result<()> {
+        let key = util::random_bytes(32);
+        let signer = HS256.signer_from_bytes(&key)?;
+        let verifier = HS256.verifier_from_bytes(&key)?;
+
+        let protected = b"{ \"typ\": \"JWT\",\n  \"alg\": \"HS256\" }";
+        let mut jwt_string = format!(
+            "{}.{}",
+            base64::encode_config(protected, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(b"{\"sub\":\"sub\"}", base64::URL_SAFE_NO_PAD)
+        );
+        let signature = signer.sign(jwt_string.as_bytes())?;
+        jwt_string.push('.');
+        jwt_string.push_str(&base64::encode_config(&signature, base64::URL_SAFE_NO_PAD));
+
+        let (payload, header, raw_header) = jwt::decode_with_verifier_raw(&jwt_string, &verifier)?;
+        assert_eq!(payload.subject(), Some("sub"));
+        assert_eq!(header.algorithm(), Some("HS256"));
+        assert_eq!(header.token_type(), Some("JWT"));
+        assert_eq!(raw_header, protected.to_vec());
+        assert_ne!(raw_header, header.to_string().into_bytes());
+
+        let other_verifier = HS256.verifier_from_bytes(&util::random_bytes(32))?;
+        assert!(jwt::decode_with_verifier_raw(&jwt_string, &other_verifier).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_decode_with_keys() -> Result<()> {
+        let old_key = util::random_bytes(64);
+        let new_key = util::random_bytes(64);
+        let other_key = util::random_bytes(64);
+
+        let mut src_payload = JwtPayload::new();
+        src_payload.set_subject("subject");
+        let signer = HS256.signer_from_bytes(&new_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &JwsHeader::new(), &signer)?;
+
+        let old_verifier = HS256.verifier_from_bytes(&old_key)?;
+        let new_verifier = HS256.verifier_from_bytes(&new_key)?;
+        let other_verifier = HS256.verifier_from_bytes(&other_key)?;
+
+        let (payload, _, label) = jwt::decode_with_keys(
+            &jwt_string,
+            &[
+                ("old", &old_verifier),
+                ("new", &new_verifier),
+                ("other", &other_verifier),
+            ],
+        )?;
+        assert_eq!(label, "new");
+        assert_eq!(payload, src_payload);
+
+        let result = jwt::decode_with_keys(
+            &jwt_string,
+            &[("old", &old_verifier), ("other", &other_verifier)],
+        );
+        assert!(matches!(result, Err(JoseError::InvalidSignature(_))));
+
+        assert!(jwt::decode_with_keys(&jwt_string, &[]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_canonical_payload() -> Result<()> {
+        let key = util::random_bytes(32);
+        let mut signer = HS256.signer_from_bytes(&key)?;
+        signer.set_key_id("key-1");
+        let verifier = HS256.verifier_from_bytes(&key)?;
+
+        let mut payload1 = JwtPayload::new();
+        payload1.set_subject("user");
+        payload1.set_claim("roles", Some(json!({"b": 2, "a": 1})))?;
+        payload1.set_issuer("issuer");
+
+        let mut payload2 = JwtPayload::new();
+        payload2.set_issuer("issuer");
+        payload2.set_claim("roles", Some(json!({"a": 1, "b": 2})))?;
+        payload2.set_subject("user");
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("JWT");
+
+        let mut context = JwtContext::new();
+        assert!(!context.is_canonical_payload());
+        assert_ne!(
+            context.encode_with_signer(&payload1, &header, &signer)?,
+            context.encode_with_signer(&payload2, &header, &signer)?
+        );
+
+        context.set_canonical_payload(true);
+        let jwt1 = context.encode_with_signer(&payload1, &header, &signer)?;
+        let jwt2 = context.encode_with_signer(&payload2, &header, &signer)?;
+        assert_eq!(jwt1, jwt2);
+
+        let parts: Vec<&str> = jwt1.split('.').collect();
+        assert_eq!(
+            base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?,
+            br#"{"alg":"HS256","kid":"key-1","typ":"JWT"}"#
+        );
+        assert_eq!(
+            base64::decode_config(parts[1], base64::URL_SAFE_NO_PAD)?,
+            br#"{"iss":"issuer","roles":{"a":1,"b":2},"sub":"user"}"#
+        );
+
+        let (decoded, _) = jwt::decode_with_verifier(&jwt1, &verifier)?;
+        assert!(decoded.semantically_eq(&payload1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_max_token_len() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&JwtPayload::new(), &JwsHeader::new(), &signer)?;
+
+        let mut context = JwtContext::new();
+        assert_eq!(context.max_token_len(), Some(1024 * 1024));
+        context.set_max_token_len(Some(jwt_string.len()));
+        context.decode_with_verifier(&jwt_string, &verifier)?;
+
+        // A oversized input is rejected without parsing, even if it is not a JWT at all.
+        let oversized = "a".repeat(10 * 1024 * 1024);
+        for err in vec![
+            context
+                .decode_with_verifier(&oversized, &verifier)
+                .unwrap_err(),
+            context.decode_header(&oversized).err().unwrap(),
+            context
+                .decode_with_decrypter(&oversized, &Dir.decrypter_from_bytes(&private_key)?)
+                .unwrap_err(),
+        ] {
+            let len_err = err.token_length_error().unwrap();
+            assert_eq!(len_err.max, jwt_string.len());
+            assert_eq!(len_err.found, oversized.len());
+        }
+
+        context.set_max_token_len(None);
+        let err = context.decode_header(&oversized).err().unwrap();
+        assert!(err.token_length_error().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_max_segment_len() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+
+        let mut payload = JwtPayload::new();
+        payload.set_claim("data", Some(json!("a".repeat(1000))))?;
+        let jwt_string = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+
+        let mut context = JwtContext::new();
+        assert_eq!(context.max_segment_len(), None);
+        context.set_max_segment_len(Some(1024 * 2));
+        context.decode_with_verifier(&jwt_string, &verifier)?;
+
+        context.set_max_segment_len(Some(1024));
+        let err = context
+            .decode_with_verifier(&jwt_string, &verifier)
+            .unwrap_err();
+        assert!(matches!(err, JoseError::InvalidJwtFormat(_)));
+        let segment_err = err.segment_decode_error().unwrap();
+        assert_eq!(segment_err.index, 1);
+        assert_eq!(segment_err.name, "payload");
+
+        // A oversized header is rejected before decoding.
+        let oversized = format!("{}.e30.", "a".repeat(1025));
+        let err = context.decode_header(&oversized).err().unwrap();
+        assert_eq!(err.segment_decode_error().map(|val| val.index), Some(0));
+
+        // The default token limit rejects a oversized token.
+        let context = JwtContext::new();
+        let oversized = format!("{}.e30.", "a".repeat(1024 * 1024));
+        let err = context.decode_header(&oversized).err().unwrap();
+        assert!(err.token_length_error().is_some());
+
+        // A header that is not a valid UTF-8 is rejected.
+        let input = format!(
+            "{}.e30.",
+            base64::encode_config(b"{\"alg\":\"\xff\"}", base64::URL_SAFE_NO_PAD)
+        );
+        let err = context.decode_header(&input).err().unwrap();
+        assert!(err
+            .segment_decode_error()
+            .map_or(false, |val| val.reason.contains("UTF-8")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_segment_count_error() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let decrypter = Dir.decrypter_from_bytes(util::random_bytes(16))?;
+
+        for (input, found) in &[("aaa.bbb", 2), ("aaa.bbb.ccc.ddd", 4)] {
+            let err = jwt::decode_with_verifier(input, &verifier).unwrap_err();
+            assert!(matches!(err, JoseError::InvalidJwsFormat(_)));
+            assert_eq!(
+                err.segment_count_error(),
+                Some(&SegmentCountError {
+                    expected: 3,
+                    found: *found
+                })
+            );
+            assert!(format!("{}", err).contains("separated by dots"));
+
+            let err = match jwt::decode_header(input) {
+                Err(err) => err,
+                Ok(_) => unreachable!(),
+            };
+            assert_eq!(
+                err.segment_count_error(),
+                Some(&SegmentCountError {
+                    expected: 3,
+                    found: *found
+                })
+            );
+
+            let err = jwt::decode_with_decrypter(input, &decrypter).unwrap_err();
+            assert_eq!(
+                err.segment_count_error(),
+                Some(&SegmentCountError {
+                    expected: 5,
+                    found: *found
+                })
+            );
+        }
+
+        let err = jwt::decode_with_verifier("a.b.c", &verifier).unwrap_err();
+        assert_eq!(err.segment_count_error(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_segment_decode_error() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let decrypter = Dir.decrypter_from_bytes(util::random_bytes(16))?;
+
+        let header = base64::encode_config(r#"{"alg":"HS256"}"#, base64::URL_SAFE_NO_PAD);
+        let cases: Vec<(Vec<u8>, usize, &str)> = vec![
+            (b"..".to_vec(), 0, "header"),
+            (b"!!!.bbb.ccc".to_vec(), 0, "header"),
+            (b"a.bbb.ccc".to_vec(), 0, "header"),
+            (
+                format!(
+                    "{}.bbb.ccc",
+                    base64::encode_config(b"\xff\xfe", base64::URL_SAFE_NO_PAD)
+                )
+                .into_bytes(),
+                0,
+                "header",
+            ),
+            (
+                format!(
+                    "{}.bbb.ccc",
+                    base64::encode_config("[1]", base64::URL_SAFE_NO_PAD)
+                )
+                .into_bytes(),
+                0,
+                "header",
+            ),
+            (format!("{}.bbb.!!!", header).into_bytes(), 2, "signature"),
+        ];
+        for (input, index, name) in &cases {
+            let err = jwt::decode_with_verifier(input, &verifier).unwrap_err();
+            assert!(matches!(err, JoseError::InvalidJwsFormat(_)));
+            let decode_error = err.segment_decode_error().unwrap();
+            assert_eq!(decode_error.index, *index);
+            assert_eq!(decode_error.name, *name);
+        }
+
+        let err = match jwt::decode_header(b"!!!.bbb.ccc") {
+            Err(err) => err,
+            Ok(_) => unreachable!(),
+        };
+        assert!(matches!(
+            err.segment_decode_error(),
+            Some(SegmentDecodeError {
+                index: 0,
+                name: "header",
+                ..
+            })
+        ));
+
+        let input = format!("{}.!!!.ccc", header);
+        let err = jwt::is_expired(&input, &SystemTime::now()).unwrap_err();
+        assert_eq!(
+            err.segment_decode_error().map(|val| val.name),
+            Some("payload")
+        );
+
+        let input = format!(
+            "{}..!!!.ccc.ddd",
+            base64::encode_config(r#"{"alg":"dir","enc":"A128GCM"}"#, base64::URL_SAFE_NO_PAD)
+        );
+        let err = jwt::decode_with_decrypter(&input, &decrypter).unwrap_err();
+        assert!(matches!(err, JoseError::InvalidJweFormat(_)));
+        assert_eq!(
+            err.segment_decode_error().map(|val| val.name),
+            Some("initialization vector")
+        );
+
+        // Malformed inputs never panic.
+        for input in &[
+            &b""[..],
+            b".",
+            b"....",
+            b"\xff.\xfe.\xfd",
+            b"eyJ.eyJ.",
+            b"eyJhbGciOiJub25lIn0..",
+            b"eyJhbGciOiJub25lIn0.\xff.",
+            b"eyJhbGciOiJIUzI1NiJ9.e30.A",
+        ] {
+            let _ = jwt::decode_header(input);
+            assert!(jwt::decode_with_verifier(input, &verifier).is_err());
+            assert!(jwt::decode_with_decrypter(input, &decrypter).is_err());
+            let _ = jwt::decode_unsecured(input);
+            let _ = jwt::is_expired(input, &SystemTime::now());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_with_empty_payload() -> Result<()> {
+        let private_key = util::random_bytes(64);
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jws::serialize_compact(b"", &src_header, &signer)?;
+        assert!(jwt_string.contains(".."));
+
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let (dst_payload, _) = jwt::decode_with_verifier(&jwt_string, &verifier)?;
+        assert_eq!(JwtPayload::new(), dst_payload);
+
+        let jwt_string = jwt::encode_with_signer(&JwtPayload::new(), &src_header, &signer)?;
+        let parts: Vec<&str> = jwt_string.split('.').collect();
+        assert_eq!(parts[1], "e30");
+        let (dst_payload, _) = jwt::decode_with_verifier(&jwt_string, &verifier)?;
+        assert_eq!(JwtPayload::new(), dst_payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_reject_unsupported_critical_before_selector() -> Result<()> {
+        let private_key = util::random_bytes(64);
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        src_header.set_critical(&vec!["unknown"]);
+        src_header.set_claim("unknown", Some(json!("value")))?;
+        let src_payload = JwtPayload::new();
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let selected = Cell::new(false);
+        let result = jwt::decode_with_verifier_selector(&jwt_string, |_header| {
+            selected.set(true);
+            Ok(Some(&verifier))
+        });
+        assert!(result.is_err());
+        assert!(!selected.get());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_clear_acceptable_criticals() -> Result<()> {
+        let names = ["crit1", "crit2", "crit3"];
+
+        let mut context = JwtContext::new();
+        let mut jwe_context = JweContext::new();
+        for name in &names {
+            context.add_acceptable_critical(name);
+            jwe_context.add_acceptable_critical(name);
+            assert!(context.is_acceptable_critical(name));
+            assert!(jwe_context.is_acceptable_critical(name));
+        }
+
+        context.clear_acceptable_criticals();
+        jwe_context.clear_acceptable_criticals();
+        for name in &names {
+            assert!(!context.is_acceptable_critical(name));
+            assert!(!jwe_context.is_acceptable_critical(name));
+        }
+
+        let private_key = util::random_bytes(64);
+        let mut src_header = JwsHeader::new();
+        src_header.set_critical(&vec!["crit1"]);
+        src_header.set_claim("crit1", Some(json!("value")))?;
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&JwtPayload::new(), &src_header, &signer)?;
+
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        assert!(context
+            .decode_with_verifier(&jwt_string, &verifier)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_allowed_algorithms() -> Result<()> {
+        let private_key = util::random_bytes(64);
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        let src_payload = JwtPayload::new();
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+        let unsecured_string = jwt::encode_unsecured(&src_payload, &src_header)?;
+
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+        let mut context = JwtContext::new();
+        context.decode_with_verifier(&jwt_string, &verifier)?;
+        context.decode_unsecured(&unsecured_string)?;
+
+        context.set_allowed_algorithms(&["RS256"]);
+        assert!(context
+            .decode_with_verifier(&jwt_string, &verifier)
+            .is_err());
+
+        context.set_allowed_algorithms(&["HS256"]);
+        context.decode_with_verifier(&jwt_string, &verifier)?;
+        assert!(context.decode_unsecured(&unsecured_string).is_err());
+
+        context.set_allowed_algorithms(&["HS256", "none"]);
+        context.decode_unsecured(&unsecured_string)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_key_policy_in_jwk_set() -> Result<()> {
+        let private_key = Jwk::from_bytes(&load_file("jwk/RSA_private.jwk")?)?;
+        let mut jwk = Jwk::from_bytes(&load_file("jwk/RSA_public.jwk")?)?;
+        jwk.set_key_id("rsa-key");
+        let mut jwk_set = Map::new();
+        jwk_set.insert("keys".to_string(), json!([jwk.as_ref()]));
+        let jwk_set = JwkSet::from_map(jwk_set)?;
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_key_id("rsa-key");
+        let src_payload = JwtPayload::new();
+        let signer = RS256.signer_from_jwk(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+
+        let verifier = RS256.verifier_from_jwk(jwk_set.keys()[0])?;
+
+        let mut context = JwtContext::new();
+        context.decode_with_verifier_in_jwk_set(&jwt_string, &jwk_set, |_| Ok(Some(&verifier)))?;
+
+        context.set_minimum_rsa_bits(3072);
+        let err = context
+            .decode_with_verifier_in_jwk_set(&jwt_string, &jwk_set, |_| Ok(Some(&verifier)))
+            .unwrap_err();
+        assert!(err.key_policy_error().is_some(), "{}", err);
+
+        // The policy is also applied to a verifier that is not built from a JWK set.
+        let err = context
+            .decode_with_verifier(&jwt_string, &verifier)
+            .unwrap_err();
+        assert!(err.key_policy_error().is_some(), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_require_kid_in_jwk_set() -> Result<()> {
+        let private_key = Jwk::generate_ec_key(P_256)?;
+        let mut jwk_1 = Jwk::generate_ec_key(P_256)?.to_public_key()?;
+        jwk_1.set_key_id("key-1");
+        let mut jwk_2 = private_key.to_public_key()?;
+        jwk_2.set_key_id("key-2");
+        let mut jwk_set = Map::new();
+        jwk_set.insert("keys".to_string(), json!([jwk_1.as_ref(), jwk_2.as_ref()]));
+        let jwk_set = JwkSet::from_map(jwk_set)?;
+
+        let signer = ES256.signer_from_jwk(&private_key)?;
+        let verifier = ES256.verifier_from_jwk(&private_key.to_public_key()?)?;
+
+        let no_kid = jwt::encode_with_signer(&JwtPayload::new(), &JwsHeader::new(), &signer)?;
+        let mut header = JwsHeader::new();
+        header.set_key_id("key-2");
+        let with_kid = jwt::encode_with_signer(&JwtPayload::new(), &header, &signer)?;
+
+        let mut context = JwtContext::new();
+        assert!(!context.is_require_kid());
+        let err = context
+            .decode_with_verifier_in_jwk_set(&no_kid, &jwk_set, |_| Ok(Some(&verifier)))
+            .unwrap_err();
+        assert!(!format!("{}", err).contains("kid"));
+
+        context.set_require_kid(true);
+        assert!(context.is_require_kid());
+        match context.decode_with_verifier_in_jwk_set(&no_kid, &jwk_set, |_| Ok(Some(&verifier))) {
+            Err(JoseError::InvalidJwtFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The JWT has no kid header claim, so a key cannot be selected from the JWK set."
+            ),
+            res => panic!("{:?}", res),
+        }
+        context.decode_with_verifier_in_jwk_set(&with_kid, &jwk_set, |_| Ok(Some(&verifier)))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_prefer_encryption_key_in_jwk_set() -> Result<()> {
+        let sig_key = Jwk::generate_ec_key(P_256)?;
+        let enc_key = Jwk::generate_ec_key(P_256)?;
+        let other_key = Jwk::generate_ec_key(P_256)?;
+
+        let mut sig_jwk = sig_key.to_public_key()?;
+        sig_jwk.set_key_id("shared");
+        sig_jwk.set_key_use("sig");
+        let mut other_jwk = other_key.to_public_key()?;
+        other_jwk.set_key_id("shared");
+        let mut enc_jwk = enc_key.to_public_key()?;
+        enc_jwk.set_key_id("shared");
+        enc_jwk.set_key_use("enc");
+        let mut ops_jwk = enc_key.to_public_key()?;
+        ops_jwk.set_key_id("shared");
+        ops_jwk.set_key_operations(vec!["decrypt"]);
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_key_id("shared");
+        let encrypter = ECDH_ES.encrypter_from_jwk(&enc_key.to_public_key()?)?;
+        let jwt_string = jwt::encode_with_encrypter(&JwtPayload::new(), &header, &encrypter)?;
+
+        // Each JWK is mapped to a decrypter of the private key that has the same x.
+        let mut decrypters = Vec::new();
+        for key in vec![&sig_key, &enc_key, &other_key] {
+            let decrypter = ECDH_ES.decrypter_from_jwk(key)?;
+            decrypters.push((key.parameter("x").cloned(), decrypter));
+        }
+        let select = |jwk: &Jwk| -> Option<&dyn JweDecrypter> {
+            assert_ne!(jwk.key_use(), Some("sig"));
+            decrypters
+                .iter()
+                .find(|(x, _)| x.as_ref() == jwk.parameter("x"))
+                .map(|(_, decrypter)| decrypter as &dyn JweDecrypter)
+        };
+
+        for jwks in vec![
+            vec![&sig_jwk, &other_jwk, &enc_jwk],
+            vec![&sig_jwk, &other_jwk, &ops_jwk],
+        ] {
+            let mut jwk_set = Map::new();
+            let keys: Vec<&Map<String, Value>> = jwks.iter().map(|jwk| jwk.as_ref()).collect();
+            jwk_set.insert("keys".to_string(), json!(keys));
+            let jwk_set = JwkSet::from_map(jwk_set)?;
+
+            jwt::decode_with_decrypter_in_jwk_set(&jwt_string, &jwk_set, |jwk| Ok(select(jwk)))?;
+        }
+
+        // Without a encryption key, the signing key is never selected.
+        let mut jwk_set = Map::new();
+        jwk_set.insert("keys".to_string(), json!([sig_jwk.as_ref()]));
+        let jwk_set = JwkSet::from_map(jwk_set)?;
+        assert!(
+            jwt::decode_with_decrypter_in_jwk_set(&jwt_string, &jwk_set, |jwk| Ok(select(jwk)))
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_reject_unmatched_verifier_algorithm() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        let mut src_header = JwsHeader::new();
+        src_header.set_token_type("JWT");
+        let src_payload = JwtPayload::new();
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let jwt_string = jwt::encode_with_signer(&src_payload, &src_header, &signer)?;
+
+        let verifier = RS256.verifier_from_pem(&public_key)?;
+        let err = jwt::decode_with_verifier(&jwt_string, &verifier)
+            .expect_err("Unmatched verifier algorithm did not fail");
+        let confusion = err.algorithm_confusion_error().unwrap();
+        assert_eq!(confusion.expected, "RS256");
+        assert_eq!(confusion.found, "HS256");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_unsupported_algorithm() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+
+        let header = base64::encode_config(r#"{"alg":"HS1024"}"#, base64::URL_SAFE_NO_PAD);
+        let payload = base64::encode_config("{}", base64::URL_SAFE_NO_PAD);
+        let jwt_string = format!("{}.{}.AAAA", header, payload);
+
+        match jwt::decode_with_verifier(&jwt_string, &verifier) {
+            Err(JoseError::UnsupportedAlgorithm(val)) => assert_eq!(val, "HS1024"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let jwt_string = format!(
+            "{}.{}.AAAA",
+            base64::encode_config(r#"{"alg":"HS512"}"#, base64::URL_SAFE_NO_PAD),
+            payload
+        );
+        match jwt::decode_with_verifier(&jwt_string, &verifier) {
+            Err(JoseError::InvalidJwtFormat(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_is_expired() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let header = JwsHeader::new();
+
+        let mut payload = JwtPayload::new();
+        payload.set_expires_at(&(SystemTime::UNIX_EPOCH + Duration::from_secs(60)));
+        let jwt_string = jwt::encode_with_signer(&payload, &header, &signer)?;
+        assert!(jwt::is_expired(
+            &jwt_string,
+            &(SystemTime::UNIX_EPOCH + Duration::from_secs(60))
+        )?);
+        assert!(!jwt::is_expired(
+            &jwt_string,
+            &(SystemTime::UNIX_EPOCH + Duration::from_secs(59))
+        )?);
+
+        let payload = JwtPayload::new();
+        let jwt_string = jwt::encode_with_signer(&payload, &header, &signer)?;
+        assert!(!jwt::is_expired(&jwt_string, &SystemTime::now())?);
+
+        let encrypter = Dir.encrypter_from_bytes(util::random_bytes(16))?;
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let jwt_string = jwt::encode_with_encrypter(&payload, &header, &encrypter)?;
+        assert!(jwt::is_expired(&jwt_string, &SystemTime::now()).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_jwt_with_rsa_pem() -> Result<()> {
         for alg in &[RS256, RS384, RS512] {
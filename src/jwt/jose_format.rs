@@ -0,0 +1,123 @@
+use anyhow::bail;
+
+use crate::{JoseError, Map, SegmentCountError, Value};
+
+/// Represents a serialization format of a JWS or a JWE.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum JoseFormat {
+    /// A JWS of compact serialization.
+    JwsCompact,
+    /// A JWE of compact serialization.
+    JweCompact,
+    /// A JWS of general or flattened json serialization.
+    JwsJson,
+    /// A JWE of general or flattened json serialization.
+    JweJson,
+    /// A JWS of compact serialization with the "none" algorithm.
+    Unsecured,
+}
+
+impl JoseFormat {
+    /// Return the format of the input detected by its structure.
+    ///
+    /// The header of a compact serialization is decoded to test the alg header claim,
+    /// but any signature or encryption is not verified.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWS or JWE string representation.
+    pub fn detect(input: &str) -> Result<Self, JoseError> {
+        (|| -> anyhow::Result<Self> {
+            let input = input.trim();
+            if input.starts_with('{') {
+                let map: Map<String, Value> = serde_json::from_str(input)?;
+                if map.contains_key("ciphertext") {
+                    return Ok(Self::JweJson);
+                } else if map.contains_key("payload")
+                    && (map.contains_key("signature") || map.contains_key("signatures"))
+                {
+                    return Ok(Self::JwsJson);
+                }
+                bail!("The json is neither a JWS nor a JWE.");
+            }
+
+            let parts: Vec<&str> = input.split('.').collect();
+            let header = match parts.len() {
+                3 | 5 => base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?,
+                len => {
+                    return Err(SegmentCountError {
+                        expected: 3,
+                        found: len,
+                    }
+                    .into())
+                }
+            };
+            let header: Map<String, Value> = serde_json::from_slice(&header)?;
+
+            if parts.len() == 5 {
+                Ok(Self::JweCompact)
+            } else if matches!(header.get("alg"), Some(Value::String(val)) if val == "none")
+                && parts[2].is_empty()
+            {
+                Ok(Self::Unsecured)
+            } else {
+                Ok(Self::JwsCompact)
+            }
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwtFormat(err),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::jwe::{self, Dir, JweHeader, JweHeaderSet};
+    use crate::jws::{self, JwsHeader, JwsHeaderSet, HS256};
+    use crate::jwt::{self, JoseFormat, JwtPayload};
+    use crate::util;
+
+    #[test]
+    fn test_detect_format() -> Result<()> {
+        let key = util::random_bytes(32);
+        let signer = HS256.signer_from_bytes(&key)?;
+        let encrypter = Dir.encrypter_from_bytes(&key)?;
+
+        let payload = JwtPayload::new();
+        let jws = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+        assert_eq!(jwt::detect_format(&jws)?, JoseFormat::JwsCompact);
+
+        let unsecured = jwt::encode_unsecured(&payload, &JwsHeader::new())?;
+        assert_eq!(jwt::detect_format(&unsecured)?, JoseFormat::Unsecured);
+
+        let mut jwe_header = JweHeader::new();
+        jwe_header.set_content_encryption("A256GCM");
+        let jwe = jwt::encode_with_encrypter(&payload, &jwe_header, &encrypter)?;
+        assert_eq!(jwt::detect_format(&jwe)?, JoseFormat::JweCompact);
+
+        let mut jws_header = JwsHeaderSet::new();
+        jws_header.set_algorithm(signer.algorithm().name(), true);
+        let jws_json = jws::serialize_flattened_json(b"{}", &jws_header, &signer)?;
+        assert_eq!(jwt::detect_format(&jws_json)?, JoseFormat::JwsJson);
+        let jws_json = jws::serialize_general_json(b"{}", &[(&jws_header, &signer)])?;
+        assert_eq!(jwt::detect_format(&jws_json)?, JoseFormat::JwsJson);
+
+        let mut jwe_header = JweHeaderSet::new();
+        jwe_header.set_content_encryption("A256GCM", true);
+        let jwe_json =
+            jwe::serialize_flattened_json(b"{}", Some(&jwe_header), None, None, &encrypter)?;
+        assert_eq!(jwt::detect_format(&jwe_json)?, JoseFormat::JweJson);
+
+        assert!(jwt::detect_format("").is_err());
+        assert!(jwt::detect_format("a.b").is_err());
+        assert!(jwt::detect_format("not.a.token").is_err());
+        assert!(jwt::detect_format("{\"payload\":\"\"}").is_err());
+        assert!(jwt::detect_format("{").is_err());
+        assert!(jwt::detect_format("[]").is_err());
+
+        Ok(())
+    }
+}
@@ -1,15 +1,30 @@
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+
 use anyhow::bail;
+use once_cell::unsync::OnceCell;
 
 use crate::jwe::{JweContext, JweDecrypter, JweEncrypter, JweHeader};
 use crate::jwk::{Jwk, JwkSet};
-use crate::jws::{JwsContext, JwsHeader, JwsSigner, JwsVerifier};
+use crate::jws::{self, JwsContext, JwsHeader, JwsSigner, JwsVerifier};
 use crate::jwt::{self, JwtPayload};
-use crate::{JoseError, JoseHeader, Map, Value};
+use crate::util;
+use crate::{JoseError, JoseHeader, Map, TokenLengthError, Value};
+
+/// The default maximum length of a token in bytes (1 MiB).
+const DEFAULT_MAX_TOKEN_LEN: usize = 1024 * 1024;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct JwtContext {
     jws_context: JwsContext,
     jwe_context: JweContext,
+    allowed_algorithms: Option<BTreeSet<String>>,
+    audience_always_array: bool,
+    required_type: Option<String>,
+    max_token_len: Option<usize>,
+    max_segment_len: Option<usize>,
+    canonical_payload: bool,
+    require_kid: bool,
 }
 
 impl JwtContext {
@@ -17,6 +32,13 @@ impl JwtContext {
         Self {
             jws_context: JwsContext::new(),
             jwe_context: JweContext::new(),
+            allowed_algorithms: None,
+            audience_always_array: false,
+            required_type: None,
+            max_token_len: Some(DEFAULT_MAX_TOKEN_LEN),
+            max_segment_len: None,
+            canonical_payload: false,
+            require_kid: false,
         }
     }
 
@@ -49,6 +71,248 @@ impl JwtContext {
         self.jwe_context.remove_acceptable_critical(name);
     }
 
+    /// Remove all acceptable critical header claim names
+    pub fn clear_acceptable_criticals(&mut self) {
+        self.jws_context.clear_acceptable_criticals();
+        self.jwe_context.clear_acceptable_criticals();
+    }
+
+    /// Set a handler that validates a critical header claim of JWS.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a acceptable critical header claim name
+    /// * `handler` - a function for validating the critical header claim.
+    pub fn set_critical_handler<F>(&mut self, name: &str, handler: F)
+    where
+        F: Fn(&JwsHeader) -> Result<(), JoseError> + Send + Sync + 'static,
+    {
+        self.jws_context.set_critical_handler(name, handler);
+    }
+
+    /// Remove a handler of a critical header claim of JWS.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a acceptable critical header claim name
+    pub fn remove_critical_handler(&mut self, name: &str) {
+        self.jws_context.remove_critical_handler(name);
+    }
+
+    /// Return the minimum RSA key length in bits for JWS keys.
+    pub fn minimum_rsa_bits(&self) -> u32 {
+        self.jws_context.minimum_rsa_bits()
+    }
+
+    /// Set the minimum RSA key length in bits for JWS keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - a minimum RSA key length
+    pub fn set_minimum_rsa_bits(&mut self, bits: u32) {
+        self.jws_context.set_minimum_rsa_bits(bits);
+    }
+
+    /// Test a curve name is allowed for JWS keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a curve name
+    pub fn is_allowed_curve(&self, name: &str) -> bool {
+        self.jws_context.is_allowed_curve(name)
+    }
+
+    /// Set curve names that are allowed for JWS keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `curves` - allowed curve names
+    pub fn set_allowed_curves(&mut self, curves: &[&str]) {
+        self.jws_context.set_allowed_curves(curves);
+    }
+
+    /// Remove the list of allowed curve names for JWS keys.
+    pub fn remove_allowed_curves(&mut self) {
+        self.jws_context.remove_allowed_curves();
+    }
+
+    /// Test the leaf certificate of a x5c header claim is checked against the verifying key.
+    pub fn is_verify_x5c_matches_key(&self) -> bool {
+        self.jws_context.is_verify_x5c_matches_key()
+    }
+
+    /// Set whether the leaf certificate of a x5c header claim is checked against the verifying key.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the x5c header claim is checked
+    pub fn set_verify_x5c_matches_key(&mut self, value: bool) {
+        self.jws_context.set_verify_x5c_matches_key(value);
+    }
+
+    /// Test the ES256K algorithm (ECDSA using secp256k1 curve) is enabled.
+    pub fn is_secp256k1_enabled(&self) -> bool {
+        self.jws_context.is_secp256k1_enabled()
+    }
+
+    /// Set whether the ES256K algorithm (ECDSA using secp256k1 curve) is enabled.
+    ///
+    /// When it is false, ES256K is rejected even if it is in the list of
+    /// `set_allowed_algorithms`. The default is true.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the ES256K algorithm is enabled
+    pub fn set_secp256k1_enabled(&mut self, value: bool) {
+        self.jws_context.set_secp256k1_enabled(value);
+    }
+
+    /// Test a JWS alg header claim value is allowed for decoding.
+    ///
+    /// All algorithms are allowed until a list is set by `set_allowed_algorithms`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a JWS alg header claim value
+    pub fn is_allowed_algorithm(&self, name: &str) -> bool {
+        match &self.allowed_algorithms {
+            Some(vals) => vals.contains(name),
+            None => true,
+        }
+    }
+
+    /// Set JWS alg header claim values that are allowed for decoding.
+    ///
+    /// A token whose alg header claim is not in the list is rejected before
+    /// the verifier selector is called, even if a verifier would be returned.
+    /// The "none" algorithm is also rejected unless it is in the list.
+    /// This check is independent of the algorithm of the verifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `algs` - allowed JWS alg header claim values
+    pub fn set_allowed_algorithms(&mut self, algs: &[&str]) {
+        self.allowed_algorithms = Some(algs.iter().map(|val| val.to_string()).collect());
+    }
+
+    /// Remove the list of allowed JWS alg header claim values.
+    pub fn remove_allowed_algorithms(&mut self) {
+        self.allowed_algorithms = None;
+    }
+
+    /// Return the JWS typ header claim value that is required for decoding.
+    pub fn required_type(&self) -> Option<&str> {
+        self.required_type.as_deref()
+    }
+
+    /// Set a JWS typ header claim value that is required for decoding.
+    ///
+    /// The typ header claim is compared case-insensitively, and "application/" is
+    /// prepended to a value that contains no '/' as described in RFC 7515
+    /// (e.g. "JWT" matches "application/jwt", but not "foo/jwt").
+    /// No typ header claim is required if the value is None. The default is None.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a required typ header claim value
+    pub fn set_required_type(&mut self, value: Option<&str>) {
+        self.required_type = value.map(|val| val.to_string());
+    }
+
+    /// Test a single audience payload claim (aud) is encoded as a array.
+    pub fn is_audience_always_array(&self) -> bool {
+        self.audience_always_array
+    }
+
+    /// Set whether a single audience payload claim (aud) is encoded as a array.
+    ///
+    /// If this option is true, a aud string value is encoded as a one-element array
+    /// for consumers that require arrays. The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if a aud claim is always encoded as a array
+    pub fn set_audience_always_array(&mut self, value: bool) {
+        self.audience_always_array = value;
+    }
+
+    /// Test the payload is encoded as a canonical JSON.
+    pub fn is_canonical_payload(&self) -> bool {
+        self.canonical_payload
+    }
+
+    /// Set whether the payload is encoded as a canonical JSON.
+    ///
+    /// If this option is true, the members of the payload are sorted by key recursively
+    /// and encoded without whitespace, so the same payload always produces the same bytes.
+    /// The header of a JWS is sorted as well, including the alg and kid header claims
+    /// of the signer. For a JWE, only the payload is canonical because the encrypter
+    /// adds header claims and random values while encrypting. The default is false.
+    /// This option does not affect decoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the payload is encoded as a canonical JSON
+    pub fn set_canonical_payload(&mut self, value: bool) {
+        self.canonical_payload = value;
+    }
+
+    /// Test a kid header claim is required for decoding with a JWK set.
+    pub fn is_require_kid(&self) -> bool {
+        self.require_kid
+    }
+
+    /// Set whether a kid header claim is required for decoding with a JWK set.
+    ///
+    /// If this option is true, decode_with_verifier_in_jwk_set and
+    /// decode_with_decrypter_in_jwk_set reject a token that has no kid header claim
+    /// with a error that explains a key cannot be selected, instead of a error that
+    /// no verifier or decrypter is selected. The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if a kid header claim is required
+    pub fn set_require_kid(&mut self, value: bool) {
+        self.require_kid = value;
+    }
+
+    /// Return the maximum length of a token in bytes for decoding.
+    pub fn max_token_len(&self) -> Option<usize> {
+        self.max_token_len
+    }
+
+    /// Set the maximum length of a token in bytes for decoding.
+    ///
+    /// A token that is longer than the limit is rejected by all decoding functions
+    /// before any parsing. The default is 1 MiB, which is large enough for
+    /// ordinary tokens but bounds the memory for a untrusted input.
+    /// The length is not checked if the value is None.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum length of a token
+    pub fn set_max_token_len(&mut self, value: Option<usize>) {
+        self.max_token_len = value;
+    }
+
+    /// Return the maximum length of a dot separated segment of a token in bytes for decoding.
+    pub fn max_segment_len(&self) -> Option<usize> {
+        self.max_segment_len
+    }
+
+    /// Set the maximum length of a dot separated segment of a token in bytes for decoding.
+    ///
+    /// A token that has a longer segment than the limit is rejected by all decoding
+    /// functions before any base64 or JSON decoding. It is useful to limit the header
+    /// more strictly than the whole token. The default is None (unlimited).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum length of a segment
+    pub fn set_max_segment_len(&mut self, value: Option<usize>) {
+        self.max_segment_len = value;
+    }
+
     /// Return the string repsentation of the JWT with a "none" algorithm.
     ///
     /// # Arguments
@@ -65,6 +329,9 @@ impl JwtContext {
 
     /// Return the string repsentation of the JWT with the siginig algorithm.
     ///
+    /// A empty payload is encoded as a empty JSON object (`e30`), not as a empty
+    /// segment, because a JWT Claims Set must be a JSON object.
+    ///
     /// # Arguments
     ///
     /// * `payload` - The payload data.
@@ -83,10 +350,23 @@ impl JwtContext {
                 }
             }
 
-            let payload_bytes = serde_json::to_vec(payload.claims_set()).unwrap();
-            let jwt = self
-                .jws_context
-                .serialize_compact(&payload_bytes, header, signer)?;
+            let payload_bytes = self.payload_bytes(payload);
+            let jwt = if self.canonical_payload {
+                let mut claims = header.claims_set().clone();
+                claims.insert(
+                    "alg".to_string(),
+                    Value::String(signer.algorithm().name().to_string()),
+                );
+                if let Some(key_id) = signer.key_id() {
+                    claims.insert("kid".to_string(), Value::String(key_id.to_string()));
+                }
+                let header = JwsHeader::from_map(util::to_canonical_map(&claims))?;
+                self.jws_context
+                    .serialize_compact(&payload_bytes, &header, signer)?
+            } else {
+                self.jws_context
+                    .serialize_compact(&payload_bytes, header, signer)?
+            };
             Ok(jwt)
         })()
         .map_err(|err| match err.downcast::<JoseError>() {
@@ -108,7 +388,7 @@ impl JwtContext {
         header: &JweHeader,
         encrypter: &dyn JweEncrypter,
     ) -> Result<String, JoseError> {
-        let payload_bytes = serde_json::to_vec(payload.claims_set()).unwrap();
+        let payload_bytes = self.payload_bytes(payload);
         let jwt = self
             .jwe_context
             .serialize_compact(&payload_bytes, header, encrypter)?;
@@ -121,23 +401,59 @@ impl JwtContext {
     ///
     /// * `input` - a JWT string representation.
     pub fn decode_header(&self, input: impl AsRef<[u8]>) -> Result<Box<dyn JoseHeader>, JoseError> {
+        self.check_token_len(input.as_ref())?;
         (|| -> anyhow::Result<Box<dyn JoseHeader>> {
-            let input = input.as_ref();
-            let parts: Vec<&[u8]> = input.split(|b| *b == '.' as u8).collect();
+            let (header, parts) = util::split_and_decode_segments(input.as_ref(), &[3, 5])?;
             if parts.len() == 3 {
                 // JWS
-                let header = base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?;
-                let header: Map<String, Value> = serde_json::from_slice(&header)?;
                 let header = JwsHeader::from_map(header)?;
                 Ok(Box::new(header))
-            } else if parts.len() == 5 {
+            } else {
                 // JWE
-                let header = base64::decode_config(parts[0], base64::URL_SAFE_NO_PAD)?;
-                let header: Map<String, Value> = serde_json::from_slice(&header)?;
                 let header = JweHeader::from_map(header)?;
                 Ok(Box::new(header))
+            }
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwtFormat(err),
+        })
+    }
+
+    /// Test the JWT has expired at a base time.
+    ///
+    /// This function decodes the payload of a JWS without verifying the signature,
+    /// so the result must not be used for any authorization decision.
+    /// A JWT that has no exp payload claim is never expired.
+    /// A JWE is rejected because the payload cannot be read without decryption.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWT string representation.
+    /// * `base_time` - a time to be compared with the exp payload claim.
+    pub fn is_expired(
+        &self,
+        input: impl AsRef<[u8]>,
+        base_time: &SystemTime,
+    ) -> Result<bool, JoseError> {
+        self.check_token_len(input.as_ref())?;
+        (|| -> anyhow::Result<bool> {
+            let (_, parts) = util::split_and_decode_segments(input.as_ref(), &[3, 5])?;
+            if parts.len() == 5 {
+                bail!("The payload of a JWE cannot be read without decryption.");
+            }
+
+            let payload = util::decode_segment(&parts, 1)?;
+            let payload = if payload.is_empty() {
+                JwtPayload::new()
             } else {
-                bail!("The input cannot be recognized as a JWT.");
+                let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
+                JwtPayload::from_map(payload)?
+            };
+
+            match payload.expires_at() {
+                Some(expires_at) => Ok(&expires_at <= base_time),
+                None => Ok(false),
             }
         })()
         .map_err(|err| match err.downcast::<JoseError>() {
@@ -172,6 +488,61 @@ impl JwtContext {
         self.decode_with_verifier_selector(input, |_header| Ok(Some(verifier)))
     }
 
+    /// Return the JWT object decoded by the selected verifier and the protected header bytes.
+    ///
+    /// The protected header bytes are the base64url-decoded first part of the compact
+    /// serialization. They are exactly what was signed, including whitespace and
+    /// the order of members that are lost in the parsed header.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWT string representation.
+    /// * `verifier` - a verifier of the signing algorithm.
+    pub fn decode_with_verifier_raw(
+        &self,
+        input: impl AsRef<[u8]>,
+        verifier: &dyn JwsVerifier,
+    ) -> Result<(JwtPayload, JwsHeader, Vec<u8>), JoseError> {
+        let input = input.as_ref();
+        let (payload, header) = self.decode_with_verifier(input, verifier)?;
+
+        (|| -> anyhow::Result<(JwtPayload, JwsHeader, Vec<u8>)> {
+            let (_, parts) = util::split_and_decode_segments(input, &[3])?;
+            let protected = util::decode_segment(&parts, 0)?;
+            Ok((payload, header, protected))
+        })()
+        .map_err(JoseError::InvalidJwtFormat)
+    }
+
+    /// Return the JWT object decoded by the first verifier that succeeds and its label.
+    ///
+    /// The verifiers are tried in order. If no verifier succeeds, the error
+    /// of the last one is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWT string representation.
+    /// * `keys` - pairs of a label (e.g. a key ID) and a verifier.
+    pub fn decode_with_keys(
+        &self,
+        input: impl AsRef<[u8]>,
+        keys: &[(&str, &dyn JwsVerifier)],
+    ) -> Result<(JwtPayload, JwsHeader, String), JoseError> {
+        let input = input.as_ref();
+        let mut last_err = None;
+        for (label, verifier) in keys {
+            match self.decode_with_verifier(input, *verifier) {
+                Ok((payload, header)) => return Ok((payload, header, label.to_string())),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(match last_err {
+            Some(err) => err,
+            None => JoseError::InvalidJwtFormat(anyhow::anyhow!("A verifier is not given.")),
+        })
+    }
+
     /// Return the JWT object decoded with a selected verifying algorithm.
     ///
     /// # Arguments
@@ -186,18 +557,61 @@ impl JwtContext {
     where
         F: Fn(&JwsHeader) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
     {
+        self.check_token_len(input.as_ref())?;
+
         (|| -> anyhow::Result<(JwtPayload, JwsHeader)> {
             let (payload, header) =
                 self.jws_context
                     .deserialize_compact_with_selector(input, |header| {
                         (|| -> anyhow::Result<Option<&'a dyn JwsVerifier>> {
+                            if self.is_acceptable_critical("b64") {
+                                bail!("JWT is not supported b64 header claim.");
+                            }
+
+                            let expected_alg = match header.algorithm() {
+                                Some(val) if self.is_allowed_algorithm(val) => val,
+                                Some(val) => {
+                                    bail!("The JWT alg header claim is not allowed: {}", val)
+                                }
+                                None => bail!("The JWT alg header claim is required."),
+                            };
+
+                            if let Some(expected_type) = &self.required_type {
+                                match header.token_type() {
+                                    Some(val) if Self::is_same_type(val, expected_type) => {}
+                                    Some(val) => bail!(
+                                        "The JWT typ header claim is not {}: {}",
+                                        expected_type,
+                                        val
+                                    ),
+                                    None => {
+                                        bail!(
+                                            "The JWT typ header claim is required: {}",
+                                            expected_type
+                                        )
+                                    }
+                                }
+                            }
+
                             let verifier = match selector(&header)? {
                                 Some(val) => val,
                                 None => return Ok(None),
                             };
 
-                            if self.is_acceptable_critical("b64") {
-                                bail!("JWT is not supported b64 header claim.");
+                            let actual_alg = verifier.algorithm().name();
+                            if actual_alg != expected_alg {
+                                if !JwsContext::is_supported_algorithm(expected_alg) {
+                                    return Err(JoseError::UnsupportedAlgorithm(
+                                        expected_alg.to_string(),
+                                    )
+                                    .into());
+                                }
+                                JwsContext::check_algorithm_confusion(actual_alg, expected_alg)?;
+                                bail!(
+                                "The JWT alg header claim is not matched to the verifier: {} != {}",
+                                expected_alg,
+                                actual_alg
+                            );
                             }
 
                             Ok(Some(verifier))
@@ -210,8 +624,13 @@ impl JwtContext {
                         })
                     })?;
 
-            let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
-            let payload = JwtPayload::from_map(payload)?;
+            // An empty payload is treated as an empty claims set.
+            let payload = if payload.is_empty() {
+                JwtPayload::new()
+            } else {
+                let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
+                JwtPayload::from_map(payload)?
+            };
 
             Ok((payload, header))
         })()
@@ -223,27 +642,33 @@ impl JwtContext {
 
     /// Return the JWT object decoded by using a JWK set.
     ///
+    /// A error is returned if a JWK that has the kid does not satisfy the key policy
+    /// (minimum RSA key length and allowed curves) of this context.
+    ///
     /// # Arguments
     ///
     /// * `input` - a JWT string representation.
     /// * `jwk_set` - a JWK set.
     /// * `selector` - a function for selecting the verifying algorithm.
-    pub fn decode_with_verifier_in_jwk_set<F>(
+    pub fn decode_with_verifier_in_jwk_set<'a, F>(
         &self,
         input: impl AsRef<[u8]>,
         jwk_set: &JwkSet,
         selector: F,
     ) -> Result<(JwtPayload, JwsHeader), JoseError>
     where
-        F: Fn(&Jwk) -> Result<Option<&dyn JwsVerifier>, JoseError>,
+        F: Fn(&Jwk) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
     {
         self.decode_with_verifier_selector(input, |header| {
             let key_id = match header.key_id() {
                 Some(val) => val,
+                None if self.require_kid => return Err(Self::missing_kid_error()),
                 None => return Ok(None),
             };
 
             for jwk in jwk_set.get(key_id) {
+                // A key that violates the key policy is never used.
+                self.jws_context.check_jwk(jwk)?;
                 if let Some(val) = selector(jwk)? {
                     return Ok(Some(val));
                 }
@@ -252,6 +677,57 @@ impl JwtContext {
         })
     }
 
+    /// Return the JWT object decoded with a selected verifying algorithm,
+    /// or with the JWK embedded in the jwk header claim if no verifier is selected.
+    ///
+    /// # Security
+    ///
+    /// The jwk header claim is supplied by the sender of the token, so anyone can sign
+    /// a token with their own key and embed it. A successful verification only proves
+    /// that the token was signed by the embedded key, not that the key is trusted.
+    /// The `accept` predicate must decide whether the embedded key is trusted (e.g. by
+    /// comparing it with a pinned key or thumbprint). Accepting any key that merely
+    /// has a given `use` or `alg` lets an attacker forge tokens.
+    ///
+    /// A embedded JWK of oct key type is always rejected because the verifying key
+    /// would be the signing key itself. The allowed algorithms and the key policy
+    /// of this context are also applied to the embedded JWK.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWT string representation.
+    /// * `selector` - a function for selecting the verifying algorithm.
+    /// * `accept` - a function for testing a embedded JWK is trusted.
+    pub fn decode_with_verifier_selector_or_embedded_jwk<'a, F, P>(
+        &self,
+        input: impl AsRef<[u8]>,
+        selector: F,
+        accept: P,
+    ) -> Result<(JwtPayload, JwsHeader), JoseError>
+    where
+        F: Fn(&JwsHeader) -> Result<Option<&'a dyn JwsVerifier>, JoseError>,
+        P: Fn(&Jwk) -> bool,
+    {
+        let embedded: OnceCell<Box<dyn JwsVerifier>> = OnceCell::new();
+        self.decode_with_verifier_selector(input, |header| {
+            if let Some(val) = selector(header)? {
+                return Ok(Some(val));
+            }
+
+            let jwk = match header.jwk() {
+                Some(val) => val,
+                None => return Ok(None),
+            };
+            if jwk.key_type() == "oct" || !accept(&jwk) {
+                return Ok(None);
+            }
+            self.jws_context.check_jwk(&jwk)?;
+
+            let verifier = embedded.get_or_try_init(|| jws::verifier_from_jwk(&jwk))?;
+            Ok(Some(verifier.as_ref()))
+        })
+    }
+
     /// Return the JWT object decoded by the selected decrypter.
     ///
     /// # Arguments
@@ -280,6 +756,8 @@ impl JwtContext {
     where
         F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
     {
+        self.check_token_len(input.as_ref())?;
+
         (|| -> anyhow::Result<(JwtPayload, JweHeader)> {
             let (payload, header) =
                 self.jwe_context
@@ -305,27 +783,40 @@ impl JwtContext {
 
     /// Return the JWT object decoded by using a JWK set.
     ///
+    /// The JWKs that have the kid of the header are passed to the selector,
+    /// except for the ones that have a sig key use. A JWK that has a enc key use
+    /// or a decrypt key operation is passed before the others, so a encryption key is
+    /// selected even if a signing key has the same kid.
+    ///
     /// # Arguments
     ///
     /// * `input` - a JWT string representation.
     /// * `jwk_set` - a JWK set.
     /// * `selector` - a function for selecting the decrypting algorithm.
-    pub fn decode_with_decrypter_in_jwk_set<F>(
+    pub fn decode_with_decrypter_in_jwk_set<'a, F>(
         &self,
         input: impl AsRef<[u8]>,
         jwk_set: &JwkSet,
         selector: F,
     ) -> Result<(JwtPayload, JweHeader), JoseError>
     where
-        F: Fn(&Jwk) -> Result<Option<&dyn JweDecrypter>, JoseError>,
+        F: Fn(&Jwk) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
     {
         self.decode_with_decrypter_selector(input, |header| {
             let key_id = match header.key_id() {
                 Some(val) => val,
+                None if self.require_kid => return Err(Self::missing_kid_error()),
                 None => return Ok(None),
             };
 
-            for jwk in jwk_set.get(key_id) {
+            let mut jwks: Vec<&Jwk> = jwk_set
+                .get(key_id)
+                .into_iter()
+                .filter(|jwk| jwk.key_use() != Some("sig"))
+                .collect();
+            jwks.sort_by_key(|jwk| !Self::is_for_decryption(jwk));
+
+            for jwk in jwks {
                 if let Some(val) = selector(jwk)? {
                     return Ok(Some(val));
                 }
@@ -333,4 +824,66 @@ impl JwtContext {
             Ok(None)
         })
     }
+
+    fn is_for_decryption(jwk: &Jwk) -> bool {
+        jwk.key_use() == Some("enc")
+            || matches!(jwk.key_operations(), Some(vals) if vals.contains(&"decrypt"))
+    }
+
+    fn missing_kid_error() -> JoseError {
+        JoseError::InvalidJwtFormat(anyhow::anyhow!(
+            "The JWT has no kid header claim, so a key cannot be selected from the JWK set."
+        ))
+    }
+
+    fn check_token_len(&self, input: &[u8]) -> Result<(), JoseError> {
+        if let Some(max) = self.max_token_len {
+            if input.len() > max {
+                return Err(JoseError::InvalidJwtFormat(
+                    TokenLengthError {
+                        max,
+                        found: input.len(),
+                    }
+                    .into(),
+                ));
+            }
+        }
+        if let Some(max) = self.max_segment_len {
+            util::check_segment_len(input, max).map_err(JoseError::InvalidJwtFormat)?;
+        }
+        Ok(())
+    }
+
+    fn payload_bytes(&self, payload: &JwtPayload) -> Vec<u8> {
+        let mut claims_set = None;
+        if self.audience_always_array {
+            if let Some(Value::String(val)) = payload.claim("aud") {
+                let mut val2 = payload.claims_set().clone();
+                val2.insert(
+                    "aud".to_string(),
+                    Value::Array(vec![Value::String(val.clone())]),
+                );
+                claims_set = Some(val2);
+            }
+        }
+        let claims_set = claims_set.as_ref().unwrap_or_else(|| payload.claims_set());
+
+        if self.canonical_payload {
+            util::to_canonical_json(&Value::Object(claims_set.clone())).into_bytes()
+        } else {
+            serde_json::to_vec(claims_set).unwrap()
+        }
+    }
+
+    fn is_same_type(actual: &str, expected: &str) -> bool {
+        // RFC 7515 4.1.9: "application/" is prepended to a value that contains no '/'.
+        fn normalize(value: &str) -> String {
+            if value.contains('/') {
+                value.to_ascii_lowercase()
+            } else {
+                format!("application/{}", value.to_ascii_lowercase())
+            }
+        }
+        normalize(actual) == normalize(expected)
+    }
 }
@@ -4,6 +4,11 @@ use std::time::{Duration, SystemTime};
 
 use crate::{JoseError, Map, Number, Value};
 use anyhow::bail;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The maximum value of NumericDate (9999-12-31T23:59:59Z) in seconds.
+pub(crate) const MAX_NUMERIC_DATE: u64 = 253402300799;
 
 #[derive(Debug, Eq, PartialEq, Clone, Default)]
 pub struct JwtPayload {
@@ -30,6 +35,32 @@ impl JwtPayload {
         Ok(Self { claims: map })
     }
 
+    /// Return the JWT payload from a serializable value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a value that is serialized to a JSON object.
+    pub fn from_serialize<T: Serialize>(value: &T) -> Result<Self, JoseError> {
+        let claims = (|| -> anyhow::Result<Map<String, Value>> {
+            match serde_json::to_value(value)? {
+                Value::Object(val) => Ok(val),
+                _ => bail!("The JWT payload must be a JSON object."),
+            }
+        })()
+        .map_err(JoseError::InvalidJson)?;
+
+        Self::from_map(claims)
+    }
+
+    /// Return a value deserialized from the JWT payload claims.
+    pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T, JoseError> {
+        (|| -> anyhow::Result<T> {
+            let value = serde_json::from_value(Value::Object(self.claims.clone()))?;
+            Ok(value)
+        })()
+        .map_err(JoseError::InvalidJson)
+    }
+
     /// Set a value for issuer payload claim (iss).
     ///
     /// # Arguments
@@ -68,6 +99,10 @@ impl JwtPayload {
 
     /// Set values for audience payload claim (aud).
     ///
+    /// A single audience is stored as a string and multiple audiences are stored
+    /// as a array. Use `JwtContext::set_audience_always_array` to encode a single
+    /// audience as a array.
+    ///
     /// # Arguments
     ///
     /// * `values` - a list of audiences
@@ -91,6 +126,19 @@ impl JwtPayload {
         }
     }
 
+    /// Set a single value for audience payload claim (aud).
+    ///
+    /// The value is always stored as a string rather than a one-element array,
+    /// for relying parties that require a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a audience
+    pub fn set_audience_single(&mut self, value: impl Into<String>) {
+        let value: String = value.into();
+        self.claims.insert("aud".to_string(), Value::String(value));
+    }
+
     /// Return values for audience payload claim (aud).
     pub fn audience(&self) -> Option<Vec<&str>> {
         match self.claims.get("aud") {
@@ -111,6 +159,18 @@ impl JwtPayload {
         }
     }
 
+    /// Return values for audience payload claim (aud) as a list.
+    ///
+    /// A single string value is returned as a one-element list, and an absent
+    /// or invalid claim is returned as an empty list. Use `claim("aud")` to get
+    /// the raw value.
+    pub fn audiences(&self) -> Vec<String> {
+        match self.audience() {
+            Some(vals) => vals.into_iter().map(|val| val.to_string()).collect(),
+            None => Vec::new(),
+        }
+    }
+
     /// Set a system time for expires at payload claim (exp).
     ///
     /// # Arguments
@@ -130,10 +190,17 @@ impl JwtPayload {
     /// Return the system time for expires at payload claim (exp).
     pub fn expires_at(&self) -> Option<SystemTime> {
         match self.claims.get("exp") {
-            Some(Value::Number(val)) => match val.as_u64() {
-                Some(val) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(val)),
-                None => None,
-            },
+            Some(Value::Number(val)) => Self::to_system_time(val),
+            _ => None,
+        }
+    }
+
+    /// Return the seconds since the Unix epoch for expires at payload claim (exp).
+    ///
+    /// A fractional value is truncated to seconds.
+    pub fn expires_at_timestamp(&self) -> Option<i64> {
+        match self.claims.get("exp") {
+            Some(Value::Number(val)) => Self::to_timestamp(val),
             _ => None,
         }
     }
@@ -157,10 +224,17 @@ impl JwtPayload {
     /// Return the system time for not before payload claim (nbf).
     pub fn not_before(&self) -> Option<SystemTime> {
         match self.claims.get("nbf") {
-            Some(Value::Number(val)) => match val.as_u64() {
-                Some(val) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(val)),
-                None => None,
-            },
+            Some(Value::Number(val)) => Self::to_system_time(val),
+            _ => None,
+        }
+    }
+
+    /// Return the seconds since the Unix epoch for not before payload claim (nbf).
+    ///
+    /// A fractional value is truncated to seconds.
+    pub fn not_before_timestamp(&self) -> Option<i64> {
+        match self.claims.get("nbf") {
+            Some(Value::Number(val)) => Self::to_timestamp(val),
             _ => None,
         }
     }
@@ -184,10 +258,17 @@ impl JwtPayload {
     /// Return the time for a issued at payload claim (iat).
     pub fn issued_at(&self) -> Option<SystemTime> {
         match self.claims.get("iat") {
-            Some(Value::Number(val)) => match val.as_u64() {
-                Some(val) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(val)),
-                None => None,
-            },
+            Some(Value::Number(val)) => Self::to_system_time(val),
+            _ => None,
+        }
+    }
+
+    /// Return the seconds since the Unix epoch for issued at payload claim (iat).
+    ///
+    /// A fractional value is truncated to seconds.
+    pub fn issued_at_timestamp(&self) -> Option<i64> {
+        match self.claims.get("iat") {
+            Some(Value::Number(val)) => Self::to_timestamp(val),
             _ => None,
         }
     }
@@ -210,6 +291,112 @@ impl JwtPayload {
         }
     }
 
+    /// Set values for scope payload claim (scope).
+    ///
+    /// The values are joined with a space.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - a list of scopes
+    pub fn set_scopes(&mut self, values: &[&str]) {
+        let value = values.join(" ");
+        self.claims
+            .insert("scope".to_string(), Value::String(value));
+    }
+
+    /// Return values for scope payload claim (scope).
+    ///
+    /// The value is split by spaces. An empty scope returns an empty list,
+    /// and a scope that is not a string (e.g. a array) returns None.
+    pub fn scopes(&self) -> Option<Vec<String>> {
+        match self.claims.get("scope") {
+            Some(Value::String(val)) => {
+                Some(val.split_whitespace().map(|val| val.to_string()).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Set a value for actor payload claim (act).
+    ///
+    /// The claims of the actor are embedded as a object. If the actor has a act claim,
+    /// it is kept as is, so a chain of delegation can be represented.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a actor
+    pub fn set_actor(&mut self, value: &JwtPayload) {
+        self.claims
+            .insert("act".to_string(), Value::Object(value.claims.clone()));
+    }
+
+    /// Return the value for actor payload claim (act).
+    ///
+    /// The prior actor of a delegation chain can be read by calling `actor` of the result.
+    /// A error is returned if the claims of the actor are invalid.
+    pub fn actor(&self) -> Result<Option<JwtPayload>, JoseError> {
+        (|| -> anyhow::Result<Option<JwtPayload>> {
+            match self.claims.get("act") {
+                Some(Value::Object(val)) => Ok(Some(JwtPayload::from_map(val.clone())?)),
+                Some(_) => bail!("The JWT act payload claim must be a object."),
+                None => Ok(None),
+            }
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwtFormat(err),
+        })
+    }
+
+    /// Return values for payload claim of a specified key as a list of string.
+    ///
+    /// A single string value is returned as a list which has one element.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key name of payload claim
+    pub fn string_array_claim(&self, key: &str) -> Option<Vec<&str>> {
+        match self.claims.get(key) {
+            Some(Value::Array(vals)) => {
+                let mut vec = Vec::with_capacity(vals.len());
+                for val in vals {
+                    match val {
+                        Value::String(val2) => {
+                            vec.push(val2.as_str());
+                        }
+                        _ => return None,
+                    }
+                }
+                Some(vec)
+            }
+            Some(Value::String(val)) => Some(vec![val]),
+            _ => None,
+        }
+    }
+
+    /// Return the realm roles of a Keycloak token (realm_access.roles).
+    ///
+    /// An empty list is returned if the claim is absent or not a expected shape.
+    /// A non-string role is ignored.
+    pub fn realm_roles(&self) -> Vec<&str> {
+        Self::roles(self.claims.get("realm_access"))
+    }
+
+    /// Return the client roles of a Keycloak token (resource_access.<client>.roles).
+    ///
+    /// An empty list is returned if the claim is absent or not a expected shape.
+    /// A non-string role is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - a client ID
+    pub fn client_roles(&self, client: &str) -> Vec<&str> {
+        match self.claims.get("resource_access") {
+            Some(Value::Object(vals)) => Self::roles(vals.get(client)),
+            _ => Vec::new(),
+        }
+    }
+
     /// Set a value for payload claim of a specified key.
     ///
     /// # Arguments
@@ -233,6 +420,33 @@ impl JwtPayload {
         .map_err(|err| JoseError::InvalidJwtFormat(err))
     }
 
+    /// Remove a payload claim of a specified key and return the removed value.
+    ///
+    /// The order of the other claims is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key name of payload claim
+    pub fn remove_claim(&mut self, key: &str) -> Option<Value> {
+        self.claims.shift_remove(key)
+    }
+
+    /// Remove payload claims except the specified keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed` - key names of payload claims to be retained
+    pub fn retain_claims(&mut self, allowed: &[&str]) {
+        self.claims.retain(|key, _| allowed.contains(&key.as_str()));
+    }
+
+    /// Remove all payload claims.
+    ///
+    /// The allocated capacity is kept, so the payload can be reused.
+    pub fn clear(&mut self) {
+        self.claims.clear();
+    }
+
     /// Return a value for payload claim of a specified key.
     ///
     /// # Arguments
@@ -242,11 +456,58 @@ impl JwtPayload {
         self.claims.get(key)
     }
 
+    /// Return a value for payload claim of a specified key as a unsigned 64bit integer.
+    ///
+    /// A integer value is kept without float coercion, so it is not lost precision
+    /// even if it is larger than 2^53.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key name of payload claim
+    pub fn claim_as_u64(&self, key: &str) -> Option<u64> {
+        match self.claims.get(key) {
+            Some(Value::Number(val)) => val.as_u64(),
+            _ => None,
+        }
+    }
+
     /// Return values for payload claims set
     pub fn claims_set(&self) -> &Map<String, Value> {
         &self.claims
     }
 
+    /// Test the logical content of this payload equals to the other one.
+    ///
+    /// Unlike `==`, a audience claim of a string is treated as equal to
+    /// a array that has only the string (aud: "x" and aud: ["x"]).
+    /// The order of claims is ignored by both of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - a payload to be compared
+    pub fn semantically_eq(&self, other: &JwtPayload) -> bool {
+        self.normalized_claims() == other.normalized_claims()
+    }
+
+    fn normalized_claims(&self) -> Map<String, Value> {
+        let mut claims = self.claims.clone();
+        if let Some(Value::String(val)) = claims.get("aud") {
+            let aud = Value::Array(vec![Value::String(val.clone())]);
+            claims.insert("aud".to_string(), aud);
+        }
+        claims
+    }
+
+    fn roles(access: Option<&Value>) -> Vec<&str> {
+        match access {
+            Some(Value::Object(vals)) => match vals.get("roles") {
+                Some(Value::Array(vals)) => vals.iter().filter_map(|val| val.as_str()).collect(),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
     fn check_claim(key: &str, value: &Value) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
             match key {
@@ -270,14 +531,20 @@ impl JwtPayload {
                     _ => bail!("The JWT {} payload claim must be a string or array.", key),
                 },
                 "exp" | "nbf" | "iat" => match &value {
-                    Value::Number(val) => match val.as_u64() {
+                    Value::Number(val) => match Self::to_system_time(val) {
                         Some(_) => {}
                         None => bail!(
-                            "The JWT {} payload claim must be a positive integer within 64bit.",
-                            key
+                            "The JWT {} payload claim must be a non-negative number up to {}: {}",
+                            key,
+                            MAX_NUMERIC_DATE,
+                            val
                         ),
                     },
-                    _ => bail!("The JWT {} header claim must be a string.", key),
+                    _ => bail!("The JWT {} payload claim must be a number.", key),
+                },
+                "act" => match &value {
+                    Value::Object(_) => {}
+                    _ => bail!("The JWT {} payload claim must be a object.", key),
                 },
                 _ => {}
             }
@@ -286,6 +553,32 @@ impl JwtPayload {
         })()
         .map_err(|err| JoseError::InvalidJwtFormat(err))
     }
+
+    /// Convert a NumericDate value to a system time.
+    ///
+    /// Both integer and fractional seconds are accepted. 0 means the epoch time.
+    /// A negative value or a value over 9999-12-31T23:59:59Z returns None.
+    fn to_system_time(value: &Number) -> Option<SystemTime> {
+        if let Some(val) = value.as_u64() {
+            if val <= MAX_NUMERIC_DATE {
+                return Some(SystemTime::UNIX_EPOCH + Duration::from_secs(val));
+            }
+        } else if let Some(val) = value.as_f64() {
+            if val.is_finite() && val >= 0.0 && val <= MAX_NUMERIC_DATE as f64 {
+                return Some(SystemTime::UNIX_EPOCH + Duration::from_secs_f64(val));
+            }
+        }
+        None
+    }
+
+    /// Convert a NumericDate value to whole seconds since the epoch time.
+    ///
+    /// A fractional part is truncated.
+    fn to_timestamp(value: &Number) -> Option<i64> {
+        let val = Self::to_system_time(value)?;
+        let secs = val.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+        Some(secs as i64)
+    }
 }
 
 impl AsRef<Map<String, Value>> for JwtPayload {
@@ -309,12 +602,16 @@ impl Display for JwtPayload {
 
 #[cfg(test)]
 mod tests {
-    use std::time::SystemTime;
+    use std::time::{Duration, SystemTime};
 
     use anyhow::Result;
+    use serde::{Deserialize, Serialize};
     use serde_json::json;
 
     use super::JwtPayload;
+    use crate::jws::{JwsHeader, HS256};
+    use crate::jwt;
+    use crate::{JoseError, Map, Value};
 
     #[test]
     fn test_new_payload() -> Result<()> {
@@ -343,4 +640,391 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_payload_remove_and_retain_claims() -> Result<()> {
+        let mut payload = JwtPayload::new();
+        payload.set_issuer("iss");
+        payload.set_subject("sub");
+        payload.set_expires_at(&SystemTime::UNIX_EPOCH);
+        payload.set_claim("email", Some(json!("user@example.com")))?;
+        payload.set_claim("role", Some(json!("admin")))?;
+
+        assert!(payload.expires_at().is_some());
+        assert_eq!(payload.remove_claim("exp"), Some(json!(0)));
+        assert_eq!(payload.expires_at(), None);
+        assert_eq!(payload.remove_claim("exp"), None);
+
+        payload.retain_claims(&["iss", "sub", "role"]);
+        assert_eq!(
+            payload.claims_set().keys().collect::<Vec<_>>(),
+            vec!["iss", "sub", "role"]
+        );
+        assert_eq!(payload.claim("email"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_semantically_eq() -> Result<()> {
+        let payload1 = JwtPayload::from_map(
+            json!({
+                "iss": "iss",
+                "aud": "aud0",
+                "nested": { "a": 1, "b": [true, null] }
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )?;
+        let payload2 = JwtPayload::from_map(
+            json!({
+                "nested": { "b": [true, null], "a": 1 },
+                "aud": ["aud0"],
+                "iss": "iss"
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )?;
+        assert_ne!(payload1, payload2);
+        assert!(payload1.semantically_eq(&payload2));
+        assert!(payload2.semantically_eq(&payload1));
+
+        let mut payload3 = payload2.clone();
+        payload3.set_audience(vec!["aud0", "aud1"]);
+        assert!(!payload1.semantically_eq(&payload3));
+
+        let mut payload4 = payload2.clone();
+        payload4.set_claim("nested", Some(json!({ "a": 2, "b": [true, null] })))?;
+        assert!(!payload1.semantically_eq(&payload4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_clear() -> Result<()> {
+        let mut payload = JwtPayload::new();
+        payload.set_audience(vec!["aud0", "aud1"]);
+        payload.set_expires_at(&SystemTime::UNIX_EPOCH);
+        payload.set_claim("email", Some(json!("user@example.com")))?;
+
+        payload.clear();
+        assert!(payload.claims_set().is_empty());
+        assert_eq!(payload.audience(), None);
+        assert_eq!(payload.expires_at(), None);
+
+        payload.set_subject("sub");
+        assert_eq!(payload.subject(), Some("sub"));
+        assert_eq!(payload.claims_set().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_actor() -> Result<()> {
+        let mut prior_actor = JwtPayload::new();
+        prior_actor.set_subject("service-b");
+
+        let mut actor = JwtPayload::new();
+        actor.set_subject("service-a");
+        actor.set_actor(&prior_actor);
+
+        let mut payload = JwtPayload::new();
+        payload.set_subject("user");
+        assert_eq!(payload.actor()?, None);
+        payload.set_actor(&actor);
+        assert_eq!(
+            payload.claim("act"),
+            Some(&json!({"sub": "service-a", "act": {"sub": "service-b"}}))
+        );
+
+        let payload = JwtPayload::from_map(payload.claims_set().clone())?;
+        let actor = payload.actor()?.unwrap();
+        assert_eq!(actor.subject(), Some("service-a"));
+        let prior_actor = actor.actor()?.unwrap();
+        assert_eq!(prior_actor.subject(), Some("service-b"));
+        assert_eq!(prior_actor.actor()?, None);
+
+        assert!(payload.clone().set_claim("act", Some(json!("x"))).is_err());
+
+        let mut payload = JwtPayload::new();
+        payload.set_claim("act", Some(json!({"sub": "service-a", "exp": "x"})))?;
+        assert!(payload.actor().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_audiences() -> Result<()> {
+        let payload = JwtPayload::new();
+        assert_eq!(payload.audiences(), Vec::<String>::new());
+
+        let mut map = Map::new();
+        map.insert("aud".to_string(), json!("aud0"));
+        let payload = JwtPayload::from_map(map)?;
+        assert_eq!(payload.audiences(), vec!["aud0".to_string()]);
+        assert_eq!(payload.claim("aud"), Some(&json!("aud0")));
+
+        let mut map = Map::new();
+        map.insert("aud".to_string(), json!([]));
+        let payload = JwtPayload::from_map(map)?;
+        assert_eq!(payload.audiences(), Vec::<String>::new());
+        assert_eq!(payload.claim("aud"), Some(&json!([])));
+
+        let mut map = Map::new();
+        map.insert("aud".to_string(), json!(["aud0", "aud1"]));
+        let payload = JwtPayload::from_map(map)?;
+        assert_eq!(
+            payload.audiences(),
+            vec!["aud0".to_string(), "aud1".to_string()]
+        );
+
+        let mut payload = JwtPayload::new();
+        payload.set_audience(vec!["aud0", "aud1"]);
+        payload.set_audience_single("aud2");
+        assert_eq!(payload.audiences(), vec!["aud2".to_string()]);
+        assert_eq!(payload.audience(), Some(vec!["aud2"]));
+        assert_eq!(payload.to_string(), r#"{"aud":"aud2"}"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_keycloak_roles() -> Result<()> {
+        let payload = JwtPayload::from_map(
+            json!({
+                "sub": "user",
+                "realm_access": {
+                    "roles": ["offline_access", "uma_authorization"]
+                },
+                "resource_access": {
+                    "account": {
+                        "roles": ["manage-account", "view-profile"]
+                    },
+                    "broken": {
+                        "roles": "admin"
+                    }
+                }
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )?;
+
+        assert_eq!(
+            payload.realm_roles(),
+            vec!["offline_access", "uma_authorization"]
+        );
+        assert_eq!(
+            payload.client_roles("account"),
+            vec!["manage-account", "view-profile"]
+        );
+        assert_eq!(payload.client_roles("other"), Vec::<&str>::new());
+        assert_eq!(payload.client_roles("broken"), Vec::<&str>::new());
+
+        let payload = JwtPayload::new();
+        assert_eq!(payload.realm_roles(), Vec::<&str>::new());
+        assert_eq!(payload.client_roles("account"), Vec::<&str>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_scopes_and_string_array() -> Result<()> {
+        let mut payload = JwtPayload::new();
+        assert_eq!(payload.scopes(), None);
+
+        payload.set_scopes(&["read", "write"]);
+        assert_eq!(payload.claim("scope"), Some(&json!("read write")));
+        assert_eq!(
+            payload.scopes(),
+            Some(vec!["read".to_string(), "write".to_string()])
+        );
+
+        payload.set_claim("scope", Some(json!(" read  write ")))?;
+        assert_eq!(
+            payload.scopes(),
+            Some(vec!["read".to_string(), "write".to_string()])
+        );
+
+        payload.set_scopes(&[]);
+        assert_eq!(payload.scopes(), Some(vec![]));
+
+        // A array scope is emitted by some issuers, so it must not fail the decoding.
+        payload.set_claim("scope", Some(json!(["read"])))?;
+        assert_eq!(payload.scopes(), None);
+        let mut map = Map::new();
+        map.insert("scope".to_string(), json!(["read", "write"]));
+        assert!(JwtPayload::from_map(map).is_ok());
+
+        payload.set_claim("roles", Some(json!(["admin", "user"])))?;
+        payload.set_claim("groups", Some(json!("staff")))?;
+        payload.set_claim("invalid", Some(json!(["admin", 1])))?;
+        assert_eq!(
+            payload.string_array_claim("roles"),
+            Some(vec!["admin", "user"])
+        );
+        assert_eq!(payload.string_array_claim("groups"), Some(vec!["staff"]));
+        assert_eq!(payload.string_array_claim("invalid"), None);
+        assert_eq!(payload.string_array_claim("missing"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_numeric_date() -> Result<()> {
+        let payload = JwtPayload::from_map(
+            json!({
+                "exp": 0,
+                "iat": 1.5,
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )?;
+        assert_eq!(payload.expires_at(), Some(SystemTime::UNIX_EPOCH));
+        assert_eq!(
+            payload.issued_at(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1500))
+        );
+        assert_eq!(payload.expires_at_timestamp(), Some(0));
+        assert_eq!(payload.issued_at_timestamp(), Some(1));
+        assert_eq!(payload.not_before_timestamp(), None);
+
+        let map: Map<String, Value> =
+            serde_json::from_str(r#"{"exp":1700000000,"nbf":1699990000,"iat":1699990000.75}"#)?;
+        let key = b"0123456789ABCDEF0123456789ABCDEF";
+        let jwt = jwt::encode_with_signer(
+            &JwtPayload::from_map(map)?,
+            &JwsHeader::new(),
+            &HS256.signer_from_bytes(key)?,
+        )?;
+        let (payload, _) = jwt::decode_with_verifier(&jwt, &HS256.verifier_from_bytes(key)?)?;
+        assert_eq!(payload.expires_at_timestamp(), Some(1700000000));
+        assert_eq!(payload.not_before_timestamp(), Some(1699990000));
+        assert_eq!(payload.issued_at_timestamp(), Some(1699990000));
+        assert_eq!(
+            payload.expires_at(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000))
+        );
+
+        for val in &[
+            json!(-1),
+            json!(-0.5),
+            json!(253402300800u64),
+            json!(u64::MAX),
+            json!(1e300),
+            json!("0"),
+        ] {
+            let mut map = Map::new();
+            map.insert("exp".to_string(), val.clone());
+            assert!(JwtPayload::from_map(map).is_err(), "{}", val);
+
+            let mut payload = JwtPayload::new();
+            assert!(payload.set_claim("exp", Some(val.clone())).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_wrong_typed_registered_claims() -> Result<()> {
+        let payload = JwtPayload::new();
+        assert_eq!(payload.issuer(), None);
+        assert_eq!(payload.subject(), None);
+        assert_eq!(payload.audience(), None);
+        assert_eq!(payload.expires_at(), None);
+        assert_eq!(payload.not_before(), None);
+        assert_eq!(payload.issued_at(), None);
+        assert_eq!(payload.jwt_id(), None);
+
+        for (key, val) in &[
+            ("iss", json!(1)),
+            ("sub", json!(["user"])),
+            ("aud", json!(1)),
+            ("aud", json!(["aud0", 1])),
+            ("exp", json!("tomorrow")),
+            ("nbf", json!(true)),
+            ("iat", json!({})),
+            ("jti", json!(null)),
+        ] {
+            let mut map = Map::new();
+            map.insert(key.to_string(), val.clone());
+            assert!(JwtPayload::from_map(map).is_err(), "{}: {}", key, val);
+
+            let mut payload = JwtPayload::new();
+            assert!(payload.set_claim(key, Some(val.clone())).is_err());
+            assert_eq!(payload.claim(key), None);
+        }
+
+        let input = format!(
+            "{}.{}.",
+            base64::encode_config(r#"{"alg":"none"}"#, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(r#"{"exp":"tomorrow"}"#, base64::URL_SAFE_NO_PAD)
+        );
+        assert!(matches!(
+            jwt::decode_unsecured(&input),
+            Err(JoseError::InvalidJwtFormat(_))
+        ));
+        assert!(jwt::is_expired(&input, &SystemTime::now()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_large_integer_claim() -> Result<()> {
+        let json = r#"{"id":9007199254740993,"exp":1600000000}"#;
+        let map: Map<String, Value> = serde_json::from_str(json)?;
+        let payload = JwtPayload::from_map(map)?;
+
+        assert_eq!(payload.claim_as_u64("id"), Some(9007199254740993));
+        assert_eq!(payload.claim_as_u64("exp"), Some(1600000000));
+        assert_eq!(payload.claim_as_u64("missing"), None);
+        assert_eq!(payload.to_string(), json);
+
+        let mut payload = JwtPayload::new();
+        payload.set_claim("id", Some(json!(9007199254740993u64)))?;
+        payload.set_claim("name", Some(json!("name")))?;
+        assert_eq!(payload.claim_as_u64("name"), None);
+
+        let map: Map<String, Value> = serde_json::from_str(&payload.to_string())?;
+        let payload = JwtPayload::from_map(map)?;
+        assert_eq!(payload.claim_as_u64("id"), Some(9007199254740993));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_from_serialize() -> Result<()> {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Claims {
+            iss: String,
+            exp: u64,
+            roles: Vec<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            nickname: Option<String>,
+        }
+
+        let src = Claims {
+            iss: "issuer".to_string(),
+            exp: 1600000000,
+            roles: vec!["admin".to_string()],
+            nickname: None,
+        };
+        let payload = JwtPayload::from_serialize(&src)?;
+        assert_eq!(payload.issuer(), Some("issuer"));
+        assert_eq!(payload.claim_as_u64("exp"), Some(1600000000));
+        assert_eq!(payload.string_array_claim("roles"), Some(vec!["admin"]));
+        assert_eq!(payload.claim("nickname"), None);
+
+        let dst: Claims = payload.deserialize_into()?;
+        assert_eq!(src, dst);
+
+        assert!(JwtPayload::from_serialize(&vec!["iss"]).is_err());
+        assert!(JwtPayload::from_serialize(&json!({ "iss": 1 })).is_err());
+        assert!(payload.deserialize_into::<Vec<String>>().is_err());
+
+        Ok(())
+    }
 }
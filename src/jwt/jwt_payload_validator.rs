@@ -1,11 +1,10 @@
 use std::convert::Into;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use anyhow::bail;
 use chrono::{DateTime, Utc};
 
 use crate::jwt::JwtPayload;
-use crate::{JoseError, Map, Value};
+use crate::{ClaimError, ClaimErrorKind, JoseError, Map, Value};
 
 /// Represents JWT payload validator.
 #[derive(Debug, Eq, PartialEq)]
@@ -13,6 +12,11 @@ pub struct JwtPayloadValidator {
     base_time: Option<SystemTime>,
     min_issued_time: Option<SystemTime>,
     max_issued_time: Option<SystemTime>,
+    leeway: Duration,
+    reject_future_iat: bool,
+    require_expiration: bool,
+    require_not_before: bool,
+    issuer_prefix: Option<String>,
     audience: Option<String>,
     claims: Map<String, Value>,
 }
@@ -24,6 +28,11 @@ impl JwtPayloadValidator {
             base_time: None,
             min_issued_time: None,
             max_issued_time: None,
+            leeway: Duration::from_secs(0),
+            reject_future_iat: false,
+            require_expiration: false,
+            require_not_before: false,
+            issuer_prefix: None,
             audience: None,
             claims: Map::new(),
         }
@@ -71,6 +80,74 @@ impl JwtPayloadValidator {
         self.max_issued_time.as_ref()
     }
 
+    /// Set a leeway for time related claims (exp, nbf, iat) validation.
+    ///
+    /// The leeway tolerates a clock skew between the issuer and the validator.
+    /// The default is zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `leeway` - a tolerance of clock skew
+    pub fn set_leeway(&mut self, leeway: Duration) {
+        self.leeway = leeway;
+    }
+
+    /// Return the leeway for time related claims (exp, nbf, iat) validation.
+    pub fn leeway(&self) -> Duration {
+        self.leeway
+    }
+
+    /// Set whether a issued at payload claim (iat) in the future is rejected.
+    ///
+    /// When it is true, a token whose iat is after the base time plus the leeway
+    /// is rejected. The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if a future iat is rejected
+    pub fn set_reject_future_iat(&mut self, value: bool) {
+        self.reject_future_iat = value;
+    }
+
+    /// Test a issued at payload claim (iat) in the future is rejected.
+    pub fn is_reject_future_iat(&self) -> bool {
+        self.reject_future_iat
+    }
+
+    /// Set whether a expires at payload claim (exp) is required.
+    ///
+    /// When it is true, a token without exp is rejected as a missing claim,
+    /// so all accepted tokens are time-bounded. The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if exp is required
+    pub fn set_require_expiration(&mut self, value: bool) {
+        self.require_expiration = value;
+    }
+
+    /// Test a expires at payload claim (exp) is required.
+    pub fn is_require_expiration(&self) -> bool {
+        self.require_expiration
+    }
+
+    /// Set whether a not before payload claim (nbf) is required.
+    ///
+    /// When it is true, a token without nbf is rejected as a missing claim.
+    /// The default is false.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if nbf is required
+    pub fn set_require_not_before(&mut self, value: bool) {
+        self.require_not_before = value;
+    }
+
+    /// Test a not before payload claim (nbf) is required.
+    pub fn is_require_not_before(&self) -> bool {
+        self.require_not_before
+    }
+
     /// Set a value for issuer payload claim (iss) validation.
     ///
     /// # Arguments
@@ -89,6 +166,29 @@ impl JwtPayloadValidator {
         }
     }
 
+    /// Set a URL prefix for issuer payload claim (iss) validation.
+    ///
+    /// A issuer passes the validation if it starts with the prefix.
+    /// A trailing slash is added to the prefix if it doesn't have one,
+    /// so "https://example.com" doesn't match "https://example.com.evil.com".
+    /// The prefix without the trailing slash is also accepted as a issuer.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a URL prefix of issuers
+    pub fn set_issuer_prefix(&mut self, value: impl Into<String>) {
+        let mut value: String = value.into();
+        if !value.ends_with('/') {
+            value.push('/');
+        }
+        self.issuer_prefix = Some(value);
+    }
+
+    /// Return the URL prefix for issuer payload claim (iss) validation.
+    pub fn issuer_prefix(&self) -> Option<&str> {
+        self.issuer_prefix.as_deref()
+    }
+
     /// Set a value for subject payload claim (sub) validation.
     ///
     /// # Arguments
@@ -174,44 +274,113 @@ impl JwtPayloadValidator {
             let min_issued_time = self.min_issued_time().unwrap_or(&SystemTime::UNIX_EPOCH);
             let max_issued_time = self.max_issued_time().unwrap_or(&now);
 
+            // A leeway that overflows the system time means no upper bound.
+            let latest_time = current_time.checked_add(self.leeway);
+
+            if self.require_not_before && payload.not_before().is_none() {
+                return Err(
+                    ClaimError::new(ClaimErrorKind::MissingClaim, "Key nbf is missing.").into(),
+                );
+            }
+
+            if self.require_expiration && payload.expires_at().is_none() {
+                return Err(
+                    ClaimError::new(ClaimErrorKind::MissingClaim, "Key exp is missing.").into(),
+                );
+            }
+
             if let Some(not_before) = payload.not_before() {
-                if &not_before > current_time {
-                    bail!(
-                        "The token is not yet valid: {}",
-                        DateTime::<Utc>::from(not_before)
-                    );
+                if matches!(latest_time, Some(val) if not_before > val) {
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::NotYetValid,
+                        format!(
+                            "The token is not yet valid: {}",
+                            DateTime::<Utc>::from(not_before)
+                        ),
+                    )
+                    .into());
                 }
             }
 
             if let Some(expires_at) = payload.expires_at() {
-                if &expires_at <= current_time {
-                    bail!(
-                        "The token has expired: {}",
-                        DateTime::<Utc>::from(expires_at)
-                    );
+                if matches!(expires_at.checked_add(self.leeway), Some(val) if val <= *current_time)
+                {
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::Expired,
+                        format!(
+                            "The token has expired: {}",
+                            DateTime::<Utc>::from(expires_at)
+                        ),
+                    )
+                    .into());
                 }
             }
 
             if let Some(issued_at) = payload.issued_at() {
+                if self.reject_future_iat && matches!(latest_time, Some(val) if issued_at > val) {
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::ClaimMismatch,
+                        format!(
+                            "The issued time is in the future: {}",
+                            DateTime::<Utc>::from(issued_at)
+                        ),
+                    )
+                    .into());
+                }
+
                 if &issued_at < min_issued_time {
-                    bail!(
-                        "The issued time is too old: {}",
-                        DateTime::<Utc>::from(issued_at)
-                    );
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::ClaimMismatch,
+                        format!(
+                            "The issued time is too old: {}",
+                            DateTime::<Utc>::from(issued_at)
+                        ),
+                    )
+                    .into());
                 }
 
                 if &issued_at > max_issued_time {
-                    bail!(
-                        "The issued time is too new: {}",
-                        DateTime::<Utc>::from(issued_at)
-                    );
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::ClaimMismatch,
+                        format!(
+                            "The issued time is too new: {}",
+                            DateTime::<Utc>::from(issued_at)
+                        ),
+                    )
+                    .into());
+                }
+            }
+
+            if let Some(prefix) = &self.issuer_prefix {
+                match payload.issuer() {
+                    Some(issuer)
+                        if issuer.starts_with(prefix.as_str())
+                            || issuer == &prefix[..prefix.len() - 1] => {}
+                    Some(issuer) => {
+                        return Err(ClaimError::new(
+                            ClaimErrorKind::InvalidIssuer,
+                            format!("Key iss is invalid: {}", issuer),
+                        )
+                        .into());
+                    }
+                    None => {
+                        return Err(ClaimError::new(
+                            ClaimErrorKind::MissingClaim,
+                            "Key iss is missing.",
+                        )
+                        .into());
+                    }
                 }
             }
 
             if let Some(audience) = &self.audience {
                 if let Some(audiences) = payload.audience() {
                     if !audiences.contains(&audience.as_str()) {
-                        bail!("Key aud is invalid: {}", audiences.join(", "));
+                        return Err(ClaimError::new(
+                            ClaimErrorKind::InvalidAudience,
+                            format!("Key aud is invalid: {}", audiences.join(", ")),
+                        )
+                        .into());
                     }
                 }
             }
@@ -219,10 +388,22 @@ impl JwtPayloadValidator {
             for (key, value1) in &self.claims {
                 if let Some(value2) = payload.claim(key) {
                     if value1 != value2 {
-                        bail!("Key {} is invalid: {}", key, value2);
+                        let kind = match key.as_str() {
+                            "iss" => ClaimErrorKind::InvalidIssuer,
+                            _ => ClaimErrorKind::ClaimMismatch,
+                        };
+                        return Err(ClaimError::new(
+                            kind,
+                            format!("Key {} is invalid: {}", key, value2),
+                        )
+                        .into());
                     }
                 } else {
-                    bail!("Key {} is missing.", key);
+                    return Err(ClaimError::new(
+                        ClaimErrorKind::MissingClaim,
+                        format!("Key {} is missing.", key),
+                    )
+                    .into());
                 }
             }
 
@@ -243,6 +424,7 @@ mod tests {
     use serde_json::json;
 
     use crate::jwt::{JwtPayload, JwtPayloadValidator};
+    use crate::{ClaimErrorKind, JoseError};
 
     #[test]
     fn test_jwt_payload_validate() -> Result<()> {
@@ -265,4 +447,192 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jwt_payload_validate_issuer_prefix() -> Result<()> {
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_issuer_prefix("https://example.com");
+        assert_eq!(validator.issuer_prefix(), Some("https://example.com/"));
+
+        let validate = |issuer: &str| {
+            let mut payload = JwtPayload::new();
+            payload.set_issuer(issuer);
+            validator.validate(&payload)
+        };
+
+        validate("https://example.com")?;
+        validate("https://example.com/")?;
+        validate("https://example.com/tenant1")?;
+        validate("https://example.com/realms/tenant2/")?;
+
+        for issuer in &[
+            "https://example.com.evil.com",
+            "https://example.com.evil.com/tenant1",
+            "https://example.community/",
+            "http://example.com/tenant1",
+        ] {
+            let err = validate(issuer).unwrap_err();
+            assert_eq!(err.claim_error_kind(), Some(ClaimErrorKind::InvalidIssuer));
+        }
+
+        let err = validator.validate(&JwtPayload::new()).unwrap_err();
+        assert_eq!(err.claim_error_kind(), Some(ClaimErrorKind::MissingClaim));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_error_kind() -> Result<()> {
+        let base_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_base_time(base_time);
+        validator.set_issuer("iss");
+        validator.set_audience("aud");
+        validator.set_claim("role", json!("admin"));
+
+        let mut payload = JwtPayload::new();
+        payload.set_issuer("iss");
+        payload.set_audience(vec!["aud"]);
+        payload.set_claim("role", Some(json!("admin")))?;
+        validator.validate(&payload)?;
+
+        let kind_of = |payload: &JwtPayload| match validator.validate(payload) {
+            Err(err) => err.claim_error_kind(),
+            Ok(_) => None,
+        };
+
+        let mut expired = payload.clone();
+        expired.set_expires_at(&base_time);
+        assert_eq!(kind_of(&expired), Some(ClaimErrorKind::Expired));
+
+        let mut not_yet_valid = payload.clone();
+        not_yet_valid.set_not_before(&(base_time + Duration::from_secs(1)));
+        assert_eq!(kind_of(&not_yet_valid), Some(ClaimErrorKind::NotYetValid));
+
+        let mut invalid_issuer = payload.clone();
+        invalid_issuer.set_issuer("other");
+        assert_eq!(
+            kind_of(&invalid_issuer),
+            Some(ClaimErrorKind::InvalidIssuer)
+        );
+
+        let mut invalid_audience = payload.clone();
+        invalid_audience.set_audience(vec!["other"]);
+        assert_eq!(
+            kind_of(&invalid_audience),
+            Some(ClaimErrorKind::InvalidAudience)
+        );
+
+        let mut mismatch = payload.clone();
+        mismatch.set_claim("role", Some(json!("user")))?;
+        assert_eq!(kind_of(&mismatch), Some(ClaimErrorKind::ClaimMismatch));
+
+        let mut missing = payload.clone();
+        missing.set_claim("role", None)?;
+        assert_eq!(kind_of(&missing), Some(ClaimErrorKind::MissingClaim));
+
+        match validator.validate(&expired) {
+            Err(err @ JoseError::InvalidClaim(_)) => {
+                assert!(format!("{}", err).starts_with("Invalid claim: The token has expired"))
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            JoseError::UnsupportedAlgorithm("HS1024".to_string()).claim_error_kind(),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_required_time_claims() -> Result<()> {
+        let base_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_base_time(base_time);
+        assert!(!validator.is_require_expiration());
+        assert!(!validator.is_require_not_before());
+        validator.validate(&JwtPayload::new())?;
+
+        validator.set_require_expiration(true);
+        let err = validator.validate(&JwtPayload::new()).unwrap_err();
+        assert_eq!(err.claim_error_kind(), Some(ClaimErrorKind::MissingClaim));
+        assert_eq!(format!("{}", err), "Invalid claim: Key exp is missing.");
+
+        let mut payload = JwtPayload::new();
+        payload.set_expires_at(&(base_time + Duration::from_secs(60)));
+        validator.validate(&payload)?;
+
+        validator.set_require_not_before(true);
+        let err = validator.validate(&payload).unwrap_err();
+        assert_eq!(err.claim_error_kind(), Some(ClaimErrorKind::MissingClaim));
+        assert_eq!(format!("{}", err), "Invalid claim: Key nbf is missing.");
+
+        payload.set_not_before(&base_time);
+        validator.validate(&payload)?;
+
+        let mut expired = payload.clone();
+        expired.set_expires_at(&base_time);
+        let err = validator.validate(&expired).unwrap_err();
+        assert_eq!(err.claim_error_kind(), Some(ClaimErrorKind::Expired));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_future_iat() -> Result<()> {
+        let base_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+
+        let mut payload = JwtPayload::new();
+        payload.set_issued_at(&(base_time + Duration::from_secs(30)));
+
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_base_time(base_time);
+        validator.set_max_issued_time(base_time + Duration::from_secs(3600));
+        assert!(!validator.is_reject_future_iat());
+        validator.validate(&payload)?;
+
+        validator.set_reject_future_iat(true);
+        assert!(validator.validate(&payload).is_err());
+
+        validator.set_leeway(Duration::from_secs(30));
+        validator.validate(&payload)?;
+
+        validator.set_leeway(Duration::from_secs(29));
+        assert!(validator.validate(&payload).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_leeway() -> Result<()> {
+        let base_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+
+        let mut payload = JwtPayload::new();
+        payload.set_expires_at(&(base_time - Duration::from_secs(10)));
+
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_base_time(base_time);
+        assert!(validator.validate(&payload).is_err());
+
+        validator.set_leeway(Duration::from_secs(11));
+        validator.validate(&payload)?;
+
+        let mut payload = JwtPayload::new();
+        payload.set_not_before(&(base_time + Duration::from_secs(10)));
+        validator.validate(&payload)?;
+
+        validator.set_leeway(Duration::from_secs(0));
+        assert!(validator.validate(&payload).is_err());
+
+        validator.set_leeway(Duration::from_secs(u64::MAX));
+        validator.validate(&payload)?;
+        let mut payload = JwtPayload::new();
+        payload.set_expires_at(&(base_time - Duration::from_secs(10)));
+        validator.validate(&payload)?;
+
+        Ok(())
+    }
 }
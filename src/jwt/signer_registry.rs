@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+
+use crate::jws::{JwsHeader, JwsSigner};
+use crate::jwt::{self, JwtPayload};
+use crate::JoseError;
+
+/// Represents a set of signers that are selected by a key (e.g. a tenant ID or a key ID).
+#[derive(Debug, Default)]
+pub struct SignerRegistry {
+    signers: BTreeMap<String, Box<dyn JwsSigner>>,
+}
+
+impl SignerRegistry {
+    /// Return a new empty SignerRegistry.
+    pub fn new() -> Self {
+        Self {
+            signers: BTreeMap::new(),
+        }
+    }
+
+    /// Register a signer for a key. A signer that has been registered for the key is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key for selecting the signer
+    /// * `signer` - a signer object
+    pub fn register(&mut self, key: impl Into<String>, signer: Box<dyn JwsSigner>) {
+        self.signers.insert(key.into(), signer);
+    }
+
+    /// Unregister the signer for a key and return it.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key for selecting the signer
+    pub fn unregister(&mut self, key: &str) -> Option<Box<dyn JwsSigner>> {
+        self.signers.remove(key)
+    }
+
+    /// Return the signer for a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a key for selecting the signer
+    pub fn get(&self, key: &str) -> Option<&dyn JwsSigner> {
+        self.signers.get(key).map(|val| val.as_ref())
+    }
+
+    /// Return the registered keys.
+    pub fn keys(&self) -> Vec<&str> {
+        self.signers.keys().map(|val| val.as_str()).collect()
+    }
+
+    /// Return the string repsentation of the JWT signed by the signer for a key.
+    ///
+    /// The kid header claim is set to the key ID of the signer,
+    /// or to the key if the signer has no key ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The payload data.
+    /// * `header` - The JWS heaser claims.
+    /// * `key` - a key for selecting the signer
+    pub fn encode(
+        &self,
+        payload: &JwtPayload,
+        header: &JwsHeader,
+        key: &str,
+    ) -> Result<String, JoseError> {
+        (|| -> anyhow::Result<String> {
+            let signer = match self.get(key) {
+                Some(val) => val,
+                None => bail!("A signer is not registered: {}", key),
+            };
+
+            let mut header = header.clone();
+            header.set_key_id(signer.key_id().unwrap_or(key));
+
+            let jwt = jwt::encode_with_signer(payload, &header, signer)?;
+            Ok(jwt)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::jws::{JwsHeader, HS256};
+    use crate::jwt::{self, JwtPayload, SignerRegistry};
+    use crate::util;
+
+    #[test]
+    fn test_signer_registry_encode() -> Result<()> {
+        let key_a = util::random_bytes(32);
+        let key_b = util::random_bytes(32);
+
+        let mut signer_b = HS256.signer_from_bytes(&key_b)?;
+        signer_b.set_key_id("kid-b");
+
+        let mut registry = SignerRegistry::new();
+        registry.register("tenant-a", Box::new(HS256.signer_from_bytes(&key_a)?));
+        registry.register("tenant-b", Box::new(signer_b));
+        assert_eq!(registry.keys(), vec!["tenant-a", "tenant-b"]);
+
+        let mut payload = JwtPayload::new();
+        payload.set_subject("user");
+        let mut header = JwsHeader::new();
+        header.set_token_type("JWT");
+
+        let verifier_a = HS256.verifier_from_bytes(&key_a)?;
+        let verifier_b = HS256.verifier_from_bytes(&key_b)?;
+
+        let jwt_a = registry.encode(&payload, &header, "tenant-a")?;
+        let (dst_payload, dst_header) = jwt::decode_with_verifier(&jwt_a, &verifier_a)?;
+        assert_eq!(dst_header.key_id(), Some("tenant-a"));
+        assert_eq!(dst_header.token_type(), Some("JWT"));
+        assert_eq!(dst_payload, payload);
+        assert!(jwt::decode_with_verifier(&jwt_a, &verifier_b).is_err());
+
+        let jwt_b = registry.encode(&payload, &header, "tenant-b")?;
+        let (_, dst_header) = jwt::decode_with_verifier(&jwt_b, &verifier_b)?;
+        assert_eq!(dst_header.key_id(), Some("kid-b"));
+        assert!(jwt::decode_with_verifier(&jwt_b, &verifier_a).is_err());
+
+        assert!(registry.encode(&payload, &header, "tenant-c").is_err());
+
+        assert!(registry.unregister("tenant-a").is_some());
+        assert!(registry.encode(&payload, &header, "tenant-a").is_err());
+
+        Ok(())
+    }
+}
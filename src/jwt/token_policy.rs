@@ -0,0 +1,192 @@
+use std::convert::Into;
+use std::time::{Duration, SystemTime};
+
+use anyhow::anyhow;
+
+use crate::jws::{JwsHeader, JwsSigner};
+use crate::jwt::jwt_payload::MAX_NUMERIC_DATE;
+use crate::jwt::{self, JwtPayload};
+use crate::JoseError;
+
+/// Represents a policy for issuing JWTs with a fixed lifetime.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TokenPolicy {
+    issuer: Option<String>,
+    audience: Vec<String>,
+    ttl: Duration,
+}
+
+impl TokenPolicy {
+    /// Return a new TokenPolicy.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` - a lifetime of issued tokens
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            issuer: None,
+            audience: Vec::new(),
+            ttl,
+        }
+    }
+
+    /// Set a value for issuer payload claim (iss) of issued tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a issuer
+    pub fn set_issuer(&mut self, value: impl Into<String>) {
+        self.issuer = Some(value.into());
+    }
+
+    /// Return the value for issuer payload claim (iss) of issued tokens.
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// Set values for audience payload claim (aud) of issued tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - a list of audiences
+    pub fn set_audience(&mut self, values: Vec<impl Into<String>>) {
+        self.audience = values.into_iter().map(|val| val.into()).collect();
+    }
+
+    /// Return values for audience payload claim (aud) of issued tokens.
+    pub fn audience(&self) -> Vec<&str> {
+        self.audience.iter().map(|val| val.as_str()).collect()
+    }
+
+    /// Set a lifetime of issued tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` - a lifetime of issued tokens
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+
+    /// Return the lifetime of issued tokens.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Return the string repsentation of a JWT issued by this policy.
+    ///
+    /// The iss, aud, iat and exp payload claims are set from this policy
+    /// and the current time. They override the same claims in `custom_claims`.
+    /// A error is returned if the exp is later than 9999-12-31T23:59:59Z.
+    ///
+    /// # Arguments
+    ///
+    /// * `custom_claims` - The payload data.
+    /// * `signer` - a signer object.
+    pub fn mint(
+        &self,
+        custom_claims: &JwtPayload,
+        signer: &dyn JwsSigner,
+    ) -> Result<String, JoseError> {
+        let now = SystemTime::now();
+        let max_expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(MAX_NUMERIC_DATE);
+        let expires_at = match now.checked_add(self.ttl) {
+            Some(val) if val <= max_expires_at => val,
+            _ => {
+                return Err(JoseError::InvalidJwtFormat(anyhow!(
+                    "The exp payload claim must be not after {}: now + {:?}",
+                    MAX_NUMERIC_DATE,
+                    self.ttl
+                )))
+            }
+        };
+
+        let mut payload = custom_claims.clone();
+        if let Some(val) = &self.issuer {
+            payload.set_issuer(val);
+        }
+        if !self.audience.is_empty() {
+            payload.set_audience(self.audience.clone());
+        }
+        payload.set_issued_at(&now);
+        payload.set_expires_at(&expires_at);
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("JWT");
+
+        jwt::encode_with_signer(&payload, &header, signer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use anyhow::Result;
+    use serde_json::json;
+
+    use crate::jws::HS256;
+    use crate::jwt::{self, JwtPayload, JwtPayloadValidator, TokenPolicy};
+    use crate::util;
+    use crate::JoseError;
+
+    #[test]
+    fn test_token_policy_mint() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+        let verifier = HS256.verifier_from_bytes(&private_key)?;
+
+        let mut policy = TokenPolicy::new(Duration::from_secs(300));
+        policy.set_issuer("https://issuer.example.com");
+        policy.set_audience(vec!["api"]);
+
+        let mut custom_claims = JwtPayload::new();
+        custom_claims.set_subject("user");
+        custom_claims.set_claim("role", Some(json!("admin")))?;
+
+        let jwt_string = policy.mint(&custom_claims, &signer)?;
+        let (payload, header) = jwt::decode_with_verifier(&jwt_string, &verifier)?;
+        assert_eq!(header.token_type(), Some("JWT"));
+        assert_eq!(payload.subject(), Some("user"));
+        assert_eq!(payload.claim("role"), Some(&json!("admin")));
+
+        let issued_at = payload.issued_at().unwrap();
+        assert_eq!(
+            payload.expires_at(),
+            Some(issued_at + Duration::from_secs(300))
+        );
+
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_issuer("https://issuer.example.com");
+        validator.set_audience("api");
+        validator.validate(&payload)?;
+
+        validator.set_base_time(SystemTime::now() + Duration::from_secs(301));
+        assert!(validator.validate(&payload).is_err());
+
+        validator.set_base_time(SystemTime::now());
+        validator.set_audience("other");
+        assert!(validator.validate(&payload).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_policy_mint_too_long_ttl() -> Result<()> {
+        let private_key = util::random_bytes(64);
+        let signer = HS256.signer_from_bytes(&private_key)?;
+
+        let mut policy = TokenPolicy::new(Duration::MAX);
+        assert!(matches!(
+            policy.mint(&JwtPayload::new(), &signer),
+            Err(JoseError::InvalidJwtFormat(_))
+        ));
+
+        policy.set_ttl(Duration::from_secs(10000 * 366 * 24 * 60 * 60));
+        assert!(matches!(
+            policy.mint(&JwtPayload::new(), &signer),
+            Err(JoseError::InvalidJwtFormat(_))
+        ));
+
+        Ok(())
+    }
+}
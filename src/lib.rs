@@ -11,8 +11,11 @@ pub mod util;
 mod jose_error;
 mod jose_header;
 
-pub use crate::jose_error::JoseError;
-pub use crate::jose_header::JoseHeader;
+pub use crate::jose_error::{
+    AlgorithmConfusionError, ClaimError, ClaimErrorKind, JoseError, KeyPolicyError,
+    SegmentCountError, SegmentDecodeError, TokenLengthError,
+};
+pub use crate::jose_header::{JoseHeader, JoseHeaderMerge};
 
 pub use serde_json::{Map, Number, Value};
 
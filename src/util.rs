@@ -5,9 +5,12 @@ pub mod oid;
 use anyhow::bail;
 use once_cell::sync::Lazy;
 use openssl::bn::BigNumRef;
+use openssl::memcmp;
 use openssl::rand;
 use regex::{self, bytes};
 
+use crate::{Map, SegmentCountError, SegmentDecodeError, Value};
+
 pub use crate::util::hash_algorithm::HashAlgorithm;
 
 pub use HashAlgorithm::Sha1 as SHA_1;
@@ -25,6 +28,13 @@ pub(crate) fn ceiling(len: usize, div: usize) -> usize {
     (len + (div - 1)) / div
 }
 
+/// Test two byte strings are equal in constant time.
+///
+/// Only the length is compared in variable time.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && memcmp::eq(a, b)
+}
+
 pub(crate) fn is_base64_url_safe_nopad(input: &str) -> bool {
     static RE_BASE64: Lazy<regex::Regex> = Lazy::new(|| {
         regex::Regex::new(
@@ -36,6 +46,114 @@ pub(crate) fn is_base64_url_safe_nopad(input: &str) -> bool {
     RE_BASE64.is_match(input)
 }
 
+/// The decoded header and the dot separated segments of a compact serialization.
+pub(crate) type CompactSegments<'a> = (Map<String, Value>, Vec<&'a [u8]>);
+
+/// Split a compact serialization into the dot separated segments, and decode the header segment.
+///
+/// Any malformed input is reported as a error instead of a panic. A error of the header
+/// segment is a `SegmentDecodeError`, and a error of the number of segments is a
+/// `SegmentCountError` that has the first expected count.
+///
+/// # Arguments
+///
+/// * `input` - a compact serialization.
+/// * `expected` - acceptable numbers of segments (3 for JWS and 5 for JWE).
+pub(crate) fn split_and_decode_segments<'a>(
+    input: &'a [u8],
+    expected: &[usize],
+) -> anyhow::Result<CompactSegments<'a>> {
+    let parts: Vec<&[u8]> = input.split(|b| *b == b'.').collect();
+    if !expected.contains(&parts.len()) {
+        return Err(SegmentCountError {
+            expected: expected.first().copied().unwrap_or(0),
+            found: parts.len(),
+        }
+        .into());
+    }
+
+    let header = decode_segment(&parts, 0)?;
+    if let Err(err) = std::str::from_utf8(&header) {
+        return Err(segment_error(
+            &parts,
+            0,
+            format!("It is not a valid UTF-8: {}", err),
+        ));
+    }
+    let header = match serde_json::from_slice::<Value>(&header) {
+        Ok(Value::Object(val)) => val,
+        Ok(_) => {
+            return Err(segment_error(
+                &parts,
+                0,
+                "It must be a JSON object.".to_string(),
+            ))
+        }
+        Err(err) => return Err(segment_error(&parts, 0, err.to_string())),
+    };
+
+    Ok((header, parts))
+}
+
+/// Check the length of each dot separated segment of a compact serialization
+/// before decoding.
+///
+/// # Arguments
+///
+/// * `input` - a compact serialization.
+/// * `max` - a maximum length of a segment in bytes.
+pub(crate) fn check_segment_len(input: &[u8], max: usize) -> anyhow::Result<()> {
+    let parts: Vec<&[u8]> = input.split(|b| *b == b'.').collect();
+    for (index, part) in parts.iter().enumerate() {
+        if part.len() > max {
+            return Err(segment_error(
+                &parts,
+                index,
+                format!("It must be {} bytes or less: {}", max, part.len()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Decode a base64url encoded segment of a compact serialization.
+///
+/// # Arguments
+///
+/// * `parts` - the segments of a compact serialization.
+/// * `index` - a index of the segment to be decoded.
+pub(crate) fn decode_segment(parts: &[&[u8]], index: usize) -> anyhow::Result<Vec<u8>> {
+    let part = match parts.get(index) {
+        Some(val) => val,
+        None => return Err(segment_error(parts, index, "It is not found.".to_string())),
+    };
+    if index == 0 && part.is_empty() {
+        return Err(segment_error(parts, index, "It is empty.".to_string()));
+    }
+
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+        .map_err(|err| segment_error(parts, index, err.to_string()))
+}
+
+fn segment_error(parts: &[&[u8]], index: usize, reason: String) -> anyhow::Error {
+    let name = match (parts.len(), index) {
+        (_, 0) => "header",
+        (3, 1) => "payload",
+        (3, 2) => "signature",
+        (5, 1) => "encrypted key",
+        (5, 2) => "initialization vector",
+        (5, 3) => "ciphertext",
+        (5, 4) => "authentication tag",
+        _ => "unknown",
+    };
+    SegmentDecodeError {
+        index,
+        name,
+        reason,
+    }
+    .into()
+}
+
 pub(crate) fn parse_pem(input: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
     static RE_PEM: Lazy<bytes::Regex> = Lazy::new(|| {
         bytes::Regex::new(concat!(
@@ -81,6 +199,84 @@ pub(crate) fn num_to_vec(num: &BigNumRef, len: usize) -> Vec<u8> {
     }
 }
 
+/// Return a JSON string that has no whitespace and the members of all objects
+/// are sorted by key, recursively.
+pub(crate) fn to_canonical_json(value: &Value) -> String {
+    // A Value is always serializable.
+    serde_json::to_string(&to_canonical_value(value)).unwrap()
+}
+
+/// Return a copy of the value whose members of all objects are sorted by key, recursively.
+pub(crate) fn to_canonical_value(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(to_canonical_map(map)),
+        Value::Array(vals) => Value::Array(vals.iter().map(to_canonical_value).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Return a copy of the map whose members are sorted by key, recursively.
+pub(crate) fn to_canonical_map(map: &Map<String, Value>) -> Map<String, Value> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    let mut sorted = Map::with_capacity(map.len());
+    for key in keys {
+        sorted.insert(key.clone(), to_canonical_value(&map[key]));
+    }
+    sorted
+}
+
+/// Return a DER encoded self-signed certificate of the key for tests.
+#[cfg(test)]
+pub(crate) fn self_signed_certificate(
+    private_key: &openssl::pkey::PKey<openssl::pkey::Private>,
+) -> anyhow::Result<Vec<u8>> {
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "test")?;
+    let name = name.build();
+
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(private_key)?;
+    builder.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+    builder.sign(private_key, MessageDigest::sha256())?;
+    Ok(builder.build().to_der()?)
+}
+
+/// Check a JWK factory accepts a JWK without or with the alg member
+/// and rejects a JWK with another alg member.
+#[cfg(test)]
+pub(crate) fn check_jwk_alg_member(
+    jwk: &crate::jwk::Jwk,
+    alg: &str,
+    from_jwk: impl Fn(&crate::jwk::Jwk) -> Result<(), crate::JoseError>,
+) -> anyhow::Result<()> {
+    assert_eq!(jwk.algorithm(), None);
+    from_jwk(jwk)?;
+
+    let mut jwk = jwk.clone();
+    jwk.set_algorithm(alg);
+    from_jwk(&jwk)?;
+
+    jwk.set_algorithm("XX256");
+    match from_jwk(&jwk) {
+        Err(crate::JoseError::InvalidKeyFormat(err)) => assert_eq!(
+            err.to_string(),
+            format!("A parameter alg must be {} but XX256", alg)
+        ),
+        res => panic!("{}: {:?}", alg, res),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::is_base64_url_safe_nopad;
@@ -331,6 +331,41 @@ impl<R: Read> DerReader<R> {
         }
     }
 
+    /// Return the big-endian magnitude of a integer with leading zero bytes stripped.
+    ///
+    /// This is the reverse of `DerBuilder::append_integer_from_be_slice`.
+    /// A negative integer is not supported.
+    pub fn to_unsigned_be_bytes(&self) -> Result<Vec<u8>, DerError> {
+        if let DerType::Integer = self.der_type {
+            if let Some(contents) = &self.contents {
+                if contents.is_empty() {
+                    return Err(DerError::InvalidLength(format!(
+                        "{} content length must be 1 or more.",
+                        self.der_type
+                    )));
+                }
+
+                if (contents[0] & 0b10000000) != 0 {
+                    return Err(DerError::InvalidContents(
+                        "Integer must not be negative.".to_string(),
+                    ));
+                }
+
+                match contents.iter().position(|b| *b != 0) {
+                    Some(pos) => Ok(contents[pos..].to_vec()),
+                    None => Ok(vec![0]),
+                }
+            } else {
+                unreachable!();
+            }
+        } else {
+            panic!(
+                "{} type is not supported to convert to unsigned bytes.",
+                self.der_type
+            );
+        }
+    }
+
     pub fn to_vec(&self) -> Result<Vec<u8>, DerError> {
         if let DerType::OctetString = self.der_type {
             if let Some(contents) = &self.contents {
@@ -616,6 +651,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_unsigned_be_bytes() -> Result<()> {
+        for (value, expected) in &[
+            (vec![0x80, 0x01], vec![0x80, 0x01]),
+            (vec![0x7F, 0x01], vec![0x7F, 0x01]),
+            (vec![0x00, 0x00, 0x7F], vec![0x7F]),
+            (vec![0x00], vec![0x00]),
+        ] {
+            let mut builder = DerBuilder::new();
+            builder.append_integer_from_be_slice(value, true);
+            let input = builder.build();
+
+            let mut parser = DerReader::from_bytes(&input);
+            assert!(matches!(parser.next()?, Some(DerType::Integer)));
+            assert_eq!(&parser.to_unsigned_be_bytes()?, expected);
+        }
+
+        let input = [0x02, 0x01, 0x80];
+        let mut parser = DerReader::from_bytes(&input);
+        assert!(matches!(parser.next()?, Some(DerType::Integer)));
+        assert!(parser.to_unsigned_be_bytes().is_err());
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<File> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");